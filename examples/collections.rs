@@ -0,0 +1,80 @@
+use std::{cell::Cell, rc::Rc};
+
+use luna::{
+    App, Button, Display, Element, FlexDir, For, Result, Style, Text, Theme, signals::SignalVec,
+};
+
+fn main() -> Result<()> {
+    let theme = Theme::default();
+    let items = SignalVec::new(vec!["Apples".to_string(), "Bread".to_string()]);
+    let added = Rc::new(Cell::new(0));
+
+    let push = {
+        let (items, added) = (items.clone(), added.clone());
+        move || {
+            added.set(added.get() + 1);
+            items.push(format!("Item #{}", added.get()));
+        }
+    };
+    let insert_front = {
+        let (items, added) = (items.clone(), added.clone());
+        move || {
+            added.set(added.get() + 1);
+            items.insert(0, format!("Item #{}", added.get()));
+        }
+    };
+    let remove_first = {
+        let items = items.clone();
+        move || {
+            if !items.is_empty() {
+                items.remove(0);
+            }
+        }
+    };
+    let last_to_front = {
+        let items = items.clone();
+        move || {
+            if items.len() > 1 {
+                items.move_item(items.len() - 1, 0);
+            }
+        }
+    };
+    let clear = {
+        let items = items.clone();
+        move || items.clear()
+    };
+
+    // For applies each change from the SignalVec to its children directly:
+    // only the rows that were added, removed or moved are touched
+    let mut list_style = Style {
+        display: Display::Flex,
+        ..Default::default()
+    };
+    list_style.flex.dir = FlexDir::Column;
+    list_style.flex.gap = 4.0;
+    let list = For::new(items, |item: &String| Text::new(item.clone())).with_style(list_style);
+
+    let ui = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Column)
+        .gap(8.0)
+        .padding(16.0)
+        .background_color(theme.color.surface)
+        .child(
+            Element::new()
+                .display(Display::Flex)
+                .gap(8.0)
+                .child(Button::label("Push").on_click(push))
+                .child(Button::label("Insert at top").on_click(insert_front))
+                .child(Button::label("Remove first").on_click(remove_first))
+                .child(Button::label("Last to top").on_click(last_to_front))
+                .child(Button::label("Clear").on_click(clear)),
+        )
+        .child(list);
+
+    App::new(ui)
+        .with_title("Collections Demo")
+        .with_size(640, 480)
+        .with_theme(theme)
+        .run()
+}
@@ -0,0 +1,272 @@
+use luna::{
+    App, Element, For, Result, Style, Text, Theme, Vec2, Widget,
+    layout::node::Node,
+    renderer::{Renderer, SlotRemap},
+    signals::SignalVec,
+    style::{Display, FlexDir},
+    widgets::BuildCtx,
+    windowing::{
+        DragPreview,
+        events::{EventCtx, EventKind, Phase},
+    },
+};
+
+// How far the pointer has to move with the button down before a card lifts
+const DRAG_THRESHOLD: f32 = 4.0;
+
+// What a card carries while it's being dragged
+struct CardDrag {
+    column: usize,
+    title: String,
+}
+
+#[derive(Clone)]
+struct Board {
+    columns: Vec<SignalVec<String>>,
+}
+
+impl Board {
+    // Takes the card out of its column and puts it before `before`, or at the
+    // end of the column if that's None
+    fn move_card(&self, drag: &CardDrag, to: usize, before: Option<&str>) {
+        let from = &self.columns[drag.column];
+        let Some(index) = from.with(|cards| cards.iter().position(|c| *c == drag.title)) else {
+            return;
+        };
+        let card = from.remove(index);
+
+        let target = &self.columns[to];
+        match before.and_then(|title| target.with(|cards| cards.iter().position(|c| c == title))) {
+            Some(index) => target.insert(index, card),
+            None => target.push(card),
+        }
+    }
+}
+
+// Accepts a card being dragged over the node handling `ev`
+fn accept_cards(ctx: &mut EventCtx, ev: &EventKind) {
+    if let EventKind::DragEnter { .. } | EventKind::DragOver { .. } = ev
+        && ctx.drag_payload::<CardDrag>().is_some()
+    {
+        ctx.accept_drag();
+    }
+}
+
+#[derive(Clone)]
+struct Card {
+    inner: Element,
+    title: String,
+    column: usize,
+    board: Board,
+    pressed_at: Option<Vec2>,
+}
+
+impl Card {
+    fn new(title: &str, column: usize, board: Board) -> Self {
+        Self {
+            inner: Element::new()
+                .key(title)
+                .padding(8.0)
+                .background_color(Theme::default().color.primary)
+                .child(Text::new(title.to_string())),
+            title: title.to_string(),
+            column,
+            board,
+            pressed_at: None,
+        }
+    }
+}
+
+impl Widget for Card {
+    fn build(&self, ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        self.inner.build(ctx)
+    }
+
+    fn style(&self) -> Style {
+        self.inner.style()
+    }
+
+    fn key(&self) -> Option<u64> {
+        Widget::key(&self.inner)
+    }
+
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.inner.update(&next.inner);
+            self.title = next.title.clone();
+            self.column = next.column;
+        }
+    }
+
+    fn release(&mut self, ren: &mut Renderer) {
+        self.inner.release(ren);
+    }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        self.inner.remap_slots(remap);
+    }
+
+    fn measure(
+        &self,
+        max_width: f32,
+        theme: &Theme,
+        font_system: &mut cosmic_text::FontSystem,
+    ) -> Vec2 {
+        self.inner.measure(max_width, theme, font_system)
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        self.inner.paint(node, ren, theme);
+    }
+
+    fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
+        if ctx.phase == Phase::Capture {
+            return;
+        }
+        accept_cards(ctx, ev);
+
+        match ev {
+            EventKind::PointerDown { pos, .. } => {
+                self.pressed_at = Some(*pos);
+                ctx.capture_pointer();
+            }
+            EventKind::PointerMove { pos, .. } => {
+                if let Some(start) = self.pressed_at
+                    && start.distance(*pos) > DRAG_THRESHOLD
+                {
+                    self.pressed_at = None;
+                    let preview = DragPreview::new(ctx.node_layout.size, [0.3, 0.46, 1.0, 0.8])
+                        .with_label(self.title.clone())
+                        .with_offset(start - ctx.node_layout.origin);
+                    ctx.start_drag(
+                        CardDrag {
+                            column: self.column,
+                            title: self.title.clone(),
+                        },
+                        preview,
+                    );
+                }
+            }
+            EventKind::PointerUp { .. } | EventKind::PointerCancel => self.pressed_at = None,
+            // Dropped on a card: slot in above it
+            EventKind::Drop { .. } => {
+                if let Some(drag) = ctx.drag_payload::<CardDrag>()
+                    && drag.title != self.title
+                {
+                    self.board.move_card(drag, self.column, Some(&self.title));
+                }
+                ctx.stop_propagation();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Column {
+    inner: Element,
+    index: usize,
+    board: Board,
+}
+
+impl Widget for Column {
+    fn build(&self, ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        self.inner.build(ctx)
+    }
+
+    fn style(&self) -> Style {
+        self.inner.style()
+    }
+
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.inner.update(&next.inner);
+        }
+    }
+
+    fn release(&mut self, ren: &mut Renderer) {
+        self.inner.release(ren);
+    }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        self.inner.remap_slots(remap);
+    }
+
+    fn measure(
+        &self,
+        max_width: f32,
+        theme: &Theme,
+        font_system: &mut cosmic_text::FontSystem,
+    ) -> Vec2 {
+        self.inner.measure(max_width, theme, font_system)
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        self.inner.paint(node, ren, theme);
+    }
+
+    // Cards dropped anywhere else in the column go to the bottom
+    fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
+        if ctx.phase == Phase::Capture {
+            return;
+        }
+        accept_cards(ctx, ev);
+
+        if let EventKind::Drop { .. } = ev
+            && let Some(drag) = ctx.drag_payload::<CardDrag>()
+        {
+            self.board.move_card(drag, self.index, None);
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let theme = Theme::default();
+    let board = Board {
+        columns: vec![
+            SignalVec::new(vec!["Sketch layout".into(), "Pick colours".into()]),
+            SignalVec::new(vec!["Write drag events".into()]),
+            SignalVec::new(vec!["Set up repo".into()]),
+        ],
+    };
+
+    let mut card_list = Style {
+        display: Display::Flex,
+        ..Default::default()
+    };
+    card_list.flex.dir = FlexDir::Column;
+    card_list.flex.gap = 8.0;
+
+    let mut ui = Element::new()
+        .display(Display::Flex)
+        .gap(16.0)
+        .padding(16.0);
+    for (index, name) in ["To do", "Doing", "Done"].into_iter().enumerate() {
+        let cards = For::new(board.columns[index].clone(), {
+            let board = board.clone();
+            move |title: &String| Card::new(title, index, board.clone())
+        })
+        .with_style(card_list);
+
+        ui = ui.child(Column {
+            inner: Element::new()
+                .display(Display::Flex)
+                .flex_direction(FlexDir::Column)
+                .gap(8.0)
+                .padding(8.0)
+                .width(180.0)
+                .height(400.0)
+                .background_color(theme.color.surface)
+                .child(Text::new(name.to_string()))
+                .child(cards),
+            index,
+            board: board.clone(),
+        });
+    }
+
+    App::new(ui)
+        .with_title("Drag and Drop Demo")
+        .with_size(640, 460)
+        .with_theme(theme)
+        .run()
+}
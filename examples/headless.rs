@@ -0,0 +1,40 @@
+use luna::{
+    Align, Button, Checkbox, Display, Element, FlexDir, Justify, Renderer, Result, Text, Theme,
+    layout::{Rect, node::Node},
+    vec2,
+    widgets::BuildCtx,
+};
+
+fn main() -> Result<()> {
+    let theme = Theme::default();
+    let (width, height) = (320, 200);
+
+    let ui = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Column)
+        .justify_content(Justify::Center)
+        .align_items(Align::Center)
+        .gap(12.0)
+        .background_color(theme.color.surface)
+        .child(Text::new("Rendered without a GPU"))
+        .child(Button::label("Button"))
+        .child(Checkbox::new_with_label("Checkbox"));
+
+    let mut renderer = Renderer::headless(width, height, 1.0);
+    let viewport = Rect::new(vec2(0.0, 0.0), vec2(width as f32, height as f32));
+    let mut root = Node::new(Box::new(ui), viewport, &mut BuildCtx);
+
+    renderer.begin_frame();
+    root.layout(width as f32, &theme, renderer.font_system());
+    root.collect(&mut renderer, &theme);
+    renderer.end_frame()?;
+
+    let out = "headless.png";
+    renderer
+        .read_pixels()
+        .expect("headless renderer keeps its pixels")
+        .save(out)?;
+    println!("wrote {out}");
+
+    Ok(())
+}
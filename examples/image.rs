@@ -1,20 +1,25 @@
 use luna::{
-    Align, App, Display, Element, FlexDir, Image, Justify, Result, widgets::image::ImageFit,
+    Align, App, Display, Element, FlexDir, Image, Justify, Result, signals::create_signal,
+    widgets::image::ImageFit,
 };
 
 fn main() -> Result<()> {
+    // Dropping an image file from the OS onto the picture swaps it in
+    let (source, set_source) = create_signal("assets/ferris.png".to_string());
+
     let content_column = Element::new()
         .display(Display::Flex)
         .flex_direction(FlexDir::Column)
         .align_items(Align::Center)
         .gap(16.0)
         .child(
-            Image::new("assets/ferris.png")
+            Image::new(source)
                 .fit(ImageFit::Fill)
                 .width(128.0)
-                .height(256.0),
+                .height(256.0)
+                .on_file_drop(move |path| set_source.set(path.display().to_string())),
         )
-        .child(luna::widgets::Text::new("Image Widget Demo"));
+        .child(luna::widgets::Text::new("Drop an image here"));
 
     let ui = Element::new()
         .display(Display::Flex)
@@ -0,0 +1,52 @@
+use std::time::Duration;
+
+use luna::{
+    App, Button, Element, Result, Text,
+    signals::{ResourceState, create_memo, create_resource, create_signal},
+    style::{Align, Display, FlexDir, Justify},
+};
+
+fn main() -> Result<()> {
+    let (page, set_page) = create_signal(1u32);
+
+    // Pretend to load each page over the network; odd pages past the first
+    // fail so the error state shows up too
+    let content = create_resource(page.clone(), |page| async move {
+        tokio::time::sleep(Duration::from_millis(600)).await;
+        if page > 1 && page % 2 == 1 {
+            anyhow::bail!("page {page} is unavailable");
+        }
+        Ok(format!("Contents of page {page}"))
+    });
+
+    let status = create_memo({
+        let content = content.clone();
+        move || match content.get() {
+            ResourceState::Loading => "Loading...".to_string(),
+            ResourceState::Ready(text) => text,
+            ResourceState::Error(err) => format!("Error: {err}"),
+        }
+    });
+
+    let ui = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Column)
+        .justify_content(Justify::Center)
+        .align_items(Align::Center)
+        .gap(16.0)
+        .child(Text::new(status))
+        .child(
+            Element::new()
+                .display(Display::Flex)
+                .gap(8.0)
+                .child(Button::label("Next page").on_click(move || {
+                    set_page.update(|page| *page += 1);
+                }))
+                .child(Button::label("Reload").on_click(move || content.refetch())),
+        );
+
+    App::new(ui)
+        .with_title("Resource Demo")
+        .with_size(400, 300)
+        .run()
+}
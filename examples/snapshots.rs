@@ -0,0 +1,173 @@
+use glam::vec4;
+use luna::{
+    Align, Button, Checkbox, Display, Element, FlexDir, Image, Justify, Result, Text, TextInput,
+    Theme, Vec4,
+    layout::Rect,
+    renderer::primatives::{CircleInstance, LineInstance},
+    snapshot::{Snapshot, SnapshotOutcome},
+    vec2,
+    widgets::{Canvas, Scrollable, Widget, image::ImageFit},
+};
+
+const GOLDEN_DIR: &str = "assets/snapshots";
+
+type Case = (&'static str, Box<dyn Widget>, (u32, u32));
+
+fn cases(theme: &Theme) -> Vec<Case> {
+    let buttons = |n: usize| -> Vec<Box<dyn Widget>> {
+        (1..=n)
+            .map(|i| Box::new(Button::label(&format!("Button {i}"))) as Box<dyn Widget>)
+            .collect()
+    };
+
+    let (red, green, blue) = (
+        vec4(0.9, 0.2, 0.2, 1.0),
+        vec4(0.2, 0.8, 0.3, 1.0),
+        vec4(0.2, 0.4, 0.9, 1.0),
+    );
+
+    vec![
+        ("button", Box::new(Button::label("Click me")), (160, 60)),
+        (
+            "checkbox",
+            Box::new(Checkbox::new_with_label("I agree")),
+            (160, 40),
+        ),
+        (
+            "text_input",
+            Box::new(TextInput::new("e.g. Jane Doe")),
+            (200, 60),
+        ),
+        (
+            "image",
+            Box::new(
+                Image::new("assets/ferris.png")
+                    .fit(ImageFit::Contain)
+                    .width(128.0)
+                    .height(96.0),
+            ),
+            (128, 96),
+        ),
+        (
+            "scrollable",
+            Box::new(Scrollable::new(
+                Element::new()
+                    .display(Display::Flex)
+                    .flex_direction(FlexDir::Column)
+                    .gap(8.0)
+                    .padding(12.0)
+                    .children(buttons(20)),
+            )),
+            (160, 200),
+        ),
+        (
+            "nested_clip",
+            Box::new(Canvas::new(move |ren| {
+                ren.push_scissor_rect(Rect::new(vec2(20.0, 20.0), vec2(120.0, 120.0)));
+                ren.draw_rect(vec2(0.0, 0.0), vec2(200.0, 200.0), red);
+
+                ren.push_scissor_rect(Rect::new(vec2(80.0, 80.0), vec2(100.0, 100.0)));
+                ren.draw_rect(vec2(0.0, 0.0), vec2(200.0, 200.0), blue);
+                ren.draw_text("clipped", vec2(70.0, 90.0), Vec4::ONE, 16.0);
+                ren.pop_scissor_rect();
+
+                ren.draw_rect(vec2(0.0, 60.0), vec2(200.0, 10.0), green);
+                ren.pop_scissor_rect();
+
+                ren.draw_rect(vec2(0.0, 170.0), vec2(200.0, 10.0), green);
+            })),
+            (200, 200),
+        ),
+        (
+            "z_order",
+            Box::new(Canvas::new({
+                let mut slots = None;
+                move |ren| {
+                    let (line, circle) =
+                        *slots.get_or_insert_with(|| (ren.alloc_line(), ren.alloc_circle()));
+
+                    // Raised above everything painted after it
+                    ren.push_z_index(1);
+                    ren.draw_rounded_rect(vec2(110.0, 20.0), vec2(60.0, 60.0), 8.0, blue);
+                    ren.pop_z_index();
+
+                    ren.update_line(
+                        line,
+                        LineInstance {
+                            a: [10.0, 50.0],
+                            b: [190.0, 50.0],
+                            color: green.to_array(),
+                            half_width: 4.0,
+                            ..Default::default()
+                        },
+                    );
+                    ren.draw_rect(vec2(40.0, 30.0), vec2(50.0, 40.0), red);
+                    ren.update_circle(
+                        circle,
+                        CircleInstance {
+                            center: [90.0, 70.0],
+                            radius: 20.0,
+                            color: [1.0, 1.0, 1.0, 1.0],
+                            ..Default::default()
+                        },
+                    );
+                    ren.draw_text("top", vec2(70.0, 60.0), vec4(0.0, 0.0, 0.0, 1.0), 16.0);
+                }
+            })),
+            (200, 100),
+        ),
+        (
+            "element_flex",
+            Box::new(
+                Element::new()
+                    .display(Display::Flex)
+                    .flex_direction(FlexDir::Row)
+                    .justify_content(Justify::SpaceBetween)
+                    .align_items(Align::Center)
+                    .padding(12.0)
+                    .background_color(theme.color.surface)
+                    .child(Text::new("Label"))
+                    .children(buttons(2)),
+            ),
+            (320, 60),
+        ),
+        (
+            "element_grid",
+            Box::new(
+                Element::new()
+                    .display(Display::Grid)
+                    .background_color(theme.color.surface)
+                    .padding(12.0)
+                    .gap(8.0)
+                    .grid_cols(3)
+                    .grid_row_height(32.0)
+                    .children(buttons(6)),
+            ),
+            (360, 110),
+        ),
+    ]
+}
+
+fn main() -> Result<()> {
+    let theme = Theme::default();
+    let mut failures = 0;
+
+    for (name, widget, (width, height)) in cases(&theme) {
+        let golden = format!("{GOLDEN_DIR}/{name}.png");
+        let mut snapshot = Snapshot::new(widget, theme, width, height).with_tolerance(2);
+
+        match snapshot.assert_matches(&golden) {
+            Ok(SnapshotOutcome::Matched) => println!("ok       {name}"),
+            Ok(SnapshotOutcome::Recorded) => println!("recorded {name}"),
+            Err(e) => {
+                println!("FAILED   {name}: {e}");
+                failures += 1;
+            }
+        }
+    }
+
+    if failures > 0 {
+        anyhow::bail!("{failures} snapshot(s) did not match");
+    }
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use luna::{
+    App, Button, Display, Element, FlexDir, Result, Text, Theme, Widget,
+    layout::node::Node,
+    renderer::Renderer,
+    signals::{ReadSignal, create_signal},
+    widgets::BuildCtx,
+};
+
+// Rebuilds its rows whenever `items` changes; rows are keyed by their text so
+// existing nodes survive insertions and removals.
+#[derive(Clone)]
+struct TodoList {
+    items: ReadSignal<Vec<String>>,
+}
+
+impl Widget for TodoList {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        let rows: Vec<Box<dyn Widget>> = self
+            .items
+            .get()
+            .into_iter()
+            .map(|item| {
+                Box::new(Element::new().key(&item).child(Text::new(item))) as Box<dyn Widget>
+            })
+            .collect();
+
+        vec![Box::new(
+            Element::new()
+                .display(Display::Flex)
+                .flex_direction(FlexDir::Column)
+                .gap(4.0)
+                .children(rows),
+        )]
+    }
+
+    fn measure(
+        &self,
+        _max_width: f32,
+        _theme: &Theme,
+        _font_system: &mut cosmic_text::FontSystem,
+    ) -> luna::Vec2 {
+        luna::Vec2::ZERO
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        for child in &mut node.children {
+            child.collect(ren, theme);
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let theme = Theme::default();
+    let (items, set_items) = create_signal(vec!["Write docs".to_string()]);
+
+    let add = {
+        let set_items = set_items.clone();
+        move || set_items.update(|items| items.push(format!("Todo #{}", items.len() + 1)))
+    };
+    let remove_first = move || {
+        set_items.update(|items| {
+            if !items.is_empty() {
+                items.remove(0);
+            }
+        })
+    };
+
+    let ui = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Column)
+        .gap(8.0)
+        .padding(16.0)
+        .background_color(theme.color.surface)
+        .child(
+            Element::new()
+                .display(Display::Flex)
+                .gap(8.0)
+                .child(Button::label("Add").on_click(add))
+                .child(Button::label("Remove first").on_click(remove_first)),
+        )
+        .child(TodoList { items });
+
+    App::new(ui)
+        .with_title("Todo Demo")
+        .with_size(320, 480)
+        .with_theme(theme)
+        .run()
+}
@@ -0,0 +1,253 @@
+mod style;
+mod tween;
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    ops::{Add, Mul, Sub},
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use glam::{Vec2, Vec3, Vec4};
+
+use crate::{scheduler, signals, timer};
+
+pub(crate) use style::StyleTransition;
+pub use tween::{Spring, create_spring, create_tween};
+
+// How far apart frames are requested while something is moving
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+// A value that can be eased between two endpoints and driven by a spring
+pub trait Animatable:
+    Copy + PartialEq + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self> + 'static
+{
+    // Used to tell when a spring has come to rest
+    fn magnitude(self) -> f32;
+
+    fn interpolate(self, to: Self, t: f32) -> Self {
+        self + (to - self) * t
+    }
+}
+
+impl Animatable for f32 {
+    fn magnitude(self) -> f32 {
+        self.abs()
+    }
+}
+
+macro_rules! impl_animatable_vec {
+    ($($ty:ty),*) => {
+        $(impl Animatable for $ty {
+            fn magnitude(self) -> f32 {
+                self.length()
+            }
+        })*
+    };
+}
+impl_animatable_vec!(Vec2, Vec3, Vec4);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    // Maps linear progress in 0..=1 onto the curve
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+// How long a change takes to play out and along which curve
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transition {
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+impl Transition {
+    pub const fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            easing: Easing::EaseInOut,
+        }
+    }
+
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    // Eased progress of a transition started at `start`
+    fn progress(&self, start: Instant, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        let elapsed = now.saturating_duration_since(start).as_secs_f32();
+        self.easing.apply(elapsed / self.duration.as_secs_f32())
+    }
+
+    fn is_done(&self, start: Instant, now: Instant) -> bool {
+        now.saturating_duration_since(start) >= self.duration
+    }
+}
+
+// A value that eases toward its target instead of jumping to it. Widgets
+// keep one in their own state for things like hover colors and read it
+// while painting.
+#[derive(Debug, Copy, Clone)]
+pub struct Animated<T> {
+    from: T,
+    to: T,
+    start: Option<Instant>,
+    transition: Transition,
+}
+
+impl<T: Animatable> Animated<T> {
+    pub fn new(value: T, transition: Transition) -> Self {
+        Self {
+            from: value,
+            to: value,
+            start: None,
+            transition,
+        }
+    }
+
+    pub fn target(&self) -> T {
+        self.to
+    }
+
+    // Starts easing from wherever the value currently is
+    pub fn set(&mut self, to: T) {
+        if to == self.to {
+            return;
+        }
+        let now = timer::now();
+        self.from = self.value_at(now);
+        self.to = to;
+        self.start = Some(now);
+        request_next_frame(now);
+    }
+
+    // Jumps straight to `value`
+    pub fn snap(&mut self, value: T) {
+        *self = Self::new(value, self.transition);
+    }
+
+    // The value for the frame being drawn. Requests another frame while it
+    // is still moving, so reading it from `paint` keeps the widget animating.
+    pub fn get(&self) -> T {
+        let now = frame_time();
+        if self.is_animating_at(now) {
+            request_next_frame(now);
+        }
+        self.value_at(now)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.is_animating_at(frame_time())
+    }
+
+    fn value_at(&self, now: Instant) -> T {
+        match self.start {
+            Some(start) => self
+                .from
+                .interpolate(self.to, self.transition.progress(start, now)),
+            None => self.to,
+        }
+    }
+
+    fn is_animating_at(&self, now: Instant) -> bool {
+        self.start
+            .is_some_and(|start| !self.transition.is_done(start, now))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct AnimationId(u64);
+
+static NEXT_ANIMATION_ID: AtomicU64 = AtomicU64::new(0);
+impl AnimationId {
+    fn new() -> Self {
+        Self(NEXT_ANIMATION_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// Advances an animation to the given frame time; returns whether it is
+// still running afterwards
+type Step = Rc<dyn Fn(Instant) -> bool>;
+
+thread_local! {
+    static FRAME_TIME: Cell<Option<Instant>> = const { Cell::new(None) };
+    static RUNNING: RefCell<HashMap<AnimationId, Step>> = RefCell::new(HashMap::new());
+    static STYLE_TRANSITIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// The time animations are sampled at for the current frame, so everything
+// drawn in one frame agrees on it. Falls back to the clock before the first
+// tick.
+pub fn frame_time() -> Instant {
+    FRAME_TIME.with(Cell::get).unwrap_or_else(timer::now)
+}
+
+// Moves the frame clock to `now` and steps every running tween and spring.
+// The event loop calls this on each wakeup; frames keep being requested
+// only for as long as something is moving. Returns whether anything is.
+pub fn tick(now: Instant) -> bool {
+    FRAME_TIME.with(|t| t.set(Some(now)));
+
+    let running: Vec<(AnimationId, Step)> = RUNNING.with(|r| {
+        r.borrow()
+            .iter()
+            .map(|(id, step)| (*id, step.clone()))
+            .collect()
+    });
+
+    // Finished ones are dropped before effects flush, so one that an effect
+    // restarts with a new target stays running
+    signals::batch(|| {
+        for (id, step) in running {
+            if !step(now) {
+                stop(id);
+            }
+        }
+    });
+
+    let active = RUNNING.with(|r| !r.borrow().is_empty()) || style_transitions_running();
+    if active {
+        request_next_frame(now);
+    }
+    active
+}
+
+fn start(id: AnimationId, step: Step) {
+    RUNNING.with(|r| r.borrow_mut().insert(id, step));
+    scheduler::request_frame();
+}
+
+fn stop(id: AnimationId) {
+    RUNNING.with(|r| r.borrow_mut().remove(&id));
+}
+
+fn request_next_frame(now: Instant) {
+    scheduler::request_frame_at(now + FRAME_INTERVAL);
+}
+
+// Whether any node is mid style transition, i.e. whether the tree needs
+// walking on this frame
+pub(crate) fn style_transitions_running() -> bool {
+    STYLE_TRANSITIONS.with(|n| n.get() > 0)
+}
@@ -0,0 +1,93 @@
+use std::time::Instant;
+
+use glam::{Vec2, Vec4};
+
+use super::{Animatable, STYLE_TRANSITIONS, Transition, request_next_frame};
+use crate::{style::Style, timer};
+
+// A node easing from the style it was showing to the one its widget now
+// reports. Only `background_color`, `width`, `height` and `padding`
+// animate; everything else switches immediately.
+pub(crate) struct StyleTransition {
+    from: Style,
+    to: Style,
+    start: Instant,
+    transition: Transition,
+}
+
+impl StyleTransition {
+    // `shown` is the style as last laid out and `size` the node's size then,
+    // which stands in for an unset width or height. Returns None if the
+    // target doesn't ask for a transition or nothing animatable changed.
+    pub(crate) fn new(shown: Style, target: Style, size: Vec2) -> Option<Self> {
+        let transition = target.transition?;
+
+        let mut from = shown;
+        if target.width.is_some() && from.width.is_none() {
+            from.width = Some(size.x);
+        }
+        if target.height.is_some() && from.height.is_none() {
+            from.height = Some(size.y);
+        }
+
+        let changed = from.background_color != target.background_color
+            || from.width != target.width
+            || from.height != target.height
+            || from.padding != target.padding;
+        if !changed || transition.duration.is_zero() {
+            return None;
+        }
+
+        let start = timer::now();
+        request_next_frame(start);
+        STYLE_TRANSITIONS.with(|n| n.set(n.get() + 1));
+        Some(Self {
+            from,
+            to: target,
+            start,
+            transition,
+        })
+    }
+
+    pub(crate) fn style_at(&self, now: Instant) -> Style {
+        let t = self.transition.progress(self.start, now);
+        let (from, to) = (self.from, self.to);
+
+        Style {
+            background_color: blend_color(from.background_color, to.background_color, t),
+            width: blend_len(from.width, to.width, t),
+            height: blend_len(from.height, to.height, t),
+            padding: from.padding.interpolate(to.padding, t),
+            ..to
+        }
+    }
+
+    pub(crate) fn is_done(&self, now: Instant) -> bool {
+        self.transition.is_done(self.start, now)
+    }
+}
+
+impl Drop for StyleTransition {
+    fn drop(&mut self) {
+        STYLE_TRANSITIONS.with(|n| n.set(n.get().saturating_sub(1)));
+    }
+}
+
+// Appearing or disappearing backgrounds fade through transparent
+fn blend_color(from: Option<Vec4>, to: Option<Vec4>, t: f32) -> Option<Vec4> {
+    let clear = |c: Vec4| c.truncate().extend(0.0);
+    match (from, to) {
+        (Some(a), Some(b)) => Some(a.interpolate(b, t)),
+        (None, Some(b)) => Some(clear(b).interpolate(b, t)),
+        (Some(a), None) if t < 1.0 => Some(a.interpolate(clear(a), t)),
+        _ => to,
+    }
+}
+
+// Going back to an automatic size has no end value to ease toward
+fn blend_len(from: Option<f32>, to: Option<f32>, t: f32) -> Option<f32> {
+    match (from, to) {
+        (Some(a), Some(b)) => Some(a.interpolate(b, t)),
+        _ => to,
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use super::{Animatable, Animated, AnimationId, Easing, Step, Transition, start, stop};
+use crate::signals::{ReadSignal, create_effect, create_signal, on_cleanup};
+
+// Returns a signal that follows `source`, easing to each new value over
+// `duration` rather than jumping to it
+pub fn create_tween<T: Animatable>(
+    source: ReadSignal<T>,
+    duration: Duration,
+    easing: Easing,
+) -> ReadSignal<T> {
+    let initial = source.get_untracked();
+    let (value, set_value) = create_signal(initial);
+    let tween = Rc::new(RefCell::new(Animated::new(
+        initial,
+        Transition::new(duration).with_easing(easing),
+    )));
+
+    let id = AnimationId::new();
+    let step: Step = Rc::new({
+        let tween = tween.clone();
+        move |now| {
+            let tween = tween.borrow();
+            set_value.set(tween.value_at(now));
+            tween.is_animating_at(now)
+        }
+    });
+
+    create_effect(move || {
+        let target = source.get();
+        if target != tween.borrow().target() {
+            tween.borrow_mut().set(target);
+            start(id, step.clone());
+        }
+    });
+    on_cleanup(move || stop(id));
+
+    value
+}
+
+// Parameters of a damped spring. The defaults settle quickly with little
+// overshoot.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Spring {
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+}
+
+impl Default for Spring {
+    fn default() -> Self {
+        Self {
+            stiffness: 170.0,
+            damping: 26.0,
+            mass: 1.0,
+        }
+    }
+}
+
+// Integration step; small enough to stay stable for stiff springs
+const SPRING_STEP: f32 = 1.0 / 240.0;
+// Longest gap simulated in one go, so a stalled frame doesn't fling it
+const MAX_SPRING_DT: f32 = 1.0 / 15.0;
+const REST_THRESHOLD: f32 = 1e-3;
+
+struct SpringState<T> {
+    position: T,
+    velocity: T,
+    target: T,
+    last: Option<Instant>,
+}
+
+impl<T: Animatable> SpringState<T> {
+    // Returns whether the spring is still moving
+    fn advance(&mut self, spring: Spring, now: Instant) -> bool {
+        let dt = match self.last {
+            Some(last) => now.saturating_duration_since(last).as_secs_f32(),
+            None => 0.0,
+        };
+        self.last = Some(now);
+
+        let mut remaining = dt.min(MAX_SPRING_DT);
+        while remaining > 0.0 {
+            let h = remaining.min(SPRING_STEP);
+            let force =
+                (self.target - self.position) * spring.stiffness - self.velocity * spring.damping;
+            self.velocity = self.velocity + force * (h / spring.mass);
+            self.position = self.position + self.velocity * h;
+            remaining -= h;
+        }
+
+        let settled = (self.target - self.position).magnitude() < REST_THRESHOLD
+            && self.velocity.magnitude() < REST_THRESHOLD;
+        if settled {
+            self.position = self.target;
+            self.velocity = self.velocity * 0.0;
+            self.last = None;
+        }
+        !settled
+    }
+}
+
+// Returns a signal that follows `source` with spring physics, carrying its
+// velocity over when the target changes mid-flight
+pub fn create_spring<T: Animatable>(source: ReadSignal<T>, spring: Spring) -> ReadSignal<T> {
+    let initial = source.get_untracked();
+    let (value, set_value) = create_signal(initial);
+    let state = Rc::new(RefCell::new(SpringState {
+        position: initial,
+        velocity: initial * 0.0,
+        target: initial,
+        last: None,
+    }));
+
+    let id = AnimationId::new();
+    let step: Step = Rc::new({
+        let state = state.clone();
+        move |now| {
+            let mut state = state.borrow_mut();
+            let moving = state.advance(spring, now);
+            set_value.set(state.position);
+            moving
+        }
+    });
+
+    create_effect(move || {
+        let target = source.get();
+        if target != state.borrow().target {
+            state.borrow_mut().target = target;
+            start(id, step.clone());
+        }
+    });
+    on_cleanup(move || stop(id));
+
+    value
+}
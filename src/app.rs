@@ -1,14 +1,15 @@
 use crate::{
-    Result,
+    Result, animation,
     layout::{Rect, node::Node},
     renderer::Renderer,
-    signals,
+    scheduler, signals,
     style::Theme,
+    timer,
     widgets::{BuildCtx, Widget},
-    windowing::events::FocusManager,
+    windowing::events::{ClickConfig, FocusManager},
 };
 use glam::{Vec2, vec2};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use winit::{
     event::{Event, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -33,6 +34,8 @@ pub struct App {
     root_widget: Box<dyn Widget>,
     window_config: WindowConfig,
     theme: Arc<Theme>,
+    debug_damage: bool,
+    click_config: ClickConfig,
 }
 
 impl App {
@@ -41,6 +44,8 @@ impl App {
             root_widget: Box::new(root_widget),
             window_config: WindowConfig::default(),
             theme: Arc::new(Theme::default()),
+            debug_damage: false,
+            click_config: ClickConfig::default(),
         }
     }
 
@@ -54,17 +59,29 @@ impl App {
         self
     }
 
+    // How fast and how close together presses must be to form a double click
+    pub fn with_click_config(mut self, config: ClickConfig) -> Self {
+        self.click_config = config;
+        self
+    }
+
     pub fn with_theme(mut self, theme: Theme) -> Self {
         self.theme = Arc::new(theme);
         self
     }
 
+    // Tints the regions repainted each frame
+    pub fn with_debug_damage(mut self, enabled: bool) -> Self {
+        self.debug_damage = enabled;
+        self
+    }
+
     pub fn run(self) -> Result<()> {
         crate::init_logging();
         log::info!("Starting {}...", &self.window_config.title);
 
         let event_loop = EventLoop::new()?;
-        event_loop.set_control_flow(ControlFlow::Poll);
+        event_loop.set_control_flow(ControlFlow::Wait);
 
         let window = Arc::new(
             WindowBuilder::new()
@@ -76,10 +93,24 @@ impl App {
         let window_clone = window.clone();
         let mut renderer =
             pollster::block_on(Renderer::new(&window_clone, window.scale_factor() as f32))?;
+        renderer.set_debug_damage(self.debug_damage);
 
         let (dirty_tx, dirty_rx) = std::sync::mpsc::channel();
         signals::init_reactivity(dirty_tx);
 
+        // Work finishing on other threads (resource fetches, `SignalSender`
+        // updates) wakes the event loop so it is applied on this thread
+        let proxy = Mutex::new(event_loop.create_proxy());
+        signals::init_waker(move || {
+            if let Ok(proxy) = proxy.lock() {
+                let _ = proxy.send_event(());
+            }
+        });
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?;
+        signals::init_async(runtime.handle().clone());
+
         let initial_size = window.inner_size();
         let mut root = Node::new(
             self.root_widget,
@@ -92,8 +123,10 @@ impl App {
 
         let mut win_width = initial_size.width as f32;
         let mut focus_mgr = FocusManager::default();
+        focus_mgr.set_click_config(self.click_config);
 
         let _ = event_loop.run(move |event, elwt| {
+            let _runtime = &runtime;
             for dirty_node_id in dirty_rx.try_iter() {
                 root.mark_dirty_by_id(dirty_node_id);
             }
@@ -105,12 +138,20 @@ impl App {
                 } if *window_id == window.id() => {
                     renderer.begin_frame();
 
+                    root.rebuild(&mut BuildCtx);
                     root.layout(win_width, &self.theme, renderer.font_system());
                     root.collect(&mut renderer, &self.theme);
+                    if let Some(drag) = focus_mgr.drag.as_ref().filter(|drag| drag.active) {
+                        drag.paint_preview(&mut renderer, &self.theme);
+                    }
 
                     if let Err(e) = renderer.end_frame() {
                         log::error!("frame error: {e}");
                     }
+                    root.clear_paint_dirty();
+                    if renderer.compaction_due() {
+                        root.remap_slots(&renderer.compact());
+                    }
                 }
 
                 Event::WindowEvent { window_id, event } if *window_id == window.id() => {
@@ -132,8 +173,38 @@ impl App {
                         _ => {}
                     }
                 }
+                // Marked nodes are picked up when AboutToWait follows
+                Event::UserEvent(()) => {
+                    signals::deliver_resources();
+                    signals::deliver_signal_updates();
+                }
                 Event::AboutToWait => {
-                    window.request_redraw();
+                    scheduler::record_wakeup();
+                    root.flush_dropped_files(&mut focus_mgr);
+
+                    let now = timer::now();
+                    timer::run_due();
+                    animation::tick(now);
+                    if animation::style_transitions_running() {
+                        root.advance_transitions(now);
+                    }
+                    for dirty_node_id in dirty_rx.try_iter() {
+                        root.mark_dirty_by_id(dirty_node_id);
+                    }
+
+                    // Only draw when something changed or a widget asked for
+                    // a frame, then sleep until the next timer (if any)
+                    if scheduler::take_due(now) || root.needs_paint() {
+                        window.request_redraw();
+                    }
+                    let deadline = match (scheduler::next_deadline(), timer::next_deadline()) {
+                        (Some(a), Some(b)) => Some(a.min(b)),
+                        (a, b) => a.or(b),
+                    };
+                    elwt.set_control_flow(match deadline {
+                        Some(at) => ControlFlow::WaitUntil(at),
+                        None => ControlFlow::Wait,
+                    });
                 }
                 _ => {}
             }
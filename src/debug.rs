@@ -8,6 +8,13 @@ macro_rules! dbg_ev {
             EventKind::PointerUp { .. } => "PointerUp",
             EventKind::PointerMove { .. } => "PointerMove",
             EventKind::PointerLeave => "PointerLeave",
+            EventKind::PointerCancel => "PointerCancel",
+            EventKind::DragEnter { .. } => "DragEnter",
+            EventKind::DragOver { .. } => "DragOver",
+            EventKind::DragLeave => "DragLeave",
+            EventKind::Drop { .. } => "Drop",
+            EventKind::FileHover { .. } => "FileHover",
+            EventKind::FileDrop { .. } => "FileDrop",
             EventKind::KeyDown { .. } => "KeyDown",
             EventKind::KeyUp { .. } => "KeyUp",
             _ => "…",
@@ -15,7 +22,7 @@ macro_rules! dbg_ev {
     }};
 }
 
-pub fn widget_type(_w: &Box<dyn Widget>) -> &'static str {
+pub fn widget_type(_w: &dyn Widget) -> &'static str {
     std::any::type_name::<Box<dyn Widget>>()
         .rsplit_once("::")
         .map(|(_, t)| t)
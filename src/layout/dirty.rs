@@ -3,4 +3,6 @@ pub struct Dirty {
     pub self_dirty: bool,  // needs a new measurement (size)
     pub child_dirty: bool, // some descendant is self_dirty
     pub paint_dirty: bool, // visual representation changed
+    pub build_dirty: bool, // children must be rebuilt from Widget::build
+    pub paint_again: bool, // asked for a frame while painting, so it changes over time
 }
@@ -1,17 +1,27 @@
+use std::any::TypeId;
+use std::collections::HashMap;
 use std::mem;
+use std::path::PathBuf;
+use std::time::Instant;
 
 use cosmic_text::FontSystem;
 use glam::{Vec2, vec2};
-use winit::event::{ElementState, Ime, MouseScrollDelta, WindowEvent};
+use winit::event::{ElementState, Ime, MouseButton, MouseScrollDelta, WindowEvent};
+use winit::keyboard::{Key, ModifiersState, NamedKey};
 
-use crate::signals::{NodeId, ScopedNodeContext};
-use crate::style::Theme;
+use crate::animation::{self, StyleTransition};
+use crate::scheduler;
+use crate::signals::{NodeId, Scope, ScopedNodeContext};
+use crate::style::{PointerEvents, Theme};
 use crate::{
     layout::{Dirty, Rect},
-    renderer::Renderer,
+    renderer::{Renderer, SlotRemap},
     style::Display,
-    widgets::{BuildCtx, Widget},
-    windowing::events::{EventCtx, EventKind, FocusManager, Phase},
+    widgets::{BuildCtx, ChildEdit, Widget},
+    windowing::{
+        self,
+        events::{EventCtx, EventKind, FocusManager, Phase},
+    },
 };
 
 #[derive(Copy, Clone)]
@@ -31,16 +41,38 @@ pub struct Node {
     pub cached_size: Vec2,
     dirty: Dirty,
     hover_path: Vec<usize>,
+    // Last pointer position seen, which button and wheel events happen at
+    pointer_pos: Vec2,
+    // Files the OS is dragging over the window and the path told about them
+    hovered_files: Vec<PathBuf>,
+    file_hover_path: Option<Vec<usize>>,
+    // Dropped files arrive one event each; they're sent as a single drop
+    dropped_files: Vec<PathBuf>,
+    // Children dropped by reconciliation, released on the next paint
+    removed: Vec<Node>,
+
+    // Owns the effects, memos and cleanups created in the current build;
+    // replaced on every rebuild and disposed with the node
+    build_scope: Scope,
+    scope: Scope,
+
+    // Set while the widget's style is easing toward a new one
+    transition: Option<StyleTransition>,
 }
 
 impl Node {
     pub fn new(widget: Box<dyn Widget>, layout: Rect, ctx: &mut BuildCtx) -> Self {
         let id = NodeId::new();
-        let kids = widget
-            .build(ctx)
-            .into_iter()
-            .map(|w| Node::new(w, layout, ctx))
-            .collect();
+        let scope = Scope::for_node(id);
+        let build_scope = scope.run(Scope::new);
+        let kids = {
+            let _owner = build_scope.enter();
+            let _guard = ScopedNodeContext::new(id);
+            widget.build(ctx)
+        }
+        .into_iter()
+        .map(|w| Node::new(w, layout, ctx))
+        .collect();
 
         Self {
             id,
@@ -52,23 +84,188 @@ impl Node {
                 self_dirty: true,
                 child_dirty: true,
                 paint_dirty: true,
+                build_dirty: false,
+                paint_again: false,
             },
             hover_path: Vec::new(),
+            pointer_pos: Vec2::ZERO,
+            hovered_files: Vec::new(),
+            file_hover_path: None,
+            dropped_files: Vec::new(),
+            removed: Vec::new(),
+            build_scope,
+            scope,
+            transition: None,
+        }
+    }
+
+    // Re-runs `Widget::build` for every node a signal marked dirty and
+    // reconciles the result against the existing children.
+    pub fn rebuild(&mut self, ctx: &mut BuildCtx) {
+        if self.dirty.build_dirty {
+            self.reconcile(ctx);
+            return;
+        }
+        if self.dirty.child_dirty {
+            for child in &mut self.children {
+                child.rebuild(ctx);
+            }
         }
     }
 
+    // Matches freshly built widgets to existing child nodes by key and type
+    // (unkeyed widgets by type, in order). Matched nodes keep their NodeId,
+    // widget state and renderer slots and are only reconciled further if
+    // their widget changed; everything else is created or dropped.
+    fn reconcile(&mut self, ctx: &mut BuildCtx) {
+        self.dirty.build_dirty = false;
+
+        let edits = {
+            let _owner = self.build_scope.enter();
+            let _guard = ScopedNodeContext::new(self.id);
+            self.widget.child_edits(ctx)
+        };
+        if let Some(edits) = edits {
+            self.apply_edits(edits, ctx);
+            return;
+        }
+
+        self.build_scope = self.scope.run(Scope::new);
+        let next = {
+            let _owner = self.build_scope.enter();
+            let _guard = ScopedNodeContext::new(self.id);
+            self.widget.build(ctx)
+        };
+
+        let mut old: Vec<Option<Node>> = mem::take(&mut self.children)
+            .into_iter()
+            .map(Some)
+            .collect();
+        let mut keyed: HashMap<(TypeId, u64), usize> = old
+            .iter()
+            .enumerate()
+            .filter_map(|(i, n)| {
+                let widget = &n.as_ref()?.widget;
+                Some(((widget.as_any().type_id(), widget.key()?), i))
+            })
+            .collect();
+        let mut unkeyed_cursor = 0;
+
+        for widget in next {
+            let type_id = widget.as_any().type_id();
+            let found = match widget.key() {
+                Some(key) => keyed.remove(&(type_id, key)),
+                None => {
+                    let found = (unkeyed_cursor..old.len()).find(|&i| {
+                        old[i].as_ref().is_some_and(|n| {
+                            n.widget.key().is_none() && n.widget.as_any().type_id() == type_id
+                        })
+                    });
+                    if let Some(i) = found {
+                        unkeyed_cursor = i + 1;
+                    }
+                    found
+                }
+            };
+
+            match found.and_then(|i| old[i].take()) {
+                Some(mut node) => {
+                    node.refresh(widget.as_ref(), ctx);
+                    self.children.push(node);
+                }
+                None => self.children.push(Node::new(widget, self.layout_rect, ctx)),
+            }
+        }
+
+        for node in old.into_iter().flatten() {
+            self.retire(node);
+        }
+
+        self.mark_dirty();
+        self.mark_child_dirty();
+    }
+
+    // Brings a kept child up to date with its freshly built widget. Only a
+    // changed widget is rebuilt and laid out again; an unchanged one just
+    // catches up on whatever signals marked dirty below it.
+    fn refresh(&mut self, next: &dyn Widget, ctx: &mut BuildCtx) {
+        if self.widget.same_config(next) {
+            self.rebuild(ctx);
+            return;
+        }
+        self.update_widget(next);
+        self.reconcile(ctx);
+        self.mark_dirty();
+    }
+
+    // Hands the widget its next configuration, easing into the new style if
+    // it declares a transition
+    fn update_widget(&mut self, next: &dyn Widget) {
+        let shown = self.style();
+        let previous = self.widget.style();
+        self.widget.update(next);
+
+        let target = self.widget.style();
+        if target != previous {
+            self.transition = StyleTransition::new(shown, target, self.cached_size);
+        }
+    }
+
+    // Patches the children in place; only inserted or replaced widgets get
+    // new nodes
+    fn apply_edits(&mut self, edits: Vec<ChildEdit>, ctx: &mut BuildCtx) {
+        if edits.is_empty() {
+            return;
+        }
+
+        for edit in edits {
+            let len = self.children.len();
+            match edit {
+                ChildEdit::Insert(index, widget) => {
+                    let node = Node::new(widget, self.layout_rect, ctx);
+                    self.children.insert(index.min(len), node);
+                }
+                ChildEdit::Remove(index) if index < len => {
+                    let node = self.children.remove(index);
+                    self.retire(node);
+                }
+                ChildEdit::Move { from, to } if from < len => {
+                    let node = self.children.remove(from);
+                    self.children.insert(to.min(len - 1), node);
+                }
+                ChildEdit::Update(index, widget) if index < len => {
+                    let node = &mut self.children[index];
+                    if node.widget.as_any().type_id() == widget.as_any().type_id() {
+                        node.refresh(widget.as_ref(), ctx);
+                    } else {
+                        let node = Node::new(widget, self.layout_rect, ctx);
+                        let old = mem::replace(&mut self.children[index], node);
+                        self.retire(old);
+                    }
+                }
+                ChildEdit::Clear => {
+                    for node in mem::take(&mut self.children) {
+                        self.retire(node);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.mark_dirty();
+        self.mark_child_dirty();
+    }
+
     pub fn layout(&mut self, max_width: f32, theme: &Theme, font_system: &mut FontSystem) -> Vec2 {
         if !self.dirty.self_dirty && !self.dirty.child_dirty {
             return self.cached_size;
         }
 
         self.dirty.paint_dirty = true;
-        let style = self.widget.style();
+        let style = self.style();
         let padding_size = style.padding_total();
 
-        let content_size: Vec2;
-
-        if !self.children.is_empty() {
+        let content_size = if !self.children.is_empty() {
             let child_max_width = if let Some(w) = style.width {
                 w - padding_size.x
             } else {
@@ -81,7 +278,7 @@ impl Node {
             let avail = vec2(max_width, self.layout_rect.size.y) - padding_size;
             let content_origin = self.layout_rect.origin + style.padding_tl();
 
-            content_size = match style.display {
+            let content_size = match style.display {
                 Display::Flex => crate::layout::flexbox::compute(
                     style.flex,
                     &mut self.children,
@@ -109,11 +306,20 @@ impl Node {
                     vec2(max_x, y)
                 }
             };
+
+            // Children that were moved above still have their own children
+            // placed relative to the old origin
+            for child in &mut self.children {
+                if child.dirty.child_dirty {
+                    child.layout(child_max_width, theme, font_system);
+                }
+            }
+
+            content_size
         } else {
-            content_size = self
-                .widget
-                .measure(max_width - padding_size.x, theme, font_system);
-        }
+            self.widget
+                .measure(max_width - padding_size.x, theme, font_system)
+        };
 
         let mut final_size = content_size + padding_size;
 
@@ -132,45 +338,131 @@ impl Node {
     }
 
     pub fn collect(&mut self, ren: &mut Renderer, theme: &Theme) {
+        let _owner = self.scope.enter();
         let _guard = ScopedNodeContext::new(self.id);
 
+        for mut node in mem::take(&mut self.removed) {
+            node.release(ren);
+        }
+
+        // Nothing in the subtree changed, so it would draw what it drew last
+        // frame. Nodes that asked for another frame while painting look
+        // different over time and always paint.
+        if !self.dirty.paint_again && !self.needs_paint() && ren.replay(self.id) {
+            return;
+        }
+
         let mut widget = mem::replace(
             &mut self.widget,
             Box::new(crate::widgets::Element::default()),
         );
 
+        let z_index = widget.style().z_index;
+        if let Some(z) = z_index {
+            ren.push_z_index(z);
+        }
+        let requests = scheduler::frame_requests();
+        ren.begin_paint(self.id);
         widget.paint(self, ren, theme);
+        ren.end_paint();
+        self.dirty.paint_again = scheduler::frame_requests() != requests;
+        if z_index.is_some() {
+            ren.pop_z_index();
+        }
 
         self.widget = widget;
         self.dirty.paint_dirty = false;
     }
 
+    // Frees the renderer slots held by this node and all of its descendants
+    pub fn release(&mut self, ren: &mut Renderer) {
+        self.widget.release(ren);
+        for child in self.children.iter_mut().chain(self.removed.iter_mut()) {
+            child.release(ren);
+        }
+        self.removed.clear();
+    }
+
+    // Passes the slot ids the renderer moved in `Renderer::compact` on to
+    // every widget in the tree
+    pub fn remap_slots(&mut self, remap: &SlotRemap) {
+        self.widget.remap_slots(remap);
+        for child in self.children.iter_mut().chain(self.removed.iter_mut()) {
+            child.remap_slots(remap);
+        }
+    }
+
+    // Takes a node out of the tree. Its effects, memos, timers and signal
+    // subscriptions end right away; its renderer slots are freed on the next
+    // paint.
+    fn retire(&mut self, mut node: Node) {
+        node.dispose_scopes();
+        self.removed.push(node);
+    }
+
+    fn dispose_scopes(&mut self) {
+        for child in &mut self.children {
+            child.dispose_scopes();
+        }
+        self.scope.dispose_now();
+    }
+
     pub fn mark_dirty_by_id(&mut self, target_id: NodeId) -> bool {
         if self.id == target_id {
             self.mark_dirty();
+            self.dirty.build_dirty = true;
             return true;
         }
         for child in &mut self.children {
             if child.mark_dirty_by_id(target_id) {
                 self.dirty.paint_dirty = true;
+                self.dirty.child_dirty = true;
                 return true;
             }
         }
         false
     }
 
-    fn hittest(&self, pt: Vec2, path: &mut Vec<usize>) -> bool {
-        if !self.layout_rect.contains(pt) {
-            return false;
+    // Path to the node on top at `pt`: the highest z-index wins, then
+    // whichever painted last. Children of a node that clips them are only
+    // considered inside its rect.
+    fn hittest(&self, pt: Vec2) -> Option<Vec<usize>> {
+        let mut top = None;
+        self.collect_hits(pt, 0, &mut Vec::new(), &mut top);
+        top.map(|(_, path)| path)
+    }
+
+    fn collect_hits(
+        &self,
+        pt: Vec2,
+        parent_z: i32,
+        path: &mut Vec<usize>,
+        top: &mut Option<(i32, Vec<usize>)>,
+    ) {
+        let style = self.widget.style();
+        let z = style.z_index.unwrap_or(parent_z);
+
+        // Nodes are visited in paint order, so an equal z-index is on top.
+        // `PointerEvents::None` only lets the pointer through this node; its
+        // children can still be hit.
+        let above = top.as_ref().is_none_or(|&(top_z, _)| z >= top_z);
+        if above
+            && style.pointer_events == PointerEvents::Auto
+            && self.widget.hit_test(pt, self.layout_rect)
+        {
+            *top = Some((z, path.clone()));
         }
 
+        // Children overflowing a parent that doesn't clip them are still
+        // visible, so they can still be hit
+        if self.widget.clips_children() && !self.layout_rect.contains(pt) {
+            return;
+        }
         for (i, child) in self.children.iter().enumerate() {
-            if child.hittest(pt, path) {
-                path.insert(0, i);
-                return true;
-            }
+            path.push(i);
+            child.collect_hits(pt, z, path, top);
+            path.pop();
         }
-        true
     }
 
     fn dispatch(
@@ -182,13 +474,17 @@ impl Node {
         ctx: &mut EventCtx,
     ) {
         let handle_event = |node: &mut Node, ctx: &mut EventCtx| {
-            ctx.node_layout = node.layout_rect;
+            ctx.set_node(depth, node.layout_rect);
+            // Timers and signals created by the handler belong to the node
+            let _owner = node.scope.enter();
             node.widget.event(ctx, ev);
 
             if ctx.layout_requested {
                 node.mark_dirty();
             }
-            node.invalidate();
+            if mem::take(&mut ctx.paint_requested) {
+                node.invalidate();
+            }
         };
         let is_target = depth == path.len();
 
@@ -231,6 +527,9 @@ impl Node {
         if let WindowEvent::ModifiersChanged(new_mods) = event {
             focus.modifiers = new_mods.state();
         }
+        if !matches!(event, WindowEvent::DroppedFile(_)) {
+            self.flush_dropped_files(focus);
+        }
 
         match *event {
             WindowEvent::CursorMoved { position, .. } => {
@@ -240,76 +539,50 @@ impl Node {
                 self.handle_pointer_move(pos, focus);
             }
 
-            WindowEvent::CursorLeft { .. } => {
+            // A captured pointer is still tracked outside the window
+            WindowEvent::CursorLeft { .. } if focus.pointer_capture().is_none() => {
                 self.flush_pointer_leave(focus);
             }
 
             WindowEvent::MouseInput { state, button, .. } => {
-                if self.hover_path.is_empty() {
-                    return;
-                }
-
-                if state == ElementState::Pressed {
-                    if !focus.is_path_focused(&self.hover_path) {
-                        focus.blur();
-                    }
-                }
-
-                let kind = match state {
-                    ElementState::Pressed => EventKind::PointerDown {
-                        button,
-                        pos: Vec2::ZERO,
-                    },
-                    ElementState::Released => EventKind::PointerUp {
-                        button,
-                        pos: Vec2::ZERO,
-                    },
-                };
-                Self::send_to_path(self, &self.hover_path.clone(), kind, focus);
+                self.handle_pointer_button(button, state, focus);
             }
 
             WindowEvent::MouseWheel { delta, .. } => {
-                if self.hover_path.is_empty() {
-                    return;
-                }
                 let d = match delta {
                     MouseScrollDelta::LineDelta(x, y) => glam::vec2(x, y),
                     MouseScrollDelta::PixelDelta(p) => glam::vec2(p.x as f32, p.y as f32),
                 };
-                Self::send_to_path(
-                    self,
-                    &self.hover_path.clone(),
-                    EventKind::Wheel { delta: d },
-                    focus,
-                );
+                self.handle_wheel(d, focus);
             }
 
             WindowEvent::KeyboardInput {
                 event: ref key_ev, ..
             } => {
-                let focused_path = focus.path().to_vec();
-                if focused_path.is_empty() && key_ev.text.is_none() {
-                    return;
-                }
+                self.handle_key(
+                    &key_ev.logical_key,
+                    key_ev.state,
+                    key_ev.text.as_deref(),
+                    focus,
+                );
+            }
 
-                let kind = match key_ev.state {
-                    ElementState::Pressed => EventKind::KeyDown {
-                        key: key_ev.logical_key.clone(),
-                    },
-                    ElementState::Released => EventKind::KeyUp {
-                        key: key_ev.logical_key.clone(),
-                    },
-                };
-                Self::send_to_path(self, &focused_path, kind, focus);
+            WindowEvent::HoveredFile(ref path) => {
+                self.hovered_files.push(path.clone());
+                self.retarget_file_hover(focus, true);
+            }
 
-                if let Some(text) = &key_ev.text {
-                    if let Some(ch) = text.chars().next() {
-                        Self::send_to_path(self, &focused_path, EventKind::CharInput { ch }, focus);
-                    }
-                }
+            WindowEvent::HoveredFileCancelled => {
+                self.end_file_hover(focus);
+            }
+
+            WindowEvent::DroppedFile(ref path) => {
+                self.dropped_files.push(path.clone());
             }
 
             WindowEvent::Focused(false) => {
+                self.cancel_drag(focus);
+                self.cancel_pointer_capture(focus);
                 focus.blur();
             }
 
@@ -326,6 +599,284 @@ impl Node {
             _ => {}
         }
 
+        self.apply_focus_change(focus);
+    }
+
+    // The path holding pointer capture, dropping the capture if its node
+    // is gone
+    fn capture_path(&self, focus: &mut FocusManager) -> Option<Vec<usize>> {
+        let path = focus.pointer_capture()?.to_vec();
+        if self.node_at(&path).is_none() {
+            focus.pointer_capture = None;
+            return None;
+        }
+        Some(path)
+    }
+
+    fn cancel_pointer_capture(&mut self, focus: &mut FocusManager) {
+        if let Some(path) = self.capture_path(focus) {
+            focus.pointer_capture = None;
+            Self::send_to_path(self, &path, EventKind::PointerCancel, focus);
+        }
+    }
+
+    fn handle_pointer_button(
+        &mut self,
+        button: MouseButton,
+        state: ElementState,
+        focus: &mut FocusManager,
+    ) {
+        if focus.is_dragging() {
+            if state == ElementState::Released {
+                self.finish_drag(focus);
+            }
+            return;
+        }
+
+        let capture = self.capture_path(focus);
+        let path = capture.clone().unwrap_or_else(|| self.hover_path.clone());
+        if path.is_empty() {
+            return;
+        }
+
+        if state == ElementState::Pressed && !focus.is_path_focused(&path) {
+            focus.blur();
+        }
+
+        let pos = self.pointer_pos;
+        let modifiers = focus.modifiers;
+        let kind = match state {
+            ElementState::Pressed => EventKind::PointerDown {
+                button,
+                pos,
+                click_count: focus.clicks.press(button, pos),
+                modifiers,
+            },
+            ElementState::Released => EventKind::PointerUp {
+                button,
+                pos,
+                click_count: focus.clicks.current(),
+                modifiers,
+            },
+        };
+        Self::send_to_path(self, &path, kind, focus);
+
+        // Capture held from before this release ends with it
+        if state == ElementState::Released && capture.is_some() && capture == focus.pointer_capture
+        {
+            focus.pointer_capture = None;
+        }
+
+        match state {
+            ElementState::Pressed => self.begin_pending_drag(focus),
+            // Too late to drag anything once the button is up
+            ElementState::Released => focus.drag = None,
+        }
+    }
+
+    // Hands the pointer over to a drag that a handler just started
+    fn begin_pending_drag(&mut self, focus: &mut FocusManager) {
+        match &mut focus.drag {
+            Some(drag) if !drag.active => drag.active = true,
+            _ => return,
+        }
+        focus.pointer_capture = None;
+        self.flush_pointer_leave(focus);
+        self.drag_to(self.pointer_pos, focus);
+    }
+
+    // Moves the drag to `pos`, telling the paths it leaves and enters
+    fn drag_to(&mut self, pos: Vec2, focus: &mut FocusManager) {
+        let Some(drag) = &mut focus.drag else {
+            return;
+        };
+        drag.pos = pos;
+        let old = drag.over.clone();
+        scheduler::request_frame();
+
+        let target = self.hittest(pos);
+        if target != old {
+            if let Some(old) = old {
+                self.send_if_present(&old, EventKind::DragLeave, focus);
+            }
+            if let Some(drag) = &mut focus.drag {
+                drag.over = target.clone();
+                drag.accepted = false;
+            }
+            if let Some(path) = &target {
+                Self::send_to_path(self, path, EventKind::DragEnter { pos }, focus);
+            }
+        }
+        if let Some(path) = &target {
+            Self::send_to_path(self, path, EventKind::DragOver { pos }, focus);
+        }
+    }
+
+    // Drops onto the path under the pointer if it accepted, then tells the
+    // source how it went
+    fn finish_drag(&mut self, focus: &mut FocusManager) {
+        let Some(drag) = &focus.drag else {
+            return;
+        };
+        let (over, source, pos) = (drag.over.clone(), drag.source.clone(), drag.pos);
+        let dropped = over.is_some() && drag.accepted;
+
+        if let Some(path) = &over {
+            let kind = if dropped {
+                EventKind::Drop { pos }
+            } else {
+                EventKind::DragLeave
+            };
+            self.send_if_present(path, kind, focus);
+        }
+        self.send_if_present(&source, EventKind::DragEnd { dropped }, focus);
+
+        focus.drag = None;
+        scheduler::request_frame();
+        // Hover picks up again where the drag ended
+        self.send_pointer_move(self.pointer_pos, focus);
+    }
+
+    fn cancel_drag(&mut self, focus: &mut FocusManager) {
+        if let Some(drag) = &mut focus.drag {
+            drag.accepted = false;
+        }
+        if focus.is_dragging() {
+            self.finish_drag(focus);
+        }
+        if focus.drag.take().is_some() {
+            scheduler::request_frame();
+        }
+    }
+
+    // Sends the files being hovered to the path under the pointer, moving
+    // the hover off the previous path if it changed. `resend` repeats it for
+    // an unchanged path, as when another file joins the hover.
+    fn retarget_file_hover(&mut self, focus: &mut FocusManager, resend: bool) {
+        let target = self.hittest(self.pointer_pos);
+        let old = self.file_hover_path.clone();
+        if target == old && !resend {
+            return;
+        }
+        if let Some(old) = old.filter(|old| Some(old) != target.as_ref()) {
+            self.send_if_present(&old, EventKind::FileHoverCancel, focus);
+        }
+
+        self.file_hover_path = target.clone();
+        if let Some(path) = target {
+            let kind = EventKind::FileHover {
+                paths: self.hovered_files.clone(),
+                pos: self.pointer_pos,
+            };
+            Self::send_to_path(self, &path, kind, focus);
+        }
+    }
+
+    fn end_file_hover(&mut self, focus: &mut FocusManager) {
+        self.hovered_files.clear();
+        if let Some(path) = self.file_hover_path.take() {
+            self.send_if_present(&path, EventKind::FileHoverCancel, focus);
+        }
+    }
+
+    // Delivers the files dropped since the last flush as one `FileDrop`.
+    // Called before any other window event and when the event loop goes
+    // idle.
+    pub fn flush_dropped_files(&mut self, focus: &mut FocusManager) {
+        if self.dropped_files.is_empty() {
+            return;
+        }
+        let paths = mem::take(&mut self.dropped_files);
+        let pos = self.pointer_pos;
+        let target = self.hittest(pos);
+
+        self.hovered_files.clear();
+        if let Some(old) = self.file_hover_path.take()
+            && Some(&old) != target.as_ref()
+        {
+            self.send_if_present(&old, EventKind::FileHoverCancel, focus);
+        }
+        if let Some(path) = target {
+            Self::send_to_path(self, &path, EventKind::FileDrop { paths, pos }, focus);
+        }
+        self.apply_focus_change(focus);
+    }
+
+    // The tree can be rebuilt while a drag is in progress, so the paths it
+    // remembered may be gone
+    fn send_if_present(&mut self, path: &[usize], kind: EventKind, focus: &mut FocusManager) {
+        if self.node_at(path).is_some() {
+            Self::send_to_path(self, path, kind, focus);
+        }
+    }
+
+    fn handle_wheel(&mut self, delta: Vec2, focus: &mut FocusManager) {
+        if self.hover_path.is_empty() {
+            return;
+        }
+        Self::send_to_path(
+            self,
+            &self.hover_path.clone(),
+            EventKind::Wheel {
+                delta,
+                pos: self.pointer_pos,
+            },
+            focus,
+        );
+    }
+
+    fn handle_key(
+        &mut self,
+        key: &Key,
+        state: ElementState,
+        text: Option<&str>,
+        focus: &mut FocusManager,
+    ) {
+        // Keys are held back mid-drag, except Escape which cancels it
+        if focus.is_dragging() {
+            if *key == Key::Named(NamedKey::Escape) && state == ElementState::Pressed {
+                self.cancel_drag(focus);
+            }
+            return;
+        }
+
+        let focused_path = focus.path().to_vec();
+        let is_tab = *key == Key::Named(NamedKey::Tab);
+        if focused_path.is_empty() && text.is_none() && !is_tab {
+            return;
+        }
+
+        let kind = match state {
+            ElementState::Pressed => EventKind::KeyDown { key: key.clone() },
+            ElementState::Released => EventKind::KeyUp { key: key.clone() },
+        };
+        let prevented = Self::send_to_path(self, &focused_path, kind, focus);
+
+        // Tab moves focus unless the focused widget wants the key itself
+        if is_tab && !prevented {
+            if state == ElementState::Pressed {
+                if focus.modifiers.shift_key() {
+                    focus.focus_prev();
+                } else {
+                    focus.focus_next();
+                }
+            }
+            return;
+        }
+
+        if let Some(ch) = text.and_then(|text| text.chars().next()) {
+            Self::send_to_path(self, &focused_path, EventKind::CharInput { ch }, focus);
+        }
+    }
+
+    fn apply_focus_change(&mut self, focus: &mut FocusManager) {
+        let traversal = focus
+            .take_traversal()
+            .and_then(|direction| windowing::traverse(self, focus.path(), direction));
+        if let Some(path) = &traversal {
+            focus.request_focus(path);
+        }
+
         if let Some(new_path) = focus.take_change_request() {
             let old_path = focus.path().to_vec();
             if new_path != old_path {
@@ -338,9 +889,20 @@ impl Node {
                 focus.commit_focus_change(new_path);
             }
         }
+
+        if let Some(path) = traversal {
+            windowing::scroll_into_view(self, &path);
+        }
     }
 
-    fn send_to_path(node: &mut Node, path: &[usize], kind: EventKind, focus: &mut FocusManager) {
+    // Returns whether a handler prevented the default action
+    fn send_to_path(
+        node: &mut Node,
+        path: &[usize],
+        kind: EventKind,
+        focus: &mut FocusManager,
+    ) -> bool {
+        let mut prevented = false;
         for &phase in &[Phase::Capture, Phase::Target, Phase::Bubble] {
             let mut ctx = EventCtx::new(
                 phase,
@@ -350,18 +912,51 @@ impl Node {
                 focus.modifiers,
             );
             node.dispatch(path, 0, phase, &kind, &mut ctx);
+            prevented |= ctx.is_default_prevented();
         }
+        prevented
     }
 
     fn handle_pointer_move(&mut self, pos: Vec2, focus: &mut FocusManager) {
-        let mut new_path = Vec::<usize>::new();
-        if !self.hittest(pos, &mut new_path) {
-            self.flush_pointer_leave(focus);
+        self.pointer_pos = pos;
+        if !self.hovered_files.is_empty() {
+            self.retarget_file_hover(focus, false);
+        }
+        if focus.is_dragging() {
+            self.drag_to(pos, focus);
             return;
         }
+        self.send_pointer_move(pos, focus);
+        self.begin_pending_drag(focus);
+    }
+
+    fn send_pointer_move(&mut self, pos: Vec2, focus: &mut FocusManager) {
+        let modifiers = focus.modifiers;
+
+        // Hover stays put while captured and catches up on the first move
+        // after release
+        if let Some(path) = self.capture_path(focus) {
+            Self::send_to_path(
+                self,
+                &path,
+                EventKind::PointerMove { pos, modifiers },
+                focus,
+            );
+            return;
+        }
+
+        let Some(new_path) = self.hittest(pos) else {
+            self.flush_pointer_leave(focus);
+            return;
+        };
 
         if self.hover_path == new_path {
-            Self::send_to_path(self, &new_path, EventKind::PointerMove { pos }, focus);
+            Self::send_to_path(
+                self,
+                &new_path,
+                EventKind::PointerMove { pos, modifiers },
+                focus,
+            );
             return;
         }
 
@@ -370,7 +965,12 @@ impl Node {
             Self::send_to_path(self, &old_path_clone, EventKind::PointerLeave, focus);
         }
 
-        Self::send_to_path(self, &new_path, EventKind::PointerMove { pos }, focus);
+        Self::send_to_path(
+            self,
+            &new_path,
+            EventKind::PointerMove { pos, modifiers },
+            focus,
+        );
 
         self.hover_path = new_path;
     }
@@ -386,6 +986,25 @@ impl Node {
         self.hover_path.clear();
     }
 
+    // Whether anything in this subtree changed since it was last painted
+    pub fn needs_paint(&self) -> bool {
+        let d = &self.dirty;
+        d.paint_dirty
+            || d.self_dirty
+            || d.child_dirty
+            || d.build_dirty
+            || self.children.iter().any(Node::needs_paint)
+    }
+
+    // Marks the whole subtree as painted, including children the last frame
+    // skipped because they were outside their parent
+    pub fn clear_paint_dirty(&mut self) {
+        self.dirty.paint_dirty = false;
+        for child in &mut self.children {
+            child.clear_paint_dirty();
+        }
+    }
+
     fn invalidate(&mut self) {
         if !self.dirty.paint_dirty {
             self.dirty.paint_dirty = true;
@@ -393,7 +1012,35 @@ impl Node {
     }
 
     pub fn style(&self) -> crate::Style {
-        self.widget.style()
+        self.animated_style().unwrap_or_else(|| self.widget.style())
+    }
+
+    // The style for this frame while a transition is running. Widgets that
+    // draw their own background should prefer it over their target style.
+    pub fn animated_style(&self) -> Option<crate::Style> {
+        let transition = self.transition.as_ref()?;
+        Some(transition.style_at(animation::frame_time()))
+    }
+
+    // Re-lays out every node whose style is mid transition and drops the
+    // finished ones. Returns whether anything in the subtree changed.
+    pub(crate) fn advance_transitions(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        if let Some(transition) = &self.transition {
+            if transition.is_done(now) {
+                self.transition = None;
+            }
+            self.mark_dirty();
+            changed = true;
+        }
+        for child in &mut self.children {
+            if child.advance_transitions(now) {
+                self.dirty.child_dirty = true;
+                self.dirty.paint_dirty = true;
+                changed = true;
+            }
+        }
+        changed
     }
 
     pub fn set_rect(&mut self, r: Rect) {
@@ -421,4 +1068,207 @@ impl Node {
         self.dirty.self_dirty = true;
         self.dirty.paint_dirty = true;
     }
+
+    // Synthetic input, routed through the same dispatch and focus handling
+    // as real window events. Layout must have run so hit testing works.
+
+    pub fn move_to(&mut self, pos: Vec2, focus: &mut FocusManager) {
+        self.handle_pointer_move(pos, focus);
+        self.apply_focus_change(focus);
+    }
+
+    pub fn click(&mut self, pos: Vec2, focus: &mut FocusManager) {
+        self.move_to(pos, focus);
+        for state in [ElementState::Pressed, ElementState::Released] {
+            self.handle_pointer_button(MouseButton::Left, state, focus);
+            self.apply_focus_change(focus);
+        }
+    }
+
+    // A click with modifier keys held, e.g. shift-click to extend a selection
+    pub fn click_with_modifiers(
+        &mut self,
+        pos: Vec2,
+        modifiers: ModifiersState,
+        focus: &mut FocusManager,
+    ) {
+        let previous = std::mem::replace(&mut focus.modifiers, modifiers);
+        self.click(pos, focus);
+        focus.modifiers = previous;
+    }
+
+    // Presses at `from`, moves to `to` and releases there
+    pub fn drag(&mut self, from: Vec2, to: Vec2, focus: &mut FocusManager) {
+        self.press_pointer(from, focus);
+        self.move_to(to, focus);
+        self.release_pointer(focus);
+    }
+
+    pub fn press_pointer(&mut self, pos: Vec2, focus: &mut FocusManager) {
+        self.move_to(pos, focus);
+        self.handle_pointer_button(MouseButton::Left, ElementState::Pressed, focus);
+        self.apply_focus_change(focus);
+    }
+
+    pub fn release_pointer(&mut self, focus: &mut FocusManager) {
+        self.handle_pointer_button(MouseButton::Left, ElementState::Released, focus);
+        self.apply_focus_change(focus);
+    }
+
+    // As if the window lost focus
+    pub fn blur_window(&mut self, focus: &mut FocusManager) {
+        self.cancel_drag(focus);
+        self.cancel_pointer_capture(focus);
+        focus.blur();
+        self.apply_focus_change(focus);
+    }
+
+    // Files dragged in from the OS to `pos`, one hover event per file as
+    // winit sends them
+    pub fn hover_files(&mut self, paths: &[PathBuf], pos: Vec2, focus: &mut FocusManager) {
+        self.pointer_pos = pos;
+        for path in paths {
+            self.hovered_files.push(path.clone());
+            self.retarget_file_hover(focus, true);
+        }
+        self.apply_focus_change(focus);
+    }
+
+    pub fn cancel_file_hover(&mut self, focus: &mut FocusManager) {
+        self.end_file_hover(focus);
+        self.apply_focus_change(focus);
+    }
+
+    pub fn drop_files(&mut self, paths: &[PathBuf], pos: Vec2, focus: &mut FocusManager) {
+        self.pointer_pos = pos;
+        self.dropped_files.extend_from_slice(paths);
+        self.flush_dropped_files(focus);
+    }
+
+    pub fn scroll(&mut self, delta: Vec2, focus: &mut FocusManager) {
+        self.handle_wheel(delta, focus);
+        self.apply_focus_change(focus);
+    }
+
+    pub fn type_text(&mut self, text: &str, focus: &mut FocusManager) {
+        for ch in text.chars() {
+            let key = Key::Character(ch.to_string().into());
+            let text = ch.to_string();
+            self.handle_key(&key, ElementState::Pressed, Some(&text), focus);
+            self.handle_key(&key, ElementState::Released, None, focus);
+            self.apply_focus_change(focus);
+        }
+    }
+
+    pub fn press(&mut self, key: Key, modifiers: ModifiersState, focus: &mut FocusManager) {
+        let previous = std::mem::replace(&mut focus.modifiers, modifiers);
+        self.handle_key(&key, ElementState::Pressed, None, focus);
+        self.handle_key(&key, ElementState::Released, None, focus);
+        focus.modifiers = previous;
+        self.apply_focus_change(focus);
+    }
+
+    pub fn tab(&mut self, focus: &mut FocusManager) {
+        self.press(Key::Named(NamedKey::Tab), ModifiersState::empty(), focus);
+    }
+
+    pub fn shift_tab(&mut self, focus: &mut FocusManager) {
+        self.press(Key::Named(NamedKey::Tab), ModifiersState::SHIFT, focus);
+    }
+
+    pub fn focus_next(&mut self, focus: &mut FocusManager) {
+        focus.focus_next();
+        self.apply_focus_change(focus);
+    }
+
+    pub fn focus_prev(&mut self, focus: &mut FocusManager) {
+        focus.focus_prev();
+        self.apply_focus_change(focus);
+    }
+
+    pub fn hover_path(&self) -> &[usize] {
+        &self.hover_path
+    }
+
+    pub fn node_at(&self, path: &[usize]) -> Option<&Node> {
+        match path.split_first() {
+            Some((&idx, rest)) => self.children.get(idx)?.node_at(rest),
+            None => Some(self),
+        }
+    }
+
+    pub fn widget_at<T: Widget + 'static>(&self, path: &[usize]) -> Option<&T> {
+        self.node_at(path)?.widget.as_any().downcast_ref::<T>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::signals::on_cleanup;
+
+    // Builds a `Watched` child while `show` is set
+    #[derive(Clone)]
+    struct Toggle {
+        show: Rc<Cell<bool>>,
+        disposed: Rc<Cell<bool>>,
+    }
+
+    impl Widget for Toggle {
+        fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+            if !self.show.get() {
+                return Vec::new();
+            }
+            vec![Box::new(Watched(self.disposed.clone()))]
+        }
+
+        fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+            Vec2::ZERO
+        }
+
+        fn paint(&mut self, _node: &mut Node, _ren: &mut Renderer, _theme: &Theme) {}
+    }
+
+    // Flags when its build scope is disposed
+    #[derive(Clone)]
+    struct Watched(Rc<Cell<bool>>);
+
+    impl Widget for Watched {
+        fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+            let disposed = self.0.clone();
+            on_cleanup(move || disposed.set(true));
+            Vec::new()
+        }
+
+        fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+            Vec2::ZERO
+        }
+
+        fn paint(&mut self, _node: &mut Node, _ren: &mut Renderer, _theme: &Theme) {}
+    }
+
+    #[test]
+    fn removed_nodes_are_disposed_before_the_next_paint() {
+        let show = Rc::new(Cell::new(true));
+        let disposed = Rc::new(Cell::new(false));
+        let widget = Toggle {
+            show: show.clone(),
+            disposed: disposed.clone(),
+        };
+        let mut root = Node::new(
+            Box::new(widget),
+            Rect::new(Vec2::ZERO, Vec2::ONE),
+            &mut BuildCtx,
+        );
+        assert_eq!(root.children.len(), 1);
+
+        show.set(false);
+        root.mark_dirty_by_id(root.id);
+        root.rebuild(&mut BuildCtx);
+
+        assert!(root.children.is_empty());
+        assert!(disposed.get());
+    }
 }
@@ -31,4 +31,11 @@ impl Rect {
 
         Rect::new(new_tl, new_size)
     }
+
+    // Smallest rect covering both
+    pub fn union(&self, other: &Rect) -> Rect {
+        let tl = self.origin.min(other.origin);
+        let br = (self.origin + self.size).max(other.origin + other.size);
+        Rect::new(tl, br - tl)
+    }
 }
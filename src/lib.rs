@@ -1,10 +1,14 @@
+pub mod animation;
 pub mod app;
 pub mod debug;
 pub mod layout;
 pub mod renderer;
+pub mod scheduler;
 pub mod signals;
+pub mod snapshot;
 pub mod style;
 pub mod text;
+pub mod timer;
 pub mod widgets;
 pub mod windowing;
 
@@ -13,7 +17,7 @@ pub use app::App;
 pub use layout::LayoutNode;
 pub use renderer::Renderer;
 pub use style::Style;
-pub use widgets::{Button, Canvas, Checkbox, Element, Image, Text, TextInput, Widget};
+pub use widgets::{Button, Canvas, Checkbox, Element, For, ForMap, Image, Text, TextInput, Widget};
 pub use windowing::{Window, WindowBuilder};
 
 pub use glam::{Mat4, Vec2, Vec3, Vec4, vec2};
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use cosmic_text::{CacheKey, FontSystem, SwashCache, SwashContent};
+
+const INITIAL_SIZE: u32 = 512;
+const MAX_SIZE: u32 = 4096;
+// Empty border kept around every glyph so filtering never bleeds neighbours
+const PADDING: u32 = 1;
+
+// Where a rasterized glyph lives in the atlas, plus the offset of its bitmap
+// from the pen position.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AtlasGlyph {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub left: i32,
+    pub top: i32,
+    // Colour bitmaps (emoji) are drawn as-is instead of tinted
+    pub is_color: bool,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    cursor_x: u32,
+}
+
+// RGBA texture that every glyph is rasterized into once. Masks are stored as
+// white with coverage in alpha so one texture serves both tinted text and
+// colour emoji. Entries are keyed by cosmic-text's CacheKey, which covers the
+// font, glyph, size and subpixel bin.
+pub struct GlyphAtlas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    shelves: Vec<Shelf>,
+    glyphs: HashMap<CacheKey, Option<AtlasGlyph>>,
+
+    // Pixel region touched since the last upload as (x0, y0, x1, y1)
+    dirty: Option<(u32, u32, u32, u32)>,
+    // Bumped whenever the atlas is cleared and old placements become invalid
+    generation: u64,
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GlyphAtlas {
+    pub fn new() -> Self {
+        Self {
+            width: INITIAL_SIZE,
+            height: INITIAL_SIZE,
+            pixels: vec![0; (INITIAL_SIZE * INITIAL_SIZE * 4) as usize],
+            shelves: Vec::new(),
+            glyphs: HashMap::new(),
+            dirty: None,
+            generation: 0,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Raw RGBA8 pixels, sRGB encoded like any other texture
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn dirty_region(&self) -> Option<(u32, u32, u32, u32)> {
+        self.dirty
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = None;
+    }
+
+    // Returns the atlas entry for a glyph, rasterizing it on first use.
+    // `None` means the glyph has no visible pixels (e.g. a space).
+    pub fn get_or_insert(
+        &mut self,
+        font_system: &mut FontSystem,
+        swash: &mut SwashCache,
+        key: CacheKey,
+    ) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&key) {
+            return *glyph;
+        }
+
+        let glyph = swash
+            .get_image_uncached(font_system, key)
+            .and_then(|image| self.insert(&image));
+        self.glyphs.insert(key, glyph);
+        glyph
+    }
+
+    fn insert(&mut self, image: &cosmic_text::SwashImage) -> Option<AtlasGlyph> {
+        let (w, h) = (image.placement.width, image.placement.height);
+        if w == 0 || h == 0 {
+            return None;
+        }
+
+        let (x, y) = match self.allocate(w, h) {
+            Some(pos) => pos,
+            None => {
+                // Out of room even at the maximum size: start over. Callers
+                // see the generation change and lay their text out again.
+                log::warn!("glyph atlas full, clearing");
+                self.reset();
+                self.allocate(w, h)?
+            }
+        };
+
+        for row in 0..h {
+            for col in 0..w {
+                let src = (row * w + col) as usize;
+                let rgba = match image.content {
+                    SwashContent::Mask => [255, 255, 255, image.data[src]],
+                    SwashContent::Color => {
+                        let i = src * 4;
+                        [
+                            image.data[i],
+                            image.data[i + 1],
+                            image.data[i + 2],
+                            image.data[i + 3],
+                        ]
+                    }
+                    SwashContent::SubpixelMask => {
+                        let i = src * 4;
+                        let coverage = (image.data[i] as u32
+                            + image.data[i + 1] as u32
+                            + image.data[i + 2] as u32)
+                            / 3;
+                        [255, 255, 255, coverage as u8]
+                    }
+                };
+                let dst = (((y + row) * self.width + x + col) * 4) as usize;
+                self.pixels[dst..dst + 4].copy_from_slice(&rgba);
+            }
+        }
+        self.mark_dirty(x, y, x + w, y + h);
+
+        Some(AtlasGlyph {
+            x,
+            y,
+            width: w,
+            height: h,
+            left: image.placement.left,
+            top: image.placement.top,
+            is_color: image.content == SwashContent::Color,
+        })
+    }
+
+    // Shelf packing: glyphs go on the first row tall enough with room left,
+    // new rows open below the last one and the atlas doubles in height when
+    // it runs out.
+    fn allocate(&mut self, w: u32, h: u32) -> Option<(u32, u32)> {
+        let (pw, ph) = (w + PADDING, h + PADDING);
+        if pw > self.width {
+            return None;
+        }
+
+        let width = self.width;
+        if let Some(shelf) = self
+            .shelves
+            .iter_mut()
+            .filter(|s| s.height >= ph && s.height <= ph * 2 && s.cursor_x + pw <= width)
+            .min_by_key(|s| s.height)
+        {
+            let x = shelf.cursor_x;
+            shelf.cursor_x += pw;
+            return Some((x, shelf.y));
+        }
+
+        let next_y = self.shelves.last().map_or(0, |s| s.y + s.height);
+        while next_y + ph > self.height {
+            if self.height >= MAX_SIZE {
+                return None;
+            }
+            self.grow();
+        }
+
+        self.shelves.push(Shelf {
+            y: next_y,
+            height: ph,
+            cursor_x: pw,
+        });
+        Some((0, next_y))
+    }
+
+    fn grow(&mut self) {
+        // Rows are contiguous, so doubling the height keeps every placement
+        self.height *= 2;
+        self.pixels
+            .resize((self.width * self.height * 4) as usize, 0);
+        self.mark_dirty(0, 0, self.width, self.height);
+    }
+
+    fn reset(&mut self) {
+        self.pixels.fill(0);
+        self.shelves.clear();
+        self.glyphs.clear();
+        self.generation += 1;
+        self.mark_dirty(0, 0, self.width, self.height);
+    }
+
+    fn mark_dirty(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        self.dirty = Some(match self.dirty {
+            Some((a, b, c, d)) => (a.min(x0), b.min(y0), c.max(x1), d.max(y1)),
+            None => (x0, y0, x1, y1),
+        });
+    }
+}
@@ -0,0 +1,148 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use anyhow::Result;
+use glam::{Vec2, Vec4};
+use image::RgbaImage;
+
+use super::atlas::GlyphAtlas;
+use super::primatives::{CircleInstance, GlyphInstance, LineInstance, RectInstance};
+use crate::layout::Rect;
+
+// Overlay drawn over repainted regions when damage debugging is enabled
+pub(crate) const DAMAGE_FLASH: Vec4 = Vec4::new(1.0, 0.0, 1.0, 0.25);
+
+#[derive(Clone)]
+pub struct ImageDraw {
+    pub rect: Rect,
+    pub key: String,
+    pub image: Arc<RgbaImage>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DrawKind {
+    Rect,
+    Glyph,
+    Line,
+    Circle,
+    Image,
+}
+
+// A run of draws of one kind that share a clip rect. Ranges index into the
+// matching pool (or `FrameData::glyphs` / `FrameData::images`) and are drawn
+// in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawBatch {
+    pub kind: DrawKind,
+    pub clip: Option<Rect>,
+    pub ranges: Vec<Range<u32>>,
+}
+
+// Everything a backend needs to produce one frame. The pools are the full
+// retained instance lists, the update lists only the entries that changed
+// since the previous frame. Only instances referenced by a batch are drawn,
+// and only inside `damage`; the rest of the target keeps the previous frame.
+pub struct FrameData<'f> {
+    pub rects: &'f [RectInstance],
+    pub lines: &'f [LineInstance],
+    pub circles: &'f [CircleInstance],
+
+    pub rect_updates: &'f [(usize, RectInstance)],
+    pub line_updates: &'f [(usize, LineInstance)],
+    pub circle_updates: &'f [(usize, CircleInstance)],
+
+    // Glyph quads of every text drawn this frame, sampled from `atlas`
+    pub glyphs: &'f [GlyphInstance],
+    pub atlas: &'f GlyphAtlas,
+
+    pub images: &'f [ImageDraw],
+
+    pub batches: &'f [DrawBatch],
+
+    // Logical region that differs from the previous frame, None if nothing
+    // changed. Backends clear and redraw only this area.
+    pub damage: Option<Rect>,
+    // Tint the repainted region on the presented image
+    pub show_damage: bool,
+
+    pub clear_color: Vec4,
+    pub scale_factor: f32,
+}
+
+pub trait RenderBackend {
+    // Size of the render target in physical pixels
+    fn size(&self) -> (u32, u32);
+
+    fn resize(&mut self, width: u32, height: u32);
+
+    fn render(&mut self, frame: &FrameData) -> Result<()>;
+
+    // Contents of the last rendered frame, if the backend can read them back
+    fn read_pixels(&self) -> Option<RgbaImage> {
+        None
+    }
+
+    fn gpu(&self) -> Option<&super::GpuContext> {
+        None
+    }
+
+    // Format of the window surface, for backends that present to one
+    fn surface_format(&self) -> Option<wgpu::TextureFormat> {
+        None
+    }
+}
+
+// Converts a logical clip rect into a physical (x, y, w, h) scissor that lies
+// within the target bounds.
+pub(crate) fn physical_scissor(
+    clip: Option<Rect>,
+    scale_factor: f32,
+    width: u32,
+    height: u32,
+) -> (u32, u32, u32, u32) {
+    let Some(logical_rect) = clip else {
+        return (0, 0, width, height);
+    };
+
+    let physical_x = ((logical_rect.origin.x * scale_factor).round() as u32).min(width);
+    let physical_y = ((logical_rect.origin.y * scale_factor).round() as u32).min(height);
+    let physical_w = (logical_rect.size.x * scale_factor).round() as u32;
+    let physical_h = (logical_rect.size.y * scale_factor).round() as u32;
+
+    let physical_w = physical_w.min(width.saturating_sub(physical_x));
+    let physical_h = physical_h.min(height.saturating_sub(physical_y));
+
+    (physical_x, physical_y, physical_w, physical_h)
+}
+
+// Converts a logical damage rect into a physical scissor, rounded outwards so
+// partially covered pixels are repainted too.
+pub(crate) fn physical_damage(
+    damage: Rect,
+    scale_factor: f32,
+    width: u32,
+    height: u32,
+) -> (u32, u32, u32, u32) {
+    let min = (damage.origin * scale_factor).floor().max(Vec2::ZERO);
+    let max = ((damage.origin + damage.size) * scale_factor).ceil();
+
+    let x0 = (min.x as u32).min(width);
+    let y0 = (min.y as u32).min(height);
+    let x1 = (max.x.max(0.0) as u32).clamp(x0, width);
+    let y1 = (max.y.max(0.0) as u32).clamp(y0, height);
+
+    (x0, y0, x1 - x0, y1 - y0)
+}
+
+// Overlap of two physical scissors, empty (zero-sized) if they don't meet
+pub(crate) fn intersect_scissor(
+    (ax, ay, aw, ah): (u32, u32, u32, u32),
+    (bx, by, bw, bh): (u32, u32, u32, u32),
+) -> (u32, u32, u32, u32) {
+    let x0 = ax.max(bx);
+    let y0 = ay.max(by);
+    let x1 = (ax + aw).min(bx + bw).max(x0);
+    let y1 = (ay + ah).min(by + bh).max(y0);
+
+    (x0, y0, x1 - x0, y1 - y0)
+}
@@ -0,0 +1,111 @@
+use std::collections::{BTreeSet, HashMap};
+
+use super::{CircId, LineId, RectId};
+
+// New ids of the slots that moved when the renderer compacted its pools.
+// Anything holding slot ids (widgets, through `Widget::remap_slots`) has to
+// swap them for these before drawing again.
+#[derive(Debug, Default, Clone)]
+pub struct SlotRemap {
+    pub(crate) rects: HashMap<RectId, RectId>,
+    pub(crate) lines: HashMap<LineId, LineId>,
+    pub(crate) circles: HashMap<CircId, CircId>,
+    pub(crate) texts: HashMap<usize, usize>,
+}
+
+impl SlotRemap {
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+            && self.lines.is_empty()
+            && self.circles.is_empty()
+            && self.texts.is_empty()
+    }
+
+    pub fn rect(&self, id: RectId) -> RectId {
+        self.rects.get(&id).copied().unwrap_or(id)
+    }
+
+    pub fn line(&self, id: LineId) -> LineId {
+        self.lines.get(&id).copied().unwrap_or(id)
+    }
+
+    pub fn circle(&self, id: CircId) -> CircId {
+        self.circles.get(&id).copied().unwrap_or(id)
+    }
+
+    pub fn text(&self, id: usize) -> usize {
+        self.texts.get(&id).copied().unwrap_or(id)
+    }
+
+    // Updates a slot id held in an Option, as widgets keep them
+    pub fn remap_rect(&self, id: &mut Option<RectId>) {
+        if let Some(id) = id {
+            *id = self.rect(*id);
+        }
+    }
+
+    pub fn remap_line(&self, id: &mut Option<LineId>) {
+        if let Some(id) = id {
+            *id = self.line(*id);
+        }
+    }
+
+    pub fn remap_circle(&self, id: &mut Option<CircId>) {
+        if let Some(id) = id {
+            *id = self.circle(*id);
+        }
+    }
+
+    pub fn remap_text(&self, id: &mut Option<usize>) {
+        if let Some(id) = id {
+            *id = self.text(*id);
+        }
+    }
+}
+
+// Moves the live entries of `pool` down over the freed slots, keeping their
+// order, and empties `free`. Returns where each moved entry went.
+pub(crate) fn compact_pool<T>(
+    pool: &mut Vec<T>,
+    free: &mut BTreeSet<usize>,
+) -> HashMap<usize, usize> {
+    let mut moved = HashMap::new();
+    for (id, item) in std::mem::take(pool).into_iter().enumerate() {
+        if free.contains(&id) {
+            continue;
+        }
+        if id != pool.len() {
+            moved.insert(id, pool.len());
+        }
+        pool.push(item);
+    }
+    free.clear();
+    pool.shrink_to(pool.len() * 2);
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn live_slots_move_down_in_order() {
+        let mut pool = vec!['a', 'b', 'c', 'd', 'e'];
+        let mut free = BTreeSet::from([0, 2]);
+
+        let moved = compact_pool(&mut pool, &mut free);
+
+        assert_eq!(pool, ['b', 'd', 'e']);
+        assert!(free.is_empty());
+        assert_eq!(moved, HashMap::from([(1, 0), (3, 1), (4, 2)]));
+    }
+
+    #[test]
+    fn nothing_moves_without_holes() {
+        let mut pool = vec![1, 2, 3, 4];
+        let mut free = BTreeSet::from([3]);
+
+        assert!(compact_pool(&mut pool, &mut free).is_empty());
+        assert_eq!(pool, [1, 2, 3]);
+    }
+}
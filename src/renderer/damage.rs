@@ -0,0 +1,204 @@
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use glam::Vec2;
+
+use super::primatives::{CircleInstance, GlyphInstance, LineInstance, RectInstance};
+use crate::{layout::Rect, signals::NodeId};
+
+// Antialiasing reaches a little past an instance's geometric bounds
+const AA_MARGIN: f32 = 1.0;
+
+// Which draw an entry of the display list is: the node that painted it (None
+// for draws made outside of any node's paint) and its place among that node's
+// draws. Stable across frames as long as the node paints the same way, so
+// adding or removing a node doesn't shift the keys of everything after it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct DrawKey {
+    pub owner: Option<NodeId>,
+    pub index: u32,
+}
+
+// What one entry of the display list drew and where. Two frames whose lists
+// match entry for entry produce the same pixels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub(crate) struct DrawSig {
+    hash: u64,
+    // Visible area after clipping; None when fully clipped away
+    bounds: Option<Rect>,
+}
+
+impl DrawSig {
+    fn new(content: &[u8], clip: Option<Rect>, bounds: Rect) -> Self {
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        clip.map(|c| [c.origin.x, c.origin.y, c.size.x, c.size.y])
+            .map(|c| bytemuck::bytes_of(&c).to_vec())
+            .hash(&mut hasher);
+
+        let bounds = Rect::new(
+            bounds.origin - Vec2::splat(AA_MARGIN),
+            bounds.size + Vec2::splat(AA_MARGIN * 2.0),
+        );
+        let bounds = match clip {
+            Some(clip) => Some(bounds.intersection(&clip)),
+            None => Some(bounds),
+        }
+        .filter(|b| b.size.x > 0.0 && b.size.y > 0.0);
+
+        Self {
+            hash: hasher.finish(),
+            bounds,
+        }
+    }
+
+    pub fn rect(inst: &RectInstance, clip: Option<Rect>) -> Self {
+        let bounds = Rect::new(Vec2::from_array(inst.pos), Vec2::from_array(inst.size));
+        Self::new(bytemuck::bytes_of(inst), clip, bounds)
+    }
+
+    pub fn line(inst: &LineInstance, clip: Option<Rect>) -> Self {
+        let a = Vec2::from_array(inst.a);
+        let b = Vec2::from_array(inst.b);
+        let min = a.min(b) - Vec2::splat(inst.half_width);
+        let max = a.max(b) + Vec2::splat(inst.half_width);
+        Self::new(bytemuck::bytes_of(inst), clip, Rect::new(min, max - min))
+    }
+
+    pub fn circle(inst: &CircleInstance, clip: Option<Rect>) -> Self {
+        let extent = Vec2::splat(inst.radius);
+        let bounds = Rect::new(Vec2::from_array(inst.center) - extent, extent * 2.0);
+        Self::new(bytemuck::bytes_of(inst), clip, bounds)
+    }
+
+    pub fn text(glyphs: &[GlyphInstance], clip: Option<Rect>) -> Self {
+        let bounds = glyphs
+            .iter()
+            .map(|g| Rect::new(Vec2::from_array(g.pos), Vec2::from_array(g.size)))
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Rect::new(Vec2::ZERO, Vec2::ZERO));
+        Self::new(bytemuck::cast_slice(glyphs), clip, bounds)
+    }
+
+    pub fn image(key: &str, rect: Rect, clip: Option<Rect>) -> Self {
+        let mut content = key.as_bytes().to_vec();
+        content.extend_from_slice(bytemuck::bytes_of(&[
+            rect.origin.x,
+            rect.origin.y,
+            rect.size.x,
+            rect.size.y,
+        ]));
+        Self::new(&content, clip, rect)
+    }
+}
+
+// Area that differs between two display lists, matching entries up by key.
+// Anything that changed, moved, appeared or disappeared damages both where it
+// was and where it is now, and so do entries whose order changed relative to
+// the ones present in both frames.
+pub(crate) fn diff(prev: &[(DrawKey, DrawSig)], next: &[(DrawKey, DrawSig)]) -> Option<Rect> {
+    let prev_sigs: HashMap<DrawKey, DrawSig> = prev.iter().copied().collect();
+    let next_sigs: HashMap<DrawKey, DrawSig> = next.iter().copied().collect();
+
+    let mut damaged = Vec::new();
+    for (key, old) in prev {
+        match next_sigs.get(key) {
+            Some(new) if new == old => {}
+            Some(new) => damaged.extend([old, new]),
+            None => damaged.push(old),
+        }
+    }
+    for (key, new) in next {
+        if !prev_sigs.contains_key(key) {
+            damaged.push(new);
+        }
+    }
+
+    let kept_prev = prev.iter().filter(|(k, _)| next_sigs.contains_key(k));
+    let kept_next = next.iter().filter(|(k, _)| prev_sigs.contains_key(k));
+    for ((old_key, old), (new_key, new)) in kept_prev.zip(kept_next) {
+        if old_key != new_key {
+            damaged.extend([old, new, &prev_sigs[new_key], &next_sigs[old_key]]);
+        }
+    }
+
+    damaged
+        .into_iter()
+        .filter_map(|sig| sig.bounds)
+        .reduce(|a, b| a.union(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::*;
+
+    fn rect(x: f32) -> DrawSig {
+        let inst = RectInstance {
+            pos: [x, 0.0],
+            size: [10.0, 10.0],
+            color: [1.0; 4],
+            radius: 0.0,
+            z: 0.0,
+            _pad: 0.0,
+        };
+        DrawSig::rect(&inst, None)
+    }
+
+    fn list(entries: &[(NodeId, f32)]) -> Vec<(DrawKey, DrawSig)> {
+        entries
+            .iter()
+            .map(|&(owner, x)| {
+                let key = DrawKey {
+                    owner: Some(owner),
+                    index: 0,
+                };
+                (key, rect(x))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_list_has_no_damage() {
+        let (a, b) = (NodeId::new(), NodeId::new());
+        let frame = list(&[(a, 0.0), (b, 20.0)]);
+        assert_eq!(diff(&frame, &frame), None);
+    }
+
+    #[test]
+    fn inserting_at_the_front_only_damages_the_new_entry() {
+        let (a, b, c, new) = (NodeId::new(), NodeId::new(), NodeId::new(), NodeId::new());
+        let prev = list(&[(a, 100.0), (b, 200.0), (c, 300.0)]);
+        let next = list(&[(new, 0.0), (a, 100.0), (b, 200.0), (c, 300.0)]);
+
+        let damage = diff(&prev, &next).unwrap();
+        assert_eq!(damage, rect(0.0).bounds.unwrap());
+        assert!(damage.origin.x + damage.size.x < 100.0);
+    }
+
+    #[test]
+    fn moved_and_removed_entries_damage_both_places() {
+        let (a, b) = (NodeId::new(), NodeId::new());
+        let prev = list(&[(a, 0.0), (b, 50.0)]);
+        let next = list(&[(a, 100.0)]);
+
+        let damage = diff(&prev, &next).unwrap();
+        assert!(damage.contains(vec2(5.0, 5.0)));
+        assert!(damage.contains(vec2(55.0, 5.0)));
+        assert!(damage.contains(vec2(105.0, 5.0)));
+    }
+
+    #[test]
+    fn reordering_overlapping_entries_damages_them() {
+        let (a, b, c) = (NodeId::new(), NodeId::new(), NodeId::new());
+        let prev = list(&[(a, 0.0), (b, 5.0), (c, 200.0)]);
+        let next = list(&[(b, 5.0), (a, 0.0), (c, 200.0)]);
+
+        let damage = diff(&prev, &next).unwrap();
+        assert!(damage.contains(vec2(1.0, 1.0)));
+        assert!(!damage.contains(vec2(205.0, 5.0)));
+    }
+}
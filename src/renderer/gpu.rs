@@ -1,5 +1,20 @@
-use anyhow::anyhow;
-use wgpu::{Adapter, Device, Instance, Queue};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Result, anyhow};
+use image::RgbaImage;
+use wgpu::util::DeviceExt;
+use wgpu::{Adapter, Device, Instance, Queue, TextureFormat};
+
+use super::RenderSurface;
+use super::atlas::GlyphAtlas;
+use super::backend::{
+    DAMAGE_FLASH, DrawKind, FrameData, RenderBackend, intersect_scissor, physical_damage,
+    physical_scissor,
+};
+use super::primatives::{CircleInstance, GlyphInstance, LineInstance, RectInstance};
+
+const START_CAPACITY: usize = 4 * 1024;
 
 pub struct GpuContext {
     pub instance: Instance,
@@ -27,3 +42,816 @@ impl GpuContext {
         })
     }
 }
+
+struct InstanceBuffer<T> {
+    buf: wgpu::Buffer,
+    capacity: usize,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T: bytemuck::Pod + bytemuck::Zeroable> InstanceBuffer<T> {
+    fn new(device: &wgpu::Device, usage: wgpu::BufferUsages) -> Self {
+        let buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("instance-buf"),
+            size: (START_CAPACITY * std::mem::size_of::<T>()) as _,
+            usage,
+            mapped_at_creation: false,
+        });
+        Self {
+            buf,
+            capacity: START_CAPACITY,
+            _marker: Default::default(),
+        }
+    }
+
+    fn ensure_capacity(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        required: usize,
+        usage: wgpu::BufferUsages,
+    ) {
+        if required <= self.capacity {
+            return;
+        }
+
+        let old_capacity = self.capacity;
+        while self.capacity < required {
+            self.capacity *= 2;
+        }
+
+        let new_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("instance-buf (grown)"),
+            size: (self.capacity * std::mem::size_of::<T>()) as _,
+            usage,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("buffer-copy-enc"),
+        });
+        encoder.copy_buffer_to_buffer(
+            &self.buf,
+            0,
+            &new_buf,
+            0,
+            (old_capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.buf = new_buf;
+    }
+
+    // Reallocates a buffer that has become mostly unused after the pool was
+    // compacted, re-uploading the whole pool into the smaller one.
+    fn shrink_to_fit(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pool: &[T],
+        usage: wgpu::BufferUsages,
+    ) {
+        if self.capacity <= START_CAPACITY || pool.len() * 4 > self.capacity {
+            return;
+        }
+
+        self.capacity = pool.len().next_power_of_two().max(START_CAPACITY);
+        self.buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("instance-buf (shrunk)"),
+            size: (self.capacity * std::mem::size_of::<T>()) as _,
+            usage,
+            mapped_at_creation: false,
+        });
+        if !pool.is_empty() {
+            queue.write_buffer(&self.buf, 0, bytemuck::cast_slice(pool));
+        }
+    }
+
+    // Replaces the buffer contents with `data`, for per-frame instance lists
+    fn upload_all(&mut self, queue: &wgpu::Queue, data: &[T]) {
+        if !data.is_empty() {
+            queue.write_buffer(&self.buf, 0, bytemuck::cast_slice(data));
+        }
+    }
+
+    fn upload_one(&mut self, queue: &wgpu::Queue, index: usize, val: &T) {
+        let offset = (index * std::mem::size_of::<T>()) as wgpu::BufferAddress;
+        queue.write_buffer(&self.buf, offset, bytemuck::bytes_of(val));
+    }
+}
+
+fn make_pipeline(
+    device: &wgpu::Device,
+    src: &'static str,
+    label: &'static str,
+    bind_layouts: &[&wgpu::BindGroupLayout],
+    v_layout: wgpu::VertexBufferLayout<'static>,
+    surface_fmt: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(src.into()),
+    });
+    let pipe_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("pipe-layout"),
+        bind_group_layouts: bind_layouts,
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&pipe_layout),
+        vertex: wgpu::VertexState {
+            module: &module,
+            entry_point: "vs_main",
+            buffers: &[v_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &module,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: surface_fmt,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+const INSTANCE_USAGE: wgpu::BufferUsages = wgpu::BufferUsages::VERTEX
+    .union(wgpu::BufferUsages::COPY_DST)
+    .union(wgpu::BufferUsages::COPY_SRC);
+
+struct AtlasTexture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+}
+
+// Offscreen copy of the last frame. Damaged regions are redrawn into it and
+// the whole texture is then copied to the surface.
+struct FrameTarget {
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    size: (u32, u32),
+}
+
+pub struct GpuBackend<'w> {
+    gpu: GpuContext,
+    surface: RenderSurface<'w>,
+
+    screen_buf: wgpu::Buffer,
+    screen_bind: wgpu::BindGroup,
+
+    image_pipe: wgpu::RenderPipeline,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_cache: HashMap<String, Arc<wgpu::BindGroup>>,
+
+    rect_pipe: wgpu::RenderPipeline,
+    line_pipe: wgpu::RenderPipeline,
+    circle_pipe: wgpu::RenderPipeline,
+    text_pipe: wgpu::RenderPipeline,
+
+    atlas: Option<AtlasTexture>,
+    atlas_sampler: wgpu::Sampler,
+    frame_target: Option<FrameTarget>,
+
+    rect_ibuf: InstanceBuffer<RectInstance>,
+    line_ibuf: InstanceBuffer<LineInstance>,
+    circle_ibuf: InstanceBuffer<CircleInstance>,
+    glyph_ibuf: InstanceBuffer<GlyphInstance>,
+}
+
+impl<'w> GpuBackend<'w> {
+    pub async fn new(window: &'w winit::window::Window) -> Result<Self> {
+        let gpu = GpuContext::new().await?;
+        let surf = RenderSurface::new(&gpu, window)?;
+        let size = window.inner_size();
+        let surface_fmt = surf.format();
+
+        let screen_buf = gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("screen-uniform"),
+                contents: bytemuck::cast_slice(&[size.width as f32, size.height as f32]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        let screen_layout = gpu
+            .device
+            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("screen-layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let screen_bind = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("screen-bind"),
+            layout: &screen_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: screen_buf.as_entire_binding(),
+            }],
+        });
+
+        // Layout for textures
+        let texture_bind_group_layout =
+            gpu.device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    entries: &[
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Texture {
+                                multisampled: false,
+                                view_dimension: wgpu::TextureViewDimension::D2,
+                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            },
+                            count: None,
+                        },
+                        wgpu::BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: wgpu::ShaderStages::FRAGMENT,
+                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                            count: None,
+                        },
+                    ],
+                    label: Some("texture_bind_group_layout"),
+                });
+
+        let rect_pipeline = make_pipeline(
+            &gpu.device,
+            include_str!("shaders/rect.wgsl"),
+            "rect.wgsl",
+            &[&screen_layout],
+            RectInstance::layout(),
+            surface_fmt,
+        );
+        let line_pipeline = make_pipeline(
+            &gpu.device,
+            include_str!("shaders/line.wgsl"),
+            "line.wgsl",
+            &[&screen_layout],
+            LineInstance::layout(),
+            surface_fmt,
+        );
+        let circle_pipeline = make_pipeline(
+            &gpu.device,
+            include_str!("shaders/circle.wgsl"),
+            "circle.wgsl",
+            &[&screen_layout],
+            CircleInstance::layout(),
+            surface_fmt,
+        );
+        let image_pipeline = make_pipeline(
+            &gpu.device,
+            include_str!("shaders/image.wgsl"),
+            "image.wgsl",
+            &[&screen_layout, &texture_bind_group_layout],
+            RectInstance::layout(),
+            surface_fmt,
+        );
+
+        let text_pipeline = make_pipeline(
+            &gpu.device,
+            include_str!("shaders/text.wgsl"),
+            "text.wgsl",
+            &[&screen_layout, &texture_bind_group_layout],
+            GlyphInstance::layout(),
+            surface_fmt,
+        );
+
+        // Glyph quads map 1:1 onto atlas texels
+        let atlas_sampler = gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("glyph-atlas-sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let rect_ibuf = InstanceBuffer::<RectInstance>::new(&gpu.device, INSTANCE_USAGE);
+        let line_ibuf = InstanceBuffer::<LineInstance>::new(&gpu.device, INSTANCE_USAGE);
+        let circle_ibuf = InstanceBuffer::<CircleInstance>::new(&gpu.device, INSTANCE_USAGE);
+        let glyph_ibuf = InstanceBuffer::<GlyphInstance>::new(&gpu.device, INSTANCE_USAGE);
+
+        Ok(Self {
+            gpu,
+            surface: surf,
+            screen_buf,
+            screen_bind,
+            image_pipe: image_pipeline,
+            texture_bind_group_layout,
+            texture_cache: HashMap::new(),
+            rect_pipe: rect_pipeline,
+            line_pipe: line_pipeline,
+            circle_pipe: circle_pipeline,
+            text_pipe: text_pipeline,
+            atlas: None,
+            atlas_sampler,
+            frame_target: None,
+            rect_ibuf,
+            line_ibuf,
+            circle_ibuf,
+            glyph_ibuf,
+        })
+    }
+
+    pub fn surface_format(&self) -> TextureFormat {
+        self.surface.format()
+    }
+
+    // Mirrors the glyph atlas into a texture, recreating it when the atlas
+    // has grown and otherwise uploading only the region that changed.
+    fn sync_atlas(&mut self, atlas: &GlyphAtlas) {
+        let (width, height) = atlas.size();
+
+        if self
+            .atlas
+            .as_ref()
+            .is_none_or(|a| a.size != (width, height))
+        {
+            let texture = self.gpu.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("glyph-atlas"),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = self
+                .gpu
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.texture_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&self.atlas_sampler),
+                        },
+                    ],
+                    label: Some("glyph-atlas-bind-group"),
+                });
+
+            self.atlas = Some(AtlasTexture {
+                texture,
+                bind_group,
+                size: (width, height),
+            });
+            self.write_atlas(atlas, (0, 0, width, height));
+        } else if let Some(region) = atlas.dirty_region() {
+            self.write_atlas(atlas, region);
+        }
+    }
+
+    // Creates the offscreen frame texture, or recreates it after a resize.
+    // Returns true when the texture is new and has to be painted in full.
+    fn sync_frame_target(&mut self, width: u32, height: u32) -> bool {
+        if self
+            .frame_target
+            .as_ref()
+            .is_some_and(|t| t.size == (width, height))
+        {
+            return false;
+        }
+
+        let texture = self.gpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("frame-target"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.surface.format(),
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // The copy is texel for texel, so the atlas' nearest sampler fits
+        let bind_group = self
+            .gpu
+            .device
+            .create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.texture_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&self.atlas_sampler),
+                    },
+                ],
+                label: Some("frame-target-bind-group"),
+            });
+
+        self.frame_target = Some(FrameTarget {
+            view,
+            bind_group,
+            size: (width, height),
+        });
+        true
+    }
+
+    fn write_atlas(&self, atlas: &GlyphAtlas, (x0, y0, x1, y1): (u32, u32, u32, u32)) {
+        let Some(target) = &self.atlas else {
+            return;
+        };
+        let (width, _) = atlas.size();
+
+        self.gpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x: x0, y: y0, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &atlas.pixels()[((y0 * width + x0) * 4) as usize..],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(y1 - y0),
+            },
+            wgpu::Extent3d {
+                width: x1 - x0,
+                height: y1 - y0,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    fn texture_bind_group(&mut self, key: &str, img: &RgbaImage) -> Arc<wgpu::BindGroup> {
+        if let Some(bg) = self.texture_cache.get(key) {
+            return bg.clone();
+        }
+
+        let (width, height) = img.dimensions();
+        let texture_size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = self.gpu.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(key),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        self.gpu.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            img,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * width),
+                rows_per_image: Some(height),
+            },
+            texture_size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = self.gpu.device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group = Arc::new(
+            self.gpu
+                .device
+                .create_bind_group(&wgpu::BindGroupDescriptor {
+                    layout: &self.texture_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(&sampler),
+                        },
+                    ],
+                    label: Some("texture_bind_group"),
+                }),
+        );
+
+        self.texture_cache
+            .insert(key.to_string(), bind_group.clone());
+        bind_group
+    }
+}
+
+impl RenderBackend for GpuBackend<'_> {
+    fn size(&self) -> (u32, u32) {
+        let size = self.surface.size();
+        (size.width, size.height)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.surface
+            .resize(&self.gpu, winit::dpi::PhysicalSize::new(width, height));
+        let data = [width as f32, height as f32];
+        self.gpu
+            .queue
+            .write_buffer(&self.screen_buf, 0, bytemuck::cast_slice(&data));
+    }
+
+    fn render(&mut self, frame: &FrameData) -> Result<()> {
+        self.rect_ibuf.ensure_capacity(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.rects.len(),
+            INSTANCE_USAGE,
+        );
+        self.rect_ibuf.shrink_to_fit(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.rects,
+            INSTANCE_USAGE,
+        );
+        self.line_ibuf.ensure_capacity(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.lines.len(),
+            INSTANCE_USAGE,
+        );
+        self.line_ibuf.shrink_to_fit(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.lines,
+            INSTANCE_USAGE,
+        );
+        self.circle_ibuf.ensure_capacity(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.circles.len(),
+            INSTANCE_USAGE,
+        );
+        self.circle_ibuf.shrink_to_fit(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.circles,
+            INSTANCE_USAGE,
+        );
+
+        self.glyph_ibuf.ensure_capacity(
+            &self.gpu.device,
+            &self.gpu.queue,
+            frame.glyphs.len(),
+            INSTANCE_USAGE,
+        );
+        if frame.damage.is_some() {
+            self.glyph_ibuf.upload_all(&self.gpu.queue, frame.glyphs);
+        }
+        self.sync_atlas(frame.atlas);
+
+        for (idx, inst) in frame.rect_updates {
+            self.rect_ibuf.upload_one(&self.gpu.queue, *idx, inst);
+        }
+        for (idx, inst) in frame.line_updates {
+            self.line_ibuf.upload_one(&self.gpu.queue, *idx, inst);
+        }
+        for (idx, inst) in frame.circle_updates {
+            self.circle_ibuf.upload_one(&self.gpu.queue, *idx, inst);
+        }
+
+        let image_bind_groups: Vec<Arc<wgpu::BindGroup>> = frame
+            .images
+            .iter()
+            .map(|draw| self.texture_bind_group(&draw.key, &draw.image))
+            .collect();
+
+        let image_instance_buffer: Option<wgpu::Buffer> = if !frame.images.is_empty() {
+            let image_instances: Vec<RectInstance> = frame
+                .images
+                .iter()
+                .map(|draw| RectInstance {
+                    pos: draw.rect.origin.to_array(),
+                    size: draw.rect.size.to_array(),
+                    ..Default::default()
+                })
+                .collect();
+
+            Some(
+                self.gpu
+                    .device
+                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("temp-image-instance-buf"),
+                        contents: bytemuck::cast_slice(&image_instances),
+                        usage: wgpu::BufferUsages::VERTEX,
+                    }),
+            )
+        } else {
+            None
+        };
+
+        let (width, height) = self.size();
+        let fresh = self.sync_frame_target(width, height);
+        let damage = if fresh {
+            Some((0, 0, width, height))
+        } else {
+            frame
+                .damage
+                .map(|d| physical_damage(d, frame.scale_factor, width, height))
+        }
+        .filter(|&(_, _, w, h)| w > 0 && h > 0);
+
+        // Full-target quads for clearing the damage, copying the frame to the
+        // surface and tinting the damage in debug mode
+        let screen = [width as f32, height as f32];
+        let overlay = [
+            RectInstance {
+                size: screen,
+                color: frame.clear_color.to_array(),
+                ..Default::default()
+            },
+            RectInstance {
+                size: screen,
+                ..Default::default()
+            },
+            RectInstance {
+                size: screen,
+                color: DAMAGE_FLASH.to_array(),
+                ..Default::default()
+            },
+        ];
+        let overlay_buf = self
+            .gpu
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("overlay-instance-buf"),
+                contents: bytemuck::cast_slice(&overlay),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+        let surface_frame = self.surface.get_current_texture()?;
+        let view = surface_frame
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let Some(target) = &self.frame_target else {
+            return Ok(());
+        };
+
+        let mut enc = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("main-enc"),
+            });
+
+        if let Some((dx, dy, dw, dh)) = damage {
+            let mut rp = enc.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("damage-pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            rp.set_bind_group(0, &self.screen_bind, &[]);
+
+            rp.set_scissor_rect(dx, dy, dw, dh);
+            rp.set_pipeline(&self.rect_pipe);
+            rp.set_vertex_buffer(0, overlay_buf.slice(..));
+            rp.draw(0..6, 0..1);
+
+            for batch in frame.batches {
+                let scissor = physical_scissor(batch.clip, frame.scale_factor, width, height);
+                let (x, y, w, h) = intersect_scissor(scissor, (dx, dy, dw, dh));
+                if w == 0 || h == 0 {
+                    continue;
+                }
+                rp.set_scissor_rect(x, y, w, h);
+
+                match batch.kind {
+                    DrawKind::Rect => {
+                        rp.set_pipeline(&self.rect_pipe);
+                        rp.set_vertex_buffer(0, self.rect_ibuf.buf.slice(..));
+                    }
+                    DrawKind::Line => {
+                        rp.set_pipeline(&self.line_pipe);
+                        rp.set_vertex_buffer(0, self.line_ibuf.buf.slice(..));
+                    }
+                    DrawKind::Circle => {
+                        rp.set_pipeline(&self.circle_pipe);
+                        rp.set_vertex_buffer(0, self.circle_ibuf.buf.slice(..));
+                    }
+                    DrawKind::Glyph => {
+                        let Some(atlas) = &self.atlas else {
+                            continue;
+                        };
+                        rp.set_pipeline(&self.text_pipe);
+                        rp.set_bind_group(1, &atlas.bind_group, &[]);
+                        rp.set_vertex_buffer(0, self.glyph_ibuf.buf.slice(..));
+                    }
+                    DrawKind::Image => {
+                        let Some(buffer) = &image_instance_buffer else {
+                            continue;
+                        };
+                        rp.set_pipeline(&self.image_pipe);
+                        rp.set_vertex_buffer(0, buffer.slice(..));
+
+                        for i in batch.ranges.iter().cloned().flatten() {
+                            rp.set_bind_group(1, &image_bind_groups[i as usize], &[]);
+                            rp.draw(0..6, i..i + 1);
+                        }
+                        continue;
+                    }
+                }
+
+                for range in &batch.ranges {
+                    rp.draw(0..6, range.clone());
+                }
+            }
+        }
+
+        {
+            let mut rp = enc.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("present-pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            rp.set_bind_group(0, &self.screen_bind, &[]);
+            rp.set_vertex_buffer(0, overlay_buf.slice(..));
+
+            rp.set_pipeline(&self.image_pipe);
+            rp.set_bind_group(1, &target.bind_group, &[]);
+            rp.draw(0..6, 1..2);
+
+            if frame.show_damage
+                && let Some((dx, dy, dw, dh)) = damage
+            {
+                rp.set_scissor_rect(dx, dy, dw, dh);
+                rp.set_pipeline(&self.rect_pipe);
+                rp.draw(0..6, 2..3);
+            }
+        }
+
+        self.gpu.queue.submit(Some(enc.finish()));
+        surface_frame.present();
+        Ok(())
+    }
+
+    fn gpu(&self) -> Option<&GpuContext> {
+        Some(&self.gpu)
+    }
+
+    fn surface_format(&self) -> Option<TextureFormat> {
+        Some(self.surface.format())
+    }
+}
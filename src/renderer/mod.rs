@@ -1,122 +1,106 @@
+pub mod atlas;
+pub mod backend;
+mod compact;
+mod damage;
 pub mod gpu;
 pub mod primatives;
+pub mod software;
 pub mod surface;
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::mem;
+use std::ops::Range;
 use std::sync::Arc;
+use std::time::Instant;
 
 use anyhow::Result;
-use cosmic_text::{Attrs, Buffer, Color, FontSystem, Metrics, Shaping, SwashCache};
+use atlas::GlyphAtlas;
+use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, Shaping, SwashCache};
+use damage::{DrawKey, DrawSig};
 use glam::{Vec2, Vec4};
-use primatives::{CircleInstance, LineInstance, RectInstance};
-use wgpu::util::DeviceExt;
-use wgpu::{Device, Queue, TextureFormat};
+use image::RgbaImage;
+use primatives::{CircleInstance, GlyphInstance, LineInstance, RectInstance};
 
-use crate::layout::Rect;
-pub use gpu::GpuContext;
+use crate::{layout::Rect, signals::NodeId};
+pub use backend::{DrawBatch, DrawKind, FrameData, ImageDraw, RenderBackend};
+pub use compact::SlotRemap;
+pub use gpu::{GpuBackend, GpuContext};
 pub use primatives::{Primative, RenderPrimative};
+pub use software::SoftwareBackend;
 pub use surface::RenderSurface;
 
-const START_CAPACITY: usize = 4 * 1024;
+const CLEAR_COLOR: Vec4 = Vec4::new(0.1, 0.1, 0.1, 1.0);
+// Frames between compactions of the instance pools
+const COMPACT_INTERVAL: u64 = 120;
 
-struct InstanceBuffer<T> {
-    buf: wgpu::Buffer,
-    capacity: usize,
-    _marker: std::marker::PhantomData<T>,
-}
-
-impl<T: bytemuck::Pod + bytemuck::Zeroable> InstanceBuffer<T> {
-    fn new(device: &wgpu::Device, usage: wgpu::BufferUsages) -> Self {
-        let buf = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("instance-buf"),
-            size: (START_CAPACITY * std::mem::size_of::<T>()) as _,
-            usage,
-            mapped_at_creation: false,
-        });
-        Self {
-            buf,
-            capacity: START_CAPACITY,
-            _marker: Default::default(),
-        }
-    }
-
-    fn ensure_capacity(
-        &mut self,
-        device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        required: usize,
-        usage: wgpu::BufferUsages,
-        _pool: &[T],
-    ) {
-        if required <= self.capacity {
-            return;
-        }
-
-        let old_capacity = self.capacity;
-        while self.capacity < required {
-            self.capacity *= 2;
-        }
+pub type RectId = usize;
+pub type LineId = usize;
+pub type CircId = usize;
 
-        let new_buf = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("instance-buf (grown)"),
-            size: (self.capacity * std::mem::size_of::<T>()) as _,
-            usage,
-            mapped_at_creation: false,
-        });
+// A paint call recorded during the frame; only recorded slots are drawn
+#[derive(Copy, Clone)]
+enum DrawItem {
+    Rect(RectId),
+    Line(LineId),
+    Circle(CircId),
+    Text(usize),
+    Image(usize),
+}
 
-        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("buffer-copy-enc"),
-        });
-        encoder.copy_buffer_to_buffer(
-            &self.buf,
-            0,
-            &new_buf,
-            0,
-            (old_capacity * std::mem::size_of::<T>()) as wgpu::BufferAddress,
-        );
-        queue.submit(Some(encoder.finish()));
+#[derive(Copy, Clone)]
+struct RecordedDraw {
+    item: DrawItem,
+    key: DrawKey,
+    clip: Option<Rect>,
+    z_index: i32,
+}
 
-        self.buf = new_buf;
-    }
+// The draws one node made in a frame, its descendants' included, and the
+// clip and z-index they were made under. A node whose subtree is unchanged
+// replays them instead of painting again.
+#[derive(Clone)]
+struct PaintRecord {
+    node: NodeId,
+    draws: Range<usize>,
+    clip: Option<Rect>,
+    z_index: i32,
+    // How many of the records after this one belong to descendants
+    nested: usize,
+}
 
-    fn upload_one(&mut self, queue: &wgpu::Queue, index: usize, val: &T) {
-        let offset = (index * std::mem::size_of::<T>()) as wgpu::BufferAddress;
-        queue.write_buffer(&self.buf, offset, bytemuck::bytes_of(val));
-    }
+// Rect and text slots handed to a node's immediate-mode draws, in call
+// order. They stay with the node while its paint is replayed.
+#[derive(Default)]
+struct ImmediateSlots {
+    rects: Vec<RectId>,
+    texts: Vec<usize>,
+    rect_calls: usize,
+    text_calls: usize,
 }
 
-pub type RectId = usize;
-pub type LineId = usize;
-pub type CircId = usize;
+// Batched form of a frame's draws, plus a signature per draw for diffing
+// against the previous frame
+struct DisplayList {
+    batches: Vec<DrawBatch>,
+    glyphs: Vec<GlyphInstance>,
+    sigs: Vec<(DrawKey, DrawSig)>,
+}
 
 struct TextData {
     primative: RenderPrimative,
-    glyph_rect_ids: Vec<RectId>,
+    glyphs: Vec<GlyphInstance>,
     is_dirty: bool,
 }
 
 pub struct Renderer<'a> {
-    gpu: GpuContext,
-    surface: RenderSurface<'a>,
-
-    screen_buf: wgpu::Buffer,
-    screen_bind: wgpu::BindGroup,
-
-    image_pipe: wgpu::RenderPipeline,
-    texture_bind_group_layout: wgpu::BindGroupLayout,
-    texture_cache: HashMap<String, Arc<wgpu::BindGroup>>,
-    frame_image_draws: Vec<(Rect, Arc<wgpu::BindGroup>)>,
-
-    rect_pipe: wgpu::RenderPipeline,
-    line_pipe: wgpu::RenderPipeline,
-    circle_pipe: wgpu::RenderPipeline,
+    backend: Box<dyn RenderBackend + 'a>,
 
-    rect_ibuf: InstanceBuffer<RectInstance>,
-    line_ibuf: InstanceBuffer<LineInstance>,
-    circle_ibuf: InstanceBuffer<CircleInstance>,
+    image_cache: HashMap<String, Arc<RgbaImage>>,
+    frame_image_draws: Vec<ImageDraw>,
 
     font_system: FontSystem,
     swash_cache: SwashCache,
+    atlas: GlyphAtlas,
     text_pool: Vec<TextData>,
 
     rect_pool: Vec<RectInstance>,
@@ -127,182 +111,65 @@ pub struct Renderer<'a> {
     line_dirty: Vec<(usize, LineInstance)>,
     circ_dirty: Vec<(usize, CircleInstance)>,
 
-    frame_rect_slots: Vec<usize>,
-    frame_text_ids: Vec<usize>,
-    rect_call_idx: usize,
-    text_call_idx: usize,
+    // Freed slots, reused lowest id first so the tail of each pool empties out
+    rect_free: BTreeSet<RectId>,
+    line_free: BTreeSet<LineId>,
+    circ_free: BTreeSet<CircId>,
+    text_free: BTreeSet<usize>,
+    frame_count: u64,
+    frame_started: Instant,
+
+    // Draws in paint order, each with the clip and z-index active for it
+    frame_draws: Vec<RecordedDraw>,
+    // Nodes being painted, innermost last, and how many draws each has made
+    // this frame; together they key the draws for damage tracking
+    paint_owners: Vec<NodeId>,
+    owner_draws: HashMap<Option<NodeId>, u32>,
+    // Nodes painted this frame in the order they began, and the ones still
+    // open, innermost last
+    paint_records: Vec<PaintRecord>,
+    open_records: Vec<usize>,
+
+    // Last frame's draws and records, for replaying unchanged subtrees
+    prev_draws: Vec<RecordedDraw>,
+    prev_image_draws: Vec<ImageDraw>,
+    prev_records: Vec<PaintRecord>,
+    prev_record_index: HashMap<NodeId, usize>,
+
+    immediate_slots: HashMap<Option<NodeId>, ImmediateSlots>,
+
+    // Display list of the last rendered frame and damage reported on top of
+    // what diffing against it finds
+    prev_sigs: Vec<(DrawKey, DrawSig)>,
+    extra_damage: Option<Rect>,
+    full_damage: bool,
+    debug_damage: bool,
 
     scissor_stack: Vec<Rect>,
+    z_stack: Vec<i32>,
     scale_factor: f32,
 }
 
 impl<'a> Renderer<'a> {
     pub async fn new(window: &'a winit::window::Window, scale_factor: f32) -> Result<Self> {
-        use primatives::{CircleInstance, LineInstance, RectInstance};
-
-        let gpu = GpuContext::new().await?;
-        let surf = RenderSurface::new(&gpu, window)?;
-        let size = window.inner_size();
-        let surface_fmt = surf.format();
-
-        let screen_buf = gpu
-            .device
-            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("screen-uniform"),
-                contents: bytemuck::cast_slice(&[size.width as f32, size.height as f32]),
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-            });
-
-        let screen_layout = gpu
-            .device
-            .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("screen-layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
-                    binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Buffer {
-                        ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: None,
-                    },
-                    count: None,
-                }],
-            });
-        let screen_bind = gpu.device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("screen-bind"),
-            layout: &screen_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: screen_buf.as_entire_binding(),
-            }],
-        });
-
-        // Layout for textures
-        let texture_bind_group_layout =
-            gpu.device
-                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                    entries: &[
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 0,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Texture {
-                                multisampled: false,
-                                view_dimension: wgpu::TextureViewDimension::D2,
-                                sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                            },
-                            count: None,
-                        },
-                        wgpu::BindGroupLayoutEntry {
-                            binding: 1,
-                            visibility: wgpu::ShaderStages::FRAGMENT,
-                            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                            count: None,
-                        },
-                    ],
-                    label: Some("texture_bind_group_layout"),
-                });
+        let backend = GpuBackend::new(window).await?;
+        Ok(Self::with_backend(Box::new(backend), scale_factor))
+    }
 
-        fn make_pipeline(
-            device: &wgpu::Device,
-            src: &'static str,
-            label: &'static str,
-            bind_layouts: &[&wgpu::BindGroupLayout],
-            v_layout: wgpu::VertexBufferLayout<'static>,
-            surface_fmt: wgpu::TextureFormat,
-        ) -> wgpu::RenderPipeline {
-            let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some(label),
-                source: wgpu::ShaderSource::Wgsl(src.into()),
-            });
-            let pipe_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("pipe-layout"),
-                bind_group_layouts: bind_layouts,
-                push_constant_ranges: &[],
-            });
-            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-                label: Some(label),
-                layout: Some(&pipe_layout),
-                vertex: wgpu::VertexState {
-                    module: &module,
-                    entry_point: "vs_main",
-                    buffers: &[v_layout],
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &module,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: surface_fmt,
-                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                }),
-                primitive: wgpu::PrimitiveState::default(),
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState::default(),
-                multiview: None,
-            })
-        }
-
-        let rect_pipeline = make_pipeline(
-            &gpu.device,
-            include_str!("shaders/rect.wgsl"),
-            "rect.wgsl",
-            &[&screen_layout],
-            RectInstance::layout(),
-            surface_fmt,
-        );
-        let line_pipeline = make_pipeline(
-            &gpu.device,
-            include_str!("shaders/line.wgsl"),
-            "line.wgsl",
-            &[&screen_layout],
-            LineInstance::layout(),
-            surface_fmt,
-        );
-        let circle_pipeline = make_pipeline(
-            &gpu.device,
-            include_str!("shaders/circle.wgsl"),
-            "circle.wgsl",
-            &[&screen_layout],
-            CircleInstance::layout(),
-            surface_fmt,
-        );
-        let image_pipeline = make_pipeline(
-            &gpu.device,
-            include_str!("shaders/image.wgsl"),
-            "image.wgsl",
-            &[&screen_layout, &texture_bind_group_layout],
-            RectInstance::layout(),
-            surface_fmt,
-        );
+    // Renders into an in-memory RGBA buffer instead of a window surface, so
+    // no GPU or display is required. Read the result with `read_pixels`.
+    pub fn headless(width: u32, height: u32, scale_factor: f32) -> Renderer<'static> {
+        Renderer::with_backend(Box::new(SoftwareBackend::new(width, height)), scale_factor)
+    }
 
-        let usage = wgpu::BufferUsages::VERTEX
-            | wgpu::BufferUsages::COPY_DST
-            | wgpu::BufferUsages::COPY_SRC;
-        let rect_ibuf = InstanceBuffer::<RectInstance>::new(&gpu.device, usage);
-        let line_ibuf = InstanceBuffer::<LineInstance>::new(&gpu.device, usage);
-        let circle_ibuf = InstanceBuffer::<CircleInstance>::new(&gpu.device, usage);
-
-        let font_system = FontSystem::new();
-        let swash_cache = SwashCache::new();
-
-        Ok(Self {
-            gpu,
-            surface: surf,
-            screen_buf,
-            screen_bind,
-            image_pipe: image_pipeline,
-            texture_bind_group_layout,
-            texture_cache: HashMap::new(),
+    pub fn with_backend(backend: Box<dyn RenderBackend + 'a>, scale_factor: f32) -> Self {
+        Self {
+            backend,
+            image_cache: HashMap::new(),
             frame_image_draws: Vec::new(),
-            rect_pipe: rect_pipeline,
-            line_pipe: line_pipeline,
-            circle_pipe: circle_pipeline,
-            rect_ibuf,
-            line_ibuf,
-            circle_ibuf,
-            font_system,
-            swash_cache,
+            font_system: FontSystem::new(),
+            swash_cache: SwashCache::new(),
+            atlas: GlyphAtlas::new(),
             text_pool: Vec::new(),
             rect_pool: Vec::new(),
             line_pool: Vec::new(),
@@ -310,115 +177,98 @@ impl<'a> Renderer<'a> {
             rect_dirty: Vec::new(),
             line_dirty: Vec::new(),
             circ_dirty: Vec::new(),
-            frame_rect_slots: Vec::new(),
-            frame_text_ids: Vec::new(),
-            rect_call_idx: 0,
-            text_call_idx: 0,
+            rect_free: BTreeSet::new(),
+            line_free: BTreeSet::new(),
+            circ_free: BTreeSet::new(),
+            text_free: BTreeSet::new(),
+            frame_count: 0,
+            frame_started: Instant::now(),
+            frame_draws: Vec::new(),
+            paint_owners: Vec::new(),
+            owner_draws: HashMap::new(),
+            paint_records: Vec::new(),
+            open_records: Vec::new(),
+            prev_draws: Vec::new(),
+            prev_image_draws: Vec::new(),
+            prev_records: Vec::new(),
+            prev_record_index: HashMap::new(),
+            immediate_slots: HashMap::new(),
+            prev_sigs: Vec::new(),
+            extra_damage: None,
+            full_damage: true,
+            debug_damage: false,
             scissor_stack: Vec::new(),
+            z_stack: Vec::new(),
             scale_factor,
-        })
+        }
     }
 
     pub fn draw_image(&mut self, path: &str, rect: Rect) {
-        if let Ok(bind_group) = self.get_texture_bind_group(path) {
-            self.frame_image_draws.push((rect, bind_group.clone()));
-        } else {
-            log::error!("Failed to load or get texture for path: {}", path);
-            self.draw_rect(rect.origin, rect.size, Vec4::new(1.0, 0.0, 1.0, 1.0));
+        match self.load_image(path) {
+            Ok(image) => {
+                self.record(DrawItem::Image(self.frame_image_draws.len()));
+                self.frame_image_draws.push(ImageDraw {
+                    rect,
+                    key: path.to_string(),
+                    image,
+                });
+            }
+            Err(e) => {
+                log::error!("Failed to load or get texture for path {}: {e}", path);
+                self.draw_rect(rect.origin, rect.size, Vec4::new(1.0, 0.0, 1.0, 1.0));
+            }
         }
     }
 
-    fn get_texture_bind_group(&mut self, path: &str) -> Result<Arc<wgpu::BindGroup>> {
-        if let Some(bg) = self.texture_cache.get(path) {
-            return Ok(bg.clone());
+    fn load_image(&mut self, path: &str) -> Result<Arc<RgbaImage>> {
+        if let Some(img) = self.image_cache.get(path) {
+            return Ok(img.clone());
         }
 
         log::info!("Loading texture: {}", path);
-        let img = image::open(path)?.to_rgba8();
-        let (width, height) = img.dimensions();
-        let texture_size = wgpu::Extent3d {
-            width,
-            height,
-            depth_or_array_layers: 1,
-        };
-
-        let texture = self.gpu.device.create_texture(&wgpu::TextureDescriptor {
-            label: Some(path),
-            size: texture_size,
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8UnormSrgb,
-            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-
-        self.gpu.queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            &img,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(4 * width),
-                rows_per_image: Some(height),
-            },
-            texture_size,
-        );
-
-        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let sampler = self.gpu.device.create_sampler(&wgpu::SamplerDescriptor {
-            address_mode_u: wgpu::AddressMode::ClampToEdge,
-            address_mode_v: wgpu::AddressMode::ClampToEdge,
-            address_mode_w: wgpu::AddressMode::ClampToEdge,
-            mag_filter: wgpu::FilterMode::Linear,
-            min_filter: wgpu::FilterMode::Nearest,
-            mipmap_filter: wgpu::FilterMode::Nearest,
-            ..Default::default()
-        });
-
-        let bind_group = Arc::new(
-            self.gpu
-                .device
-                .create_bind_group(&wgpu::BindGroupDescriptor {
-                    layout: &self.texture_bind_group_layout,
-                    entries: &[
-                        wgpu::BindGroupEntry {
-                            binding: 0,
-                            resource: wgpu::BindingResource::TextureView(&view),
-                        },
-                        wgpu::BindGroupEntry {
-                            binding: 1,
-                            resource: wgpu::BindingResource::Sampler(&sampler),
-                        },
-                    ],
-                    label: Some("texture_bind_group"),
-                }),
-        );
-
-        self.texture_cache
-            .insert(path.to_string(), bind_group.clone());
-        Ok(bind_group)
+        let img = Arc::new(image::open(path)?.to_rgba8());
+        self.image_cache.insert(path.to_string(), img.clone());
+        Ok(img)
     }
 
     pub fn set_scale_factor(&mut self, new_factor: f32) {
         self.scale_factor = new_factor;
+        self.full_damage = true;
     }
 
     pub fn push_text(&mut self, p: RenderPrimative) -> usize {
-        let id = self.text_pool.len();
-        self.text_pool.push(TextData {
+        let data = TextData {
             primative: p,
-            glyph_rect_ids: Vec::new(),
+            glyphs: Vec::new(),
             is_dirty: true,
-        });
+        };
+
+        let id = match self.text_free.pop_first() {
+            Some(id) => {
+                self.text_pool[id] = data;
+                id
+            }
+            None => {
+                self.text_pool.push(data);
+                self.text_pool.len() - 1
+            }
+        };
+        self.record(DrawItem::Text(id));
         id
     }
 
+    pub fn free_text(&mut self, id: usize) {
+        if !self.text_free.insert(id) {
+            return;
+        }
+
+        self.text_pool[id].glyphs = Vec::new();
+        self.text_pool[id].primative = RenderPrimative::text("", Vec2::ZERO, Vec4::ZERO, 0.0);
+        self.text_pool[id].is_dirty = false;
+    }
+
     pub fn update_text(&mut self, id: usize, p: RenderPrimative) {
+        self.record(DrawItem::Text(id));
         if self.text_pool[id].primative != p {
             self.text_pool[id].primative = p;
             self.text_pool[id].is_dirty = true;
@@ -426,13 +276,30 @@ impl<'a> Renderer<'a> {
     }
 
     pub fn alloc_rect(&mut self) -> RectId {
+        // Freed slots were already reset to an invisible default
+        if let Some(id) = self.rect_free.pop_first() {
+            return id;
+        }
+
         let id = self.rect_pool.len();
         self.rect_pool.push(RectInstance::default());
         self.rect_dirty.push((id, self.rect_pool[id]));
         id
     }
 
+    pub fn free_rect(&mut self, id: RectId) {
+        if self.rect_free.insert(id) {
+            self.write_rect(id, RectInstance::default());
+        }
+    }
+
+    // Sets the slot's instance and draws it this frame under the current clip
     pub fn update_rect(&mut self, id: RectId, data: RectInstance) {
+        self.write_rect(id, data);
+        self.record(DrawItem::Rect(id));
+    }
+
+    fn write_rect(&mut self, id: RectId, data: RectInstance) {
         if self.rect_pool[id] != data {
             self.rect_pool[id] = data;
             self.rect_dirty.push((id, data));
@@ -440,13 +307,30 @@ impl<'a> Renderer<'a> {
     }
 
     pub fn alloc_line(&mut self) -> LineId {
+        // Freed slots were already reset to an invisible default
+        if let Some(id) = self.line_free.pop_first() {
+            return id;
+        }
+
         let id = self.line_pool.len();
         self.line_pool.push(LineInstance::default());
         self.line_dirty.push((id, self.line_pool[id]));
         id
     }
 
+    pub fn free_line(&mut self, id: LineId) {
+        if self.line_free.insert(id) {
+            self.write_line(id, LineInstance::default());
+        }
+    }
+
+    // Sets the slot's instance and draws it this frame under the current clip
     pub fn update_line(&mut self, id: LineId, data: LineInstance) {
+        self.write_line(id, data);
+        self.record(DrawItem::Line(id));
+    }
+
+    fn write_line(&mut self, id: LineId, data: LineInstance) {
         if self.line_pool[id] != data {
             self.line_pool[id] = data;
             self.line_dirty.push((id, data));
@@ -454,37 +338,83 @@ impl<'a> Renderer<'a> {
     }
 
     pub fn alloc_circle(&mut self) -> CircId {
+        // Freed slots were already reset to an invisible default
+        if let Some(id) = self.circ_free.pop_first() {
+            return id;
+        }
+
         let id = self.circ_pool.len();
         self.circ_pool.push(CircleInstance::default());
         self.circ_dirty.push((id, self.circ_pool[id]));
         id
     }
 
+    pub fn free_circle(&mut self, id: CircId) {
+        if self.circ_free.insert(id) {
+            self.write_circle(id, CircleInstance::default());
+        }
+    }
+
+    // Sets the slot's instance and draws it this frame under the current clip
     pub fn update_circle(&mut self, id: CircId, data: CircleInstance) {
+        self.write_circle(id, data);
+        self.record(DrawItem::Circle(id));
+    }
+
+    fn write_circle(&mut self, id: CircId, data: CircleInstance) {
         if self.circ_pool[id] != data {
             self.circ_pool[id] = data;
             self.circ_dirty.push((id, data));
         }
     }
 
-    pub fn surface_format(&self) -> TextureFormat {
-        self.surface.format()
+    pub fn resize(&mut self, new: winit::dpi::PhysicalSize<u32>) {
+        self.backend.resize(new.width, new.height);
+        self.full_damage = true;
     }
 
-    pub fn resize(&mut self, new: winit::dpi::PhysicalSize<u32>) {
-        self.surface.resize(&self.gpu, new);
-        let data = [new.width as f32, new.height as f32];
-        self.gpu
-            .queue
-            .write_buffer(&self.screen_buf, 0, bytemuck::cast_slice(&data));
+    // Marks a logical region for repainting next frame. Draws that changed
+    // are found automatically; this is for content the renderer can't see
+    // change, such as an image file replaced on disk.
+    pub fn add_damage(&mut self, rect: Rect) {
+        self.extra_damage = Some(match self.extra_damage {
+            Some(d) => d.union(&rect),
+            None => rect,
+        });
+    }
+
+    // Repaints the whole target next frame
+    pub fn invalidate(&mut self) {
+        self.full_damage = true;
+    }
+
+    // Tints the regions repainted each frame so damage tracking can be
+    // checked by eye. The tint is cleared on the following frame.
+    pub fn set_debug_damage(&mut self, enabled: bool) {
+        self.debug_damage = enabled;
+        self.full_damage = true;
     }
 
-    fn blit_text(
+    pub fn size(&self) -> winit::dpi::PhysicalSize<u32> {
+        let (width, height) = self.backend.size();
+        winit::dpi::PhysicalSize::new(width, height)
+    }
+
+    // Pixels of the last rendered frame; only backends that can read back
+    // (such as the headless one) return a buffer.
+    pub fn read_pixels(&self) -> Option<RgbaImage> {
+        self.backend.read_pixels()
+    }
+
+    // Shapes a text primative and turns every glyph into an atlas quad,
+    // rasterizing glyphs the atlas hasn't seen yet. Glyph positions keep their
+    // fractional part through the subpixel bin in the cache key.
+    fn layout_text(
         prim: &RenderPrimative,
         font_system: &mut FontSystem,
         swash: &mut SwashCache,
-        out: &mut Vec<RectInstance>,
-    ) {
+        atlas: &mut GlyphAtlas,
+    ) -> Vec<GlyphInstance> {
         let RenderPrimative::Text {
             text,
             position,
@@ -492,234 +422,366 @@ impl<'a> Renderer<'a> {
             size,
         } = prim
         else {
-            return;
+            return Vec::new();
         };
 
         let metrics = Metrics::new(*size, size * 1.2);
-        let mut buf = cosmic_text::Buffer::new(font_system, metrics);
-        let mut buf = buf.borrow_with(font_system);
+        let mut buf = Buffer::new(font_system, metrics);
+        buf.set_text(font_system, text, &Attrs::new(), Shaping::Advanced);
+        buf.shape_until_scroll(font_system, true);
+
+        let mut glyphs = Vec::new();
+        for run in buf.layout_runs() {
+            for glyph in run.glyphs {
+                let physical = glyph.physical((position.x, position.y), 1.0);
+                let Some(entry) = atlas.get_or_insert(font_system, swash, physical.cache_key)
+                else {
+                    continue;
+                };
+
+                let color = match glyph.color_opt {
+                    Some(c) => Vec4::new(
+                        c.r() as f32 / 255.0,
+                        c.g() as f32 / 255.0,
+                        c.b() as f32 / 255.0,
+                        c.a() as f32 / 255.0,
+                    ),
+                    None => *color,
+                };
+                let x = physical.x + entry.left;
+                let y = run.line_y as i32 + physical.y - entry.top;
+
+                glyphs.push(GlyphInstance {
+                    pos: [x as f32, y as f32],
+                    size: [entry.width as f32, entry.height as f32],
+                    uv: [
+                        entry.x as f32,
+                        entry.y as f32,
+                        entry.width as f32,
+                        entry.height as f32,
+                    ],
+                    color: color.to_array(),
+                    is_color: if entry.is_color { 1.0 } else { 0.0 },
+                    _pad: [0.0; 3],
+                });
+            }
+        }
+        glyphs
+    }
 
-        buf.set_text(text, &Attrs::new(), Shaping::Advanced);
-        buf.shape_until_scroll(true);
+    fn relayout_dirty_text(&mut self) {
+        for text in self.text_pool.iter_mut().filter(|t| t.is_dirty) {
+            text.glyphs = Renderer::layout_text(
+                &text.primative,
+                &mut self.font_system,
+                &mut self.swash_cache,
+                &mut self.atlas,
+            );
+            text.is_dirty = false;
+        }
+    }
 
-        let fg = Color::rgba(
-            (color.x * 255.0) as u8,
-            (color.y * 255.0) as u8,
-            (color.z * 255.0) as u8,
-            (color.w * 255.0) as u8,
-        );
+    fn record(&mut self, item: DrawItem) {
+        let owner = self.paint_owners.last().copied();
+        let count = self.owner_draws.entry(owner).or_default();
+        let key = DrawKey {
+            owner,
+            index: *count,
+        };
+        *count += 1;
 
-        buf.draw(swash, fg, |x, y, w, h, rgba| {
-            let pos = *position + Vec2::new(x as f32, y as f32);
-            out.push(RectInstance {
-                pos: pos.to_array(),
-                size: [w as f32, h as f32],
-                color: [
-                    rgba.r() as f32 / 255.0,
-                    rgba.g() as f32 / 255.0,
-                    rgba.b() as f32 / 255.0,
-                    rgba.a() as f32 / 255.0,
-                ],
-                radius: 0.0,
-                z: 0.0,
-                _pad: 0.0,
-            });
+        self.frame_draws.push(RecordedDraw {
+            item,
+            key,
+            clip: self.scissor_stack.last().copied(),
+            z_index: self.z_stack.last().copied().unwrap_or(0),
         });
     }
 
     pub fn begin_frame(&mut self) {
-        self.rect_dirty.clear();
-        self.line_dirty.clear();
-        self.circ_dirty.clear();
-        self.frame_image_draws.clear();
-        self.rect_call_idx = 0;
-        self.text_call_idx = 0;
+        self.frame_started = Instant::now();
+        self.prev_image_draws = mem::take(&mut self.frame_image_draws);
+        self.prev_draws = mem::take(&mut self.frame_draws);
+        self.prev_records = mem::take(&mut self.paint_records);
+        self.prev_record_index = (self.prev_records.iter().enumerate())
+            .map(|(index, record)| (record.node, index))
+            .collect();
+        self.paint_owners.clear();
+        self.open_records.clear();
+        self.owner_draws.clear();
+        for slots in self.immediate_slots.values_mut() {
+            slots.rect_calls = 0;
+            slots.text_calls = 0;
+        }
     }
 
     pub fn end_frame(&mut self) -> Result<()> {
-        let dirty_items: Vec<(usize, RenderPrimative, Vec<RectId>)> = self
-            .text_pool
-            .iter()
-            .enumerate()
-            .filter(|(_, t)| t.is_dirty)
-            .map(|(i, t)| (i, t.primative.clone(), t.glyph_rect_ids.clone()))
-            .collect();
-
-        for (index, primative, old_glyph_ids) in dirty_items {
-            let mut new_glyph_instances = Vec::new();
-            Renderer::blit_text(
-                &primative,
-                &mut self.font_system,
-                &mut self.swash_cache,
-                &mut new_glyph_instances,
-            );
-
-            let num_new = new_glyph_instances.len();
-            let num_old = old_glyph_ids.len();
-            let mut new_glyph_ids = Vec::with_capacity(num_new);
-
-            for i in 0..num_new.min(num_old) {
-                let rect_id = old_glyph_ids[i];
-                self.update_rect(rect_id, new_glyph_instances[i]);
-                new_glyph_ids.push(rect_id);
-            }
+        // Immediate-mode slots that weren't drawn into this frame
+        let (mut unused_rects, mut unused_texts) = (Vec::new(), Vec::new());
+        self.immediate_slots.retain(|_, slots| {
+            unused_rects.extend(slots.rects.drain(slots.rect_calls..));
+            unused_texts.extend(slots.texts.drain(slots.text_calls..));
+            !slots.rects.is_empty() || !slots.texts.is_empty()
+        });
+        for id in unused_rects {
+            self.free_rect(id);
+        }
+        for id in unused_texts {
+            self.free_text(id);
+        }
 
-            if num_new > num_old {
-                for i in num_old..num_new {
-                    let rect_id = self.alloc_rect();
-                    self.update_rect(rect_id, new_glyph_instances[i]);
-                    new_glyph_ids.push(rect_id);
-                }
-            } else if num_new < num_old {
-                for i in num_new..num_old {
-                    let rect_id = old_glyph_ids[i];
-                    self.update_rect(rect_id, RectInstance::default());
-                }
+        let generation = self.atlas.generation();
+        self.relayout_dirty_text();
+        if self.atlas.generation() != generation {
+            // The atlas was cleared to make room, so every placement laid out
+            // before that point is stale
+            for text in &mut self.text_pool {
+                text.is_dirty = true;
             }
-
-            let text_data = &mut self.text_pool[index];
-            text_data.glyph_rect_ids = new_glyph_ids;
-            text_data.is_dirty = false;
+            self.relayout_dirty_text();
         }
 
-        let usage = wgpu::BufferUsages::VERTEX
-            | wgpu::BufferUsages::COPY_DST
-            | wgpu::BufferUsages::COPY_SRC;
+        self.frame_count += 1;
 
-        self.rect_ibuf.ensure_capacity(
-            &self.gpu.device,
-            &self.gpu.queue,
-            self.rect_pool.len(),
-            usage,
-            &self.rect_pool,
-        );
-        self.line_ibuf.ensure_capacity(
-            &self.gpu.device,
-            &self.gpu.queue,
-            self.line_pool.len(),
-            usage,
-            &self.line_pool,
-        );
-        self.circle_ibuf.ensure_capacity(
-            &self.gpu.device,
-            &self.gpu.queue,
-            self.circ_pool.len(),
-            usage,
-            &self.circ_pool,
-        );
+        let list = self.build_display_list();
+        let damage = self.take_damage(&list.sigs);
+        if self.debug_damage && damage.is_some() {
+            crate::scheduler::request_frame();
+        }
 
-        for (idx, inst) in self.rect_dirty.drain(..) {
-            self.rect_ibuf.upload_one(&self.gpu.queue, idx, &inst);
-        }
-        for (idx, inst) in self.line_dirty.drain(..) {
-            self.line_ibuf.upload_one(&self.gpu.queue, idx, &inst);
-        }
-        for (idx, inst) in self.circ_dirty.drain(..) {
-            self.circle_ibuf.upload_one(&self.gpu.queue, idx, &inst);
-        }
-
-        let image_instance_buffer: Option<wgpu::Buffer> = if !self.frame_image_draws.is_empty() {
-            let image_instances: Vec<RectInstance> = self
-                .frame_image_draws
-                .iter()
-                .map(|(rect, _)| RectInstance {
-                    pos: rect.origin.to_array(),
-                    size: rect.size.to_array(),
-                    ..Default::default()
-                })
-                .collect();
-
-            Some(
-                self.gpu
-                    .device
-                    .create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("temp-image-instance-buf"),
-                        contents: bytemuck::cast_slice(&image_instances),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    }),
-            )
-        } else {
-            None
+        let frame = FrameData {
+            rects: &self.rect_pool,
+            lines: &self.line_pool,
+            circles: &self.circ_pool,
+            rect_updates: &self.rect_dirty,
+            line_updates: &self.line_dirty,
+            circle_updates: &self.circ_dirty,
+            glyphs: &list.glyphs,
+            atlas: &self.atlas,
+            images: &self.frame_image_draws,
+            batches: &list.batches,
+            damage,
+            show_damage: self.debug_damage,
+            clear_color: CLEAR_COLOR,
+            scale_factor: self.scale_factor,
         };
+        if let Err(e) = self.backend.render(&frame) {
+            // Nothing is known about what reached the target, so keep the
+            // pending uploads and repaint everything next time
+            self.full_damage = true;
+            return Err(e);
+        }
 
-        let frame = self.surface.get_current_texture()?;
-        let view = frame
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.rect_dirty.clear();
+        self.line_dirty.clear();
+        self.circ_dirty.clear();
+        self.atlas.clear_dirty();
+        self.prev_sigs = list.sigs;
+        crate::scheduler::record_frame(self.frame_started);
+        Ok(())
+    }
 
-        let mut enc = self
-            .gpu
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("main-enc"),
-            });
+    // Attributes the draws made until `end_paint` to `node`, so damage
+    // tracking can match them up with the node's draws in the last frame
+    pub(crate) fn begin_paint(&mut self, node: NodeId) {
+        self.paint_owners.push(node);
+        self.open_records.push(self.paint_records.len());
+        self.paint_records.push(PaintRecord {
+            node,
+            draws: self.frame_draws.len()..self.frame_draws.len(),
+            clip: self.scissor_stack.last().copied(),
+            z_index: self.z_stack.last().copied().unwrap_or(0),
+            nested: 0,
+        });
+    }
 
+    pub(crate) fn end_paint(&mut self) {
+        self.paint_owners.pop();
+        if let Some(index) = self.open_records.pop() {
+            let nested = self.paint_records.len() - index - 1;
+            let record = &mut self.paint_records[index];
+            record.draws.end = self.frame_draws.len();
+            record.nested = nested;
+        }
+    }
+
+    // Draws what `node` and its descendants drew last frame again, in place
+    // of painting them. Only possible when they were painted last frame
+    // under the clip and z-index in effect now; returns whether it happened.
+    pub(crate) fn replay(&mut self, node: NodeId) -> bool {
+        let Some(&first) = self.prev_record_index.get(&node) else {
+            return false;
+        };
+        let record = &self.prev_records[first];
+        if record.clip != self.scissor_stack.last().copied()
+            || record.z_index != self.z_stack.last().copied().unwrap_or(0)
         {
-            let mut rp = enc.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("main-pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.1,
-                            b: 0.1,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
+            return false;
+        }
 
-            let size = self.surface.size();
-            let full_rect = Rect::new(
-                Vec2::ZERO,
-                glam::vec2(size.width as f32, size.height as f32),
-            );
-            let logical_rect = self.scissor_stack.last().unwrap_or(&full_rect);
+        let start = self.frame_draws.len();
+        for draw in &self.prev_draws[record.draws.clone()] {
+            let mut draw = *draw;
+            if let DrawItem::Image(index) = draw.item {
+                draw.item = DrawItem::Image(self.frame_image_draws.len());
+                self.frame_image_draws
+                    .push(self.prev_image_draws[index].clone());
+            }
+            self.frame_draws.push(draw);
+        }
 
-            let physical_x = (logical_rect.origin.x * self.scale_factor).round() as u32;
-            let physical_y = (logical_rect.origin.y * self.scale_factor).round() as u32;
-            let physical_w = (logical_rect.size.x * self.scale_factor).round() as u32;
-            let physical_h = (logical_rect.size.y * self.scale_factor).round() as u32;
+        let shift = |i: usize| i - record.draws.start + start;
+        for record in &self.prev_records[first..=first + record.nested] {
+            self.paint_records.push(PaintRecord {
+                draws: shift(record.draws.start)..shift(record.draws.end),
+                ..record.clone()
+            });
+            // The replayed draws still use the node's immediate-mode slots
+            if let Some(slots) = self.immediate_slots.get_mut(&Some(record.node)) {
+                slots.rect_calls = slots.rects.len();
+                slots.text_calls = slots.texts.len();
+            }
+        }
+        true
+    }
 
-            let physical_w = physical_w.min(size.width.saturating_sub(physical_x));
-            let physical_h = physical_h.min(size.height.saturating_sub(physical_y));
+    // Turns the recorded draws into batches, ordered by z-index and then by
+    // paint order. Consecutive draws of one kind under the same clip share a
+    // batch. Glyphs of the drawn texts are gathered into one list that the
+    // glyph batches index.
+    fn build_display_list(&self) -> DisplayList {
+        let mut batches = Vec::new();
+        let mut glyphs = Vec::new();
+        let mut sigs = Vec::new();
 
-            rp.set_scissor_rect(physical_x, physical_y, physical_w, physical_h);
+        let mut draws: Vec<&RecordedDraw> = self.frame_draws.iter().collect();
+        draws.sort_by_key(|d| d.z_index);
 
-            rp.set_bind_group(0, &self.screen_bind, &[]);
+        for draw in draws {
+            let clip = draw.clip;
+            if clip.is_some_and(|c| c.size.x <= 0.0 || c.size.y <= 0.0) {
+                continue;
+            }
 
-            rp.set_pipeline(&self.rect_pipe);
-            rp.set_vertex_buffer(0, self.rect_ibuf.buf.slice(..));
-            rp.draw(0..6, 0..self.rect_pool.len() as _);
+            match draw.item {
+                DrawItem::Rect(id) if id < self.rect_pool.len() => {
+                    push_draw(&mut batches, DrawKind::Rect, clip, id);
+                    sigs.push((draw.key, DrawSig::rect(&self.rect_pool[id], clip)));
+                }
+                DrawItem::Text(id) if id < self.text_pool.len() => {
+                    let text = &self.text_pool[id].glyphs;
+                    for glyph in text {
+                        push_draw(&mut batches, DrawKind::Glyph, clip, glyphs.len());
+                        glyphs.push(*glyph);
+                    }
+                    sigs.push((draw.key, DrawSig::text(text, clip)));
+                }
+                DrawItem::Line(id) if id < self.line_pool.len() => {
+                    push_draw(&mut batches, DrawKind::Line, clip, id);
+                    sigs.push((draw.key, DrawSig::line(&self.line_pool[id], clip)));
+                }
+                DrawItem::Circle(id) if id < self.circ_pool.len() => {
+                    push_draw(&mut batches, DrawKind::Circle, clip, id);
+                    sigs.push((draw.key, DrawSig::circle(&self.circ_pool[id], clip)));
+                }
+                DrawItem::Image(index) => {
+                    push_draw(&mut batches, DrawKind::Image, clip, index);
+                    let image = &self.frame_image_draws[index];
+                    sigs.push((draw.key, DrawSig::image(&image.key, image.rect, clip)));
+                }
+                _ => {}
+            }
+        }
 
-            rp.set_pipeline(&self.line_pipe);
-            rp.set_vertex_buffer(0, self.line_ibuf.buf.slice(..));
-            rp.draw(0..6, 0..self.line_pool.len() as _);
+        DisplayList {
+            batches,
+            glyphs,
+            sigs,
+        }
+    }
 
-            rp.set_pipeline(&self.circle_pipe);
-            rp.set_vertex_buffer(0, self.circle_ibuf.buf.slice(..));
-            rp.draw(0..6, 0..self.circ_pool.len() as _);
+    // Region to repaint this frame: everything whose draw changed since the
+    // last frame plus any damage added by hand, or the whole target after a
+    // resize.
+    fn take_damage(&mut self, sigs: &[(DrawKey, DrawSig)]) -> Option<Rect> {
+        let extra = self.extra_damage.take();
+        if std::mem::take(&mut self.full_damage) {
+            let (width, height) = self.backend.size();
+            let size = Vec2::new(width as f32, height as f32) / self.scale_factor;
+            return Some(Rect::new(Vec2::ZERO, size));
+        }
 
-            if let Some(buffer) = &image_instance_buffer {
-                rp.set_pipeline(&self.image_pipe);
-                rp.set_vertex_buffer(0, buffer.slice(..));
+        match (damage::diff(&self.prev_sigs, sigs), extra) {
+            (Some(a), Some(b)) => Some(a.union(&b)),
+            (a, b) => a.or(b),
+        }
+    }
 
-                for (i, (_, bind_group)) in self.frame_image_draws.iter().enumerate() {
-                    rp.set_bind_group(1, bind_group, &[]);
-                    rp.draw(0..6, i as u32..(i + 1) as u32);
-                }
+    // Whether enough frames have passed since the pools were last compacted
+    pub fn compaction_due(&self) -> bool {
+        self.frame_count.is_multiple_of(COMPACT_INTERVAL)
+    }
+
+    // Moves live slots down over freed ones so every pool is dense again and
+    // backends can shrink their buffers. Ids held outside the renderer are
+    // stale afterwards: pass the returned remap to whoever holds them, as
+    // `Node::remap_slots` does for a widget tree, before the next frame.
+    pub fn compact(&mut self) -> SlotRemap {
+        // Pending writes to freed slots only reset them, and they are gone now
+        self.rect_dirty
+            .retain(|(id, _)| !self.rect_free.contains(id));
+        self.line_dirty
+            .retain(|(id, _)| !self.line_free.contains(id));
+        self.circ_dirty
+            .retain(|(id, _)| !self.circ_free.contains(id));
+
+        let remap = SlotRemap {
+            rects: compact::compact_pool(&mut self.rect_pool, &mut self.rect_free),
+            lines: compact::compact_pool(&mut self.line_pool, &mut self.line_free),
+            circles: compact::compact_pool(&mut self.circ_pool, &mut self.circ_free),
+            texts: compact::compact_pool(&mut self.text_pool, &mut self.text_free),
+        };
+
+        for slots in self.immediate_slots.values_mut() {
+            for id in &mut slots.rects {
+                *id = remap.rect(*id);
+            }
+            for id in &mut slots.texts {
+                *id = remap.text(*id);
             }
         }
+        // The next frame replays these
+        for draw in &mut self.frame_draws {
+            draw.item = match draw.item {
+                DrawItem::Rect(id) => DrawItem::Rect(remap.rect(id)),
+                DrawItem::Line(id) => DrawItem::Line(remap.line(id)),
+                DrawItem::Circle(id) => DrawItem::Circle(remap.circle(id)),
+                DrawItem::Text(id) => DrawItem::Text(remap.text(id)),
+                DrawItem::Image(index) => DrawItem::Image(index),
+            };
+        }
 
-        self.gpu.queue.submit(Some(enc.finish()));
-        frame.present();
-        Ok(())
+        // Backends keep instances by slot, so moved ones are uploaded again
+        for (id, _) in &mut self.rect_dirty {
+            *id = remap.rect(*id);
+        }
+        for (id, _) in &mut self.line_dirty {
+            *id = remap.line(*id);
+        }
+        for (id, _) in &mut self.circ_dirty {
+            *id = remap.circle(*id);
+        }
+        let rect_pool = &self.rect_pool;
+        self.rect_dirty
+            .extend(remap.rects.values().map(|&id| (id, rect_pool[id])));
+        let line_pool = &self.line_pool;
+        self.line_dirty
+            .extend(remap.lines.values().map(|&id| (id, line_pool[id])));
+        let circ_pool = &self.circ_pool;
+        self.circ_dirty
+            .extend(remap.circles.values().map(|&id| (id, circ_pool[id])));
+
+        remap
     }
 
     pub fn push_scissor_rect(&mut self, rect: Rect) {
@@ -737,14 +799,26 @@ impl<'a> Renderer<'a> {
         self.scissor_stack.pop();
     }
 
+    // Draws recorded until the matching pop are layered by `z_index` rather
+    // than paint order: higher values go on top, equal values keep the order
+    // they were painted in.
+    pub fn push_z_index(&mut self, z_index: i32) {
+        self.z_stack.push(z_index);
+    }
+
+    pub fn pop_z_index(&mut self) {
+        self.z_stack.pop();
+    }
+
     pub fn draw_rounded_rect(&mut self, pos: Vec2, size: Vec2, radius: f32, colour: Vec4) {
-        if self.rect_call_idx == self.frame_rect_slots.len() {
-            let id = self.alloc_rect();
-            self.frame_rect_slots.push(id);
+        let owner = self.paint_owners.last().copied();
+        let mut slots = self.immediate_slots.remove(&owner).unwrap_or_default();
+        if slots.rect_calls == slots.rects.len() {
+            slots.rects.push(self.alloc_rect());
         }
-
-        let id = self.frame_rect_slots[self.rect_call_idx];
-        self.rect_call_idx += 1;
+        let id = slots.rects[slots.rect_calls];
+        slots.rect_calls += 1;
+        self.immediate_slots.insert(owner, slots);
 
         self.update_rect(
             id,
@@ -808,24 +882,124 @@ impl<'a> Renderer<'a> {
     }
 
     pub fn draw_text(&mut self, text: &str, pos: Vec2, color: Vec4, size: f32) {
-        if self.text_call_idx == self.frame_text_ids.len() {
-            let prim = RenderPrimative::text(text, pos, color, size);
-            let id = self.push_text(prim);
-            self.frame_text_ids.push(id);
+        let prim = RenderPrimative::text(text, pos, color, size);
+
+        let owner = self.paint_owners.last().copied();
+        let mut slots = self.immediate_slots.remove(&owner).unwrap_or_default();
+        if slots.text_calls == slots.texts.len() {
+            slots.texts.push(self.push_text(prim));
+        } else {
+            self.update_text(slots.texts[slots.text_calls], prim);
         }
+        slots.text_calls += 1;
+        self.immediate_slots.insert(owner, slots);
+    }
 
-        let id = self.frame_text_ids[self.text_call_idx];
-        self.text_call_idx += 1;
+    pub fn gpu(&self) -> Option<&GpuContext> {
+        self.backend.gpu()
+    }
 
-        let prim = RenderPrimative::text(text, pos, color, size);
-        self.update_text(id, prim);
+    // The accessors from before backends were pluggable; they panic on a
+    // backend without a GPU, such as `Renderer::headless`
+    #[deprecated(note = "use `gpu()`, which is None for headless renderers")]
+    pub fn device(&self) -> &wgpu::Device {
+        &self.gpu().expect("renderer has no GPU backend").device
+    }
+
+    #[deprecated(note = "use `gpu()`, which is None for headless renderers")]
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.gpu().expect("renderer has no GPU backend").queue
+    }
+
+    #[deprecated(note = "only GPU backends that present to a window have a surface format")]
+    pub fn surface_format(&self) -> wgpu::TextureFormat {
+        self.backend
+            .surface_format()
+            .expect("renderer has no window surface")
+    }
+}
+
+// Appends a draw to the last batch when kind and clip match, extending its
+// final range if the slot directly follows it.
+fn push_draw(batches: &mut Vec<DrawBatch>, kind: DrawKind, clip: Option<Rect>, id: usize) {
+    let id = id as u32;
+
+    if let Some(batch) = batches.last_mut()
+        && batch.kind == kind
+        && batch.clip == clip
+    {
+        match batch.ranges.last_mut() {
+            Some(range) if range.end == id => range.end += 1,
+            _ => batch.ranges.push(id..id + 1),
+        }
+        return;
+    }
+
+    batches.push(DrawBatch {
+        kind,
+        clip,
+        ranges: std::iter::once(id..id + 1).collect(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use anyhow::anyhow;
+    use glam::vec2;
+
+    use super::*;
+
+    // Records the damage of every frame and fails when told to
+    #[derive(Default)]
+    struct Log {
+        damage: Vec<Option<Rect>>,
+        fail_next: bool,
+    }
+
+    struct FlakyBackend(Rc<RefCell<Log>>);
+
+    impl RenderBackend for FlakyBackend {
+        fn size(&self) -> (u32, u32) {
+            (100, 100)
+        }
+
+        fn resize(&mut self, _width: u32, _height: u32) {}
+
+        fn render(&mut self, frame: &FrameData) -> Result<()> {
+            let mut log = self.0.borrow_mut();
+            log.damage.push(frame.damage);
+            if std::mem::take(&mut log.fail_next) {
+                return Err(anyhow!("surface lost"));
+            }
+            Ok(())
+        }
     }
 
-    pub fn device(&self) -> &Device {
-        &self.gpu.device
+    fn frame(ren: &mut Renderer, x: f32) -> Result<()> {
+        ren.begin_frame();
+        ren.draw_rect(vec2(x, 0.0), vec2(10.0, 10.0), Vec4::ONE);
+        ren.end_frame()
     }
 
-    pub fn queue(&self) -> &Queue {
-        &self.gpu.queue
+    #[test]
+    fn failed_frame_is_fully_repainted_next_time() {
+        let log = Rc::new(RefCell::new(Log::default()));
+        let mut ren = Renderer::with_backend(Box::new(FlakyBackend(log.clone())), 1.0);
+        let full = Rect::new(Vec2::ZERO, vec2(100.0, 100.0));
+
+        frame(&mut ren, 0.0).unwrap();
+        frame(&mut ren, 0.0).unwrap();
+
+        log.borrow_mut().fail_next = true;
+        assert!(frame(&mut ren, 50.0).is_err());
+        // Same display list as the failed frame, but it never made it out
+        frame(&mut ren, 50.0).unwrap();
+
+        let damage = &log.borrow().damage;
+        assert_eq!(damage.len(), 4);
+        assert_eq!(damage[1], None);
+        assert_eq!(damage[3], Some(full));
     }
 }
@@ -111,6 +111,37 @@ impl CircleInstance {
     }
 }
 
+// One textured quad per glyph, sampled from the glyph atlas. `uv` is the
+// glyph's rect inside the atlas in pixels so it survives the atlas growing.
+#[repr(C)]
+#[derive(Copy, Clone, Default, PartialEq, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphInstance {
+    pub pos: [f32; 2],
+    pub size: [f32; 2],
+    pub uv: [f32; 4],
+    pub color: [f32; 4],
+    pub is_color: f32,
+    pub _pad: [f32; 3],
+}
+
+impl GlyphInstance {
+    const ATTRS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x2,   // pos
+        1 => Float32x2,   // size
+        2 => Float32x4,   // uv
+        3 => Float32x4,   // color
+        4 => Float32      // is_color
+    ];
+
+    pub fn layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as _,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRS,
+        }
+    }
+}
+
 impl From<&RenderPrimative> for RectInstance {
     fn from(p: &RenderPrimative) -> Self {
         match p {
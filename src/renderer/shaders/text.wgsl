@@ -0,0 +1,48 @@
+@group(0) @binding(0) var<uniform> screen : vec2<f32>; // window size
+
+@group(1) @binding(0) var t_atlas : texture_2d<f32>;
+@group(1) @binding(1) var s_atlas : sampler;
+
+struct VertexOut {
+    @builtin(position) pos : vec4<f32>,
+    @location(0) uv        : vec2<f32>,   // atlas position in pixels
+    @location(1) color     : vec4<f32>,
+    @location(2) is_color  : f32,
+};
+
+@vertex
+fn vs_main(
+    @location(0) pos      : vec2<f32>,
+    @location(1) size     : vec2<f32>,
+    @location(2) uv_rect  : vec4<f32>,
+    @location(3) color    : vec4<f32>,
+    @location(4) is_color : f32,
+    @builtin(vertex_index) vi : u32
+) -> VertexOut {
+    let x = select(0.0, 1.0, vi == 1u || vi == 2u || vi == 4u);
+    let y = select(0.0, 1.0, vi == 2u || vi == 4u || vi == 5u);
+    let corner = vec2(x, y);
+
+    let p   = pos + corner * size;
+    let ndc = vec2(p.x / screen.x * 2.0 - 1.0,
+                   1.0 - p.y / screen.y * 2.0);
+
+    var o : VertexOut;
+    o.pos      = vec4(ndc, 0.0, 1.0);
+    o.uv       = uv_rect.xy + corner * uv_rect.zw;
+    o.color    = color;
+    o.is_color = is_color;
+    return o;
+}
+
+@fragment
+fn fs_main(in : VertexOut) -> @location(0) vec4<f32> {
+    let texel = textureSample(t_atlas, s_atlas, in.uv / vec2<f32>(textureDimensions(t_atlas)));
+
+    // Masks carry coverage in alpha and take the text colour; colour glyphs
+    // (emoji) keep their own colours and only inherit the opacity.
+    if (in.is_color > 0.5) {
+        return vec4(texel.rgb, texel.a * in.color.a);
+    }
+    return vec4(in.color.rgb, in.color.a * texel.a);
+}
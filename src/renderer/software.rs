@@ -0,0 +1,324 @@
+use anyhow::Result;
+use glam::{Vec2, Vec4, vec2};
+use image::{Rgba, RgbaImage};
+
+use super::atlas::GlyphAtlas;
+use super::backend::{
+    DAMAGE_FLASH, DrawKind, FrameData, RenderBackend, intersect_scissor, physical_damage,
+    physical_scissor,
+};
+use super::primatives::{CircleInstance, GlyphInstance, LineInstance, RectInstance};
+
+// Pixel bounds (exclusive max) that drawing is restricted to
+#[derive(Copy, Clone)]
+struct Clip {
+    x0: i32,
+    y0: i32,
+    x1: i32,
+    y1: i32,
+}
+
+impl Clip {
+    fn from_scissor((x, y, w, h): (u32, u32, u32, u32)) -> Self {
+        Self {
+            x0: x as i32,
+            y0: y as i32,
+            x1: (x + w) as i32,
+            y1: (y + h) as i32,
+        }
+    }
+
+    // Pixel rows/columns whose centres fall inside [min, max)
+    fn span(&self, min: Vec2, max: Vec2) -> Option<(i32, i32, i32, i32)> {
+        let x0 = ((min.x - 0.5).ceil() as i32).max(self.x0);
+        let y0 = ((min.y - 0.5).ceil() as i32).max(self.y0);
+        let x1 = ((max.x - 0.5).ceil() as i32).min(self.x1);
+        let y1 = ((max.y - 0.5).ceil() as i32).min(self.y1);
+
+        (x0 < x1 && y0 < y1).then_some((x0, y0, x1, y1))
+    }
+}
+
+// CPU rasterizer that mirrors the wgpu pipelines closely enough for tests and
+// batch rendering on machines without a GPU or display. Blending happens in
+// linear space and the result is encoded as sRGB, the same way the GPU path
+// renders into an sRGB surface. The pixel buffer persists between frames and
+// only the damaged region is repainted.
+pub struct SoftwareBackend {
+    width: u32,
+    height: u32,
+    pixels: Vec<Vec4>,
+    // Region tinted in `read_pixels` while damage debugging is on
+    flash: Option<Clip>,
+}
+
+impl SoftwareBackend {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![Vec4::ZERO; (width * height) as usize],
+            flash: None,
+        }
+    }
+
+    fn blend(&mut self, x: i32, y: i32, src: Vec4) {
+        if src.w <= 0.0 {
+            return;
+        }
+        let dst = &mut self.pixels[(y as u32 * self.width + x as u32) as usize];
+        let rgb = src.truncate() * src.w + dst.truncate() * (1.0 - src.w);
+        let a = src.w + dst.w * (1.0 - src.w);
+        *dst = rgb.extend(a);
+    }
+
+    fn fill_rect(&mut self, inst: &RectInstance, clip: Clip) {
+        let pos = Vec2::from_array(inst.pos);
+        let size = Vec2::from_array(inst.size);
+        let color = Vec4::from_array(inst.color);
+
+        if color.w <= 0.0 {
+            return;
+        }
+        let Some((x0, y0, x1, y1)) = clip.span(pos, pos + size) else {
+            return;
+        };
+
+        let half = size * 0.5 - Vec2::splat(inst.radius);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let p = vec2(x as f32 + 0.5, y as f32 + 0.5) - pos - size * 0.5;
+                let dist = sd_rounded_box(p, half, inst.radius);
+                let alpha = (0.5 - dist).clamp(0.0, 1.0);
+                self.blend(x, y, color * vec4_alpha(alpha));
+            }
+        }
+    }
+
+    // Nearest-texel copy of the glyph's atlas rect, matching the GPU sampler
+    fn draw_glyph(&mut self, inst: &GlyphInstance, atlas: &GlyphAtlas, clip: Clip) {
+        let pos = Vec2::from_array(inst.pos);
+        let size = Vec2::from_array(inst.size);
+        let color = Vec4::from_array(inst.color);
+        let [u, v, uw, vh] = inst.uv;
+
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return;
+        }
+        let Some((x0, y0, x1, y1)) = clip.span(pos, pos + size) else {
+            return;
+        };
+
+        let (atlas_w, atlas_h) = atlas.size();
+        let pixels = atlas.pixels();
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let local = (vec2(x as f32 + 0.5, y as f32 + 0.5) - pos) / size;
+                let tx = ((u + local.x * uw) as u32).min(atlas_w - 1);
+                let ty = ((v + local.y * vh) as u32).min(atlas_h - 1);
+                let i = ((ty * atlas_w + tx) * 4) as usize;
+                let texel = &pixels[i..i + 4];
+                let coverage = texel[3] as f32 / 255.0;
+
+                let src = if inst.is_color > 0.5 {
+                    Vec4::new(
+                        srgb_to_linear(texel[0] as f32 / 255.0),
+                        srgb_to_linear(texel[1] as f32 / 255.0),
+                        srgb_to_linear(texel[2] as f32 / 255.0),
+                        coverage * color.w,
+                    )
+                } else {
+                    color * vec4_alpha(coverage)
+                };
+                self.blend(x, y, src);
+            }
+        }
+    }
+
+    fn fill_line(&mut self, inst: &LineInstance, clip: Clip) {
+        let a = Vec2::from_array(inst.a);
+        let b = Vec2::from_array(inst.b);
+        let color = Vec4::from_array(inst.color);
+
+        let len = a.distance(b);
+        if color.w <= 0.0 || len <= 0.0 {
+            return;
+        }
+        let dir = (b - a) / len;
+        let perp = vec2(-dir.y, dir.x);
+        let offset = perp * inst.half_width;
+
+        let corners = [a + offset, a - offset, b + offset, b - offset];
+        let min = corners.iter().fold(corners[0], |m, c| m.min(*c));
+        let max = corners.iter().fold(corners[0], |m, c| m.max(*c));
+        let Some((x0, y0, x1, y1)) = clip.span(min, max) else {
+            return;
+        };
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let rel = vec2(x as f32 + 0.5, y as f32 + 0.5) - a;
+                let along = rel.dot(dir);
+                let across = rel.dot(perp).abs();
+                if (0.0..len).contains(&along) && across <= inst.half_width {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn fill_circle(&mut self, inst: &CircleInstance, clip: Clip) {
+        let center = Vec2::from_array(inst.center);
+        let color = Vec4::from_array(inst.color);
+
+        if color.w <= 0.0 || inst.radius <= 0.0 {
+            return;
+        }
+        let extent = Vec2::splat(inst.radius);
+        let Some((x0, y0, x1, y1)) = clip.span(center - extent, center + extent) else {
+            return;
+        };
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if vec2(x as f32 + 0.5, y as f32 + 0.5).distance(center) <= inst.radius {
+                    self.blend(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn blit_image(&mut self, rect: crate::layout::Rect, img: &RgbaImage, clip: Clip) {
+        let (img_w, img_h) = img.dimensions();
+        if img_w == 0 || img_h == 0 || rect.size.x <= 0.0 || rect.size.y <= 0.0 {
+            return;
+        }
+        let Some((x0, y0, x1, y1)) = clip.span(rect.origin, rect.origin + rect.size) else {
+            return;
+        };
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let uv = (vec2(x as f32 + 0.5, y as f32 + 0.5) - rect.origin) / rect.size;
+                let tx = ((uv.x * img_w as f32) as u32).min(img_w - 1);
+                let ty = ((uv.y * img_h as f32) as u32).min(img_h - 1);
+                let Rgba([r, g, b, a]) = *img.get_pixel(tx, ty);
+                let texel = Vec4::new(
+                    srgb_to_linear(r as f32 / 255.0),
+                    srgb_to_linear(g as f32 / 255.0),
+                    srgb_to_linear(b as f32 / 255.0),
+                    a as f32 / 255.0,
+                );
+                self.blend(x, y, texel);
+            }
+        }
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![Vec4::ZERO; (width * height) as usize];
+        self.flash = None;
+    }
+
+    fn render(&mut self, frame: &FrameData) -> Result<()> {
+        self.flash = None;
+        let Some(damage) = frame.damage else {
+            return Ok(());
+        };
+        let damage = physical_damage(damage, frame.scale_factor, self.width, self.height);
+        let area = Clip::from_scissor(damage);
+
+        for y in area.y0..area.y1 {
+            let row = (y as u32 * self.width) as usize;
+            self.pixels[row + area.x0 as usize..row + area.x1 as usize].fill(frame.clear_color);
+        }
+
+        for batch in frame.batches {
+            let scissor = physical_scissor(batch.clip, frame.scale_factor, self.width, self.height);
+            let clip = Clip::from_scissor(intersect_scissor(scissor, damage));
+            if clip.x0 >= clip.x1 || clip.y0 >= clip.y1 {
+                continue;
+            }
+
+            for i in batch.ranges.iter().cloned().flatten() {
+                let i = i as usize;
+                match batch.kind {
+                    DrawKind::Rect => self.fill_rect(&frame.rects[i], clip),
+                    DrawKind::Glyph => self.draw_glyph(&frame.glyphs[i], frame.atlas, clip),
+                    DrawKind::Line => self.fill_line(&frame.lines[i], clip),
+                    DrawKind::Circle => self.fill_circle(&frame.circles[i], clip),
+                    DrawKind::Image => {
+                        let draw = &frame.images[i];
+                        self.blit_image(draw.rect, &draw.image, clip);
+                    }
+                }
+            }
+        }
+
+        if frame.show_damage {
+            self.flash = Some(area);
+        }
+        Ok(())
+    }
+
+    fn read_pixels(&self) -> Option<RgbaImage> {
+        let mut out = RgbaImage::new(self.width, self.height);
+        for (i, (dst, src)) in out.pixels_mut().zip(&self.pixels).enumerate() {
+            let (x, y) = (
+                (i as u32 % self.width) as i32,
+                (i as u32 / self.width) as i32,
+            );
+            let src = match self.flash {
+                Some(f) if (f.x0..f.x1).contains(&x) && (f.y0..f.y1).contains(&y) => {
+                    let tint = DAMAGE_FLASH.truncate();
+                    (src.truncate() * (1.0 - DAMAGE_FLASH.w) + tint * DAMAGE_FLASH.w).extend(src.w)
+                }
+                _ => *src,
+            };
+            *dst = Rgba([
+                to_u8(linear_to_srgb(src.x)),
+                to_u8(linear_to_srgb(src.y)),
+                to_u8(linear_to_srgb(src.z)),
+                to_u8(src.w),
+            ]);
+        }
+        Some(out)
+    }
+}
+
+fn vec4_alpha(alpha: f32) -> Vec4 {
+    Vec4::new(1.0, 1.0, 1.0, alpha)
+}
+
+fn sd_rounded_box(p: Vec2, b: Vec2, r: f32) -> f32 {
+    let q = p.abs() - b;
+    q.max(Vec2::ZERO).length() + q.x.max(q.y).min(0.0) - r
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
@@ -0,0 +1,92 @@
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+// Counters for confirming how often the app actually renders
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    // Frames rendered since startup (or the last reset)
+    pub frames: u64,
+    // Times the event loop woke up, whether or not it rendered
+    pub wakeups: u64,
+    pub last_frame_at: Option<Instant>,
+    pub last_frame_time: Duration,
+}
+
+#[derive(Default)]
+struct Scheduler {
+    frame_requested: bool,
+    // Bumped by every request, so callers can tell whether one was made
+    requests: u64,
+    deadlines: Vec<Instant>,
+    stats: FrameStats,
+}
+
+thread_local! {
+    static SCHEDULER: RefCell<Scheduler> = RefCell::new(Scheduler::default());
+}
+
+// Asks for another frame as soon as the event loop gets to it, even if no
+// node is paint-dirty.
+pub fn request_frame() {
+    SCHEDULER.with(|s| {
+        let mut s = s.borrow_mut();
+        s.frame_requested = true;
+        s.requests += 1;
+    });
+}
+
+// Asks for a frame once `at` has passed; the event loop sleeps until then.
+// Widgets with time-based visuals (such as a blinking caret) use this from
+// `paint` to schedule their next change.
+pub fn request_frame_at(at: Instant) {
+    SCHEDULER.with(|s| {
+        let mut s = s.borrow_mut();
+        s.requests += 1;
+        if !s.deadlines.contains(&at) {
+            s.deadlines.push(at);
+        }
+    });
+}
+
+pub fn frame_stats() -> FrameStats {
+    SCHEDULER.with(|s| s.borrow().stats)
+}
+
+pub fn reset_frame_stats() {
+    SCHEDULER.with(|s| s.borrow_mut().stats = FrameStats::default());
+}
+
+// Consumes the pending request and any deadlines that have passed, returning
+// whether a frame is due at `now`.
+pub(crate) fn take_due(now: Instant) -> bool {
+    SCHEDULER.with(|s| {
+        let mut s = s.borrow_mut();
+        let before = s.deadlines.len();
+        s.deadlines.retain(|d| *d > now);
+        std::mem::take(&mut s.frame_requested) || s.deadlines.len() != before
+    })
+}
+
+// Number of frame requests made so far
+pub(crate) fn frame_requests() -> u64 {
+    SCHEDULER.with(|s| s.borrow().requests)
+}
+
+pub(crate) fn next_deadline() -> Option<Instant> {
+    SCHEDULER.with(|s| s.borrow().deadlines.iter().min().copied())
+}
+
+pub(crate) fn record_wakeup() {
+    SCHEDULER.with(|s| s.borrow_mut().stats.wakeups += 1);
+}
+
+pub(crate) fn record_frame(started: Instant) {
+    SCHEDULER.with(|s| {
+        let stats = &mut s.borrow_mut().stats;
+        stats.frames += 1;
+        stats.last_frame_at = Some(started);
+        stats.last_frame_time = started.elapsed();
+    });
+}
@@ -0,0 +1,343 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+    rc::{Rc, Weak},
+};
+
+use super::{ReadSignal, WriteSignal, create_signal};
+
+// A single change to a `SignalVec`, in the order it was made. Indices refer
+// to the list as it was right before the change.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VecDiff<T> {
+    Insert { index: usize, value: T },
+    Remove { index: usize },
+    Move { from: usize, to: usize },
+    Update { index: usize, value: T },
+    Clear,
+}
+
+// A single change to a `SignalMap`
+#[derive(Debug, Clone, PartialEq)]
+pub enum MapDiff<K, V> {
+    Insert { key: K, value: V },
+    Remove { key: K },
+    Update { key: K, value: V },
+    Clear,
+}
+
+type DiffQueue<D> = Rc<RefCell<VecDeque<D>>>;
+
+// Receives every change made to a collection after the watcher was created.
+// Dropping it unregisters it.
+pub struct Watcher<D> {
+    queue: DiffQueue<D>,
+}
+
+impl<D> Watcher<D> {
+    // Changes made since the last call, oldest first
+    pub fn drain(&self) -> Vec<D> {
+        self.queue.borrow_mut().drain(..).collect()
+    }
+}
+
+pub type VecWatcher<T> = Watcher<VecDiff<T>>;
+pub type MapWatcher<K, V> = Watcher<MapDiff<K, V>>;
+
+// Fan-out of diffs to live watchers plus a signal that readers subscribe to,
+// so effects and nodes rerun once per batch of changes.
+struct Changes<D: 'static> {
+    watchers: Vec<Weak<RefCell<VecDeque<D>>>>,
+    trigger: (ReadSignal<()>, WriteSignal<()>),
+}
+
+impl<D: Clone> Changes<D> {
+    fn new() -> Self {
+        Self {
+            watchers: Vec::new(),
+            trigger: create_signal(()),
+        }
+    }
+
+    fn watch(&mut self) -> Watcher<D> {
+        let queue = DiffQueue::default();
+        self.watchers.push(Rc::downgrade(&queue));
+        Watcher { queue }
+    }
+
+    fn track(&self) {
+        self.trigger.0.with(|_| ());
+    }
+
+    // Queues the diff for every watcher; readers are notified separately,
+    // once the collection itself is no longer borrowed
+    fn push(&mut self, diff: D) {
+        self.watchers.retain(|w| w.strong_count() > 0);
+        for watcher in self.watchers.iter().filter_map(Weak::upgrade) {
+            watcher.borrow_mut().push_back(diff.clone());
+        }
+    }
+
+    fn notifier(&self) -> WriteSignal<()> {
+        self.trigger.1.clone()
+    }
+}
+
+struct VecInner<T: 'static> {
+    items: Vec<T>,
+    changes: Changes<VecDiff<T>>,
+}
+
+// A reactive list that reports what changed rather than only that something
+// did. Reads subscribe like a signal; `watch` yields the individual edits,
+// which is what `For` uses to touch only the affected rows.
+pub struct SignalVec<T: 'static> {
+    inner: Rc<RefCell<VecInner<T>>>,
+}
+
+impl<T> Clone for SignalVec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Default for SignalVec<T> {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+impl<T: Clone + 'static> SignalVec<T> {
+    pub fn new(items: Vec<T>) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(VecInner {
+                items,
+                changes: Changes::new(),
+            })),
+        }
+    }
+
+    pub fn watch(&self) -> VecWatcher<T> {
+        self.inner.borrow_mut().changes.watch()
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    // Subscribes the current effect or node without reading anything
+    pub fn track(&self) {
+        self.inner.borrow().changes.track();
+    }
+
+    pub fn with<R>(&self, f: impl FnOnce(&[T]) -> R) -> R {
+        self.track();
+        f(&self.inner.borrow().items)
+    }
+
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.with(|items| items.get(index).cloned())
+    }
+
+    pub fn len(&self) -> usize {
+        self.with(|items| items.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.with(|items| items.to_vec())
+    }
+
+    pub fn push(&self, value: T) {
+        let index = self.inner.borrow().items.len();
+        self.insert(index, value);
+    }
+
+    pub fn insert(&self, index: usize, value: T) {
+        self.modify(|inner| {
+            inner.items.insert(index, value.clone());
+            VecDiff::Insert { index, value }
+        });
+    }
+
+    pub fn remove(&self, index: usize) -> T {
+        let mut removed = None;
+        self.modify(|inner| {
+            removed = Some(inner.items.remove(index));
+            VecDiff::Remove { index }
+        });
+        removed.expect("remove always yields the item")
+    }
+
+    pub fn pop(&self) -> Option<T> {
+        let len = self.inner.borrow().items.len();
+        (len > 0).then(|| self.remove(len - 1))
+    }
+
+    // Moves the item at `from` so that it ends up at index `to`
+    pub fn move_item(&self, from: usize, to: usize) {
+        self.modify(|inner| {
+            let item = inner.items.remove(from);
+            inner.items.insert(to, item);
+            VecDiff::Move { from, to }
+        });
+    }
+
+    pub fn set(&self, index: usize, value: T) {
+        self.modify(|inner| {
+            inner.items[index] = value.clone();
+            VecDiff::Update { index, value }
+        });
+    }
+
+    pub fn update_at(&self, index: usize, f: impl FnOnce(&mut T)) {
+        self.modify(|inner| {
+            f(&mut inner.items[index]);
+            VecDiff::Update {
+                index,
+                value: inner.items[index].clone(),
+            }
+        });
+    }
+
+    pub fn clear(&self) {
+        self.modify(|inner| {
+            inner.items.clear();
+            VecDiff::Clear
+        });
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut VecInner<T>) -> VecDiff<T>) {
+        let notifier = {
+            let mut inner = self.inner.borrow_mut();
+            let diff = f(&mut inner);
+            inner.changes.push(diff);
+            inner.changes.notifier()
+        };
+        notifier.set(());
+    }
+}
+
+struct MapInner<K: 'static, V: 'static> {
+    items: HashMap<K, V>,
+    changes: Changes<MapDiff<K, V>>,
+}
+
+// Keyed counterpart of `SignalVec`
+pub struct SignalMap<K: 'static, V: 'static> {
+    inner: Rc<RefCell<MapInner<K, V>>>,
+}
+
+impl<K, V> Clone for SignalMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for SignalMap<K, V>
+where
+    K: Clone + Eq + Hash + 'static,
+    V: Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new(HashMap::new())
+    }
+}
+
+impl<K, V> SignalMap<K, V>
+where
+    K: Clone + Eq + Hash + 'static,
+    V: Clone + 'static,
+{
+    pub fn new(items: HashMap<K, V>) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(MapInner {
+                items,
+                changes: Changes::new(),
+            })),
+        }
+    }
+
+    pub fn watch(&self) -> MapWatcher<K, V> {
+        self.inner.borrow_mut().changes.watch()
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    pub fn track(&self) {
+        self.inner.borrow().changes.track();
+    }
+
+    pub fn with<R>(&self, f: impl FnOnce(&HashMap<K, V>) -> R) -> R {
+        self.track();
+        f(&self.inner.borrow().items)
+    }
+
+    pub fn get(&self, key: &K) -> Option<V> {
+        self.with(|items| items.get(key).cloned())
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.with(|items| items.contains_key(key))
+    }
+
+    pub fn len(&self) -> usize {
+        self.with(|items| items.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut previous = None;
+        self.modify(|inner| {
+            previous = inner.items.insert(key.clone(), value.clone());
+            match previous {
+                Some(_) => MapDiff::Update { key, value },
+                None => MapDiff::Insert { key, value },
+            }
+        });
+        previous
+    }
+
+    pub fn remove(&self, key: &K) -> Option<V> {
+        if !self.inner.borrow().items.contains_key(key) {
+            return None;
+        }
+
+        let mut removed = None;
+        self.modify(|inner| {
+            removed = inner.items.remove(key);
+            MapDiff::Remove { key: key.clone() }
+        });
+        removed
+    }
+
+    pub fn clear(&self) {
+        self.modify(|inner| {
+            inner.items.clear();
+            MapDiff::Clear
+        });
+    }
+
+    fn modify(&self, f: impl FnOnce(&mut MapInner<K, V>) -> MapDiff<K, V>) {
+        let notifier = {
+            let mut inner = self.inner.borrow_mut();
+            let diff = f(&mut inner);
+            inner.changes.push(diff);
+            inner.changes.notifier()
+        };
+        notifier.set(());
+    }
+}
@@ -0,0 +1,1283 @@
+mod collections;
+mod resource;
+mod sender;
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{BTreeSet, HashMap, HashSet},
+    mem,
+    rc::{Rc, Weak},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+pub use collections::{MapDiff, MapWatcher, SignalMap, SignalVec, VecDiff, VecWatcher, Watcher};
+pub use resource::{Resource, ResourceState, create_resource, deliver_resources, init_async};
+pub use sender::{SignalSender, deliver_signal_updates};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct NodeId(u64);
+
+static NEXT_NODE_ID: AtomicU64 = AtomicU64::new(0);
+impl NodeId {
+    pub fn new() -> Self {
+        Self(NEXT_NODE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+impl Default for NodeId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct EffectId(u64);
+
+static NEXT_EFFECT_ID: AtomicU64 = AtomicU64::new(0);
+impl EffectId {
+    fn new() -> Self {
+        Self(NEXT_EFFECT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct MemoId(u64);
+
+static NEXT_MEMO_ID: AtomicU64 = AtomicU64::new(0);
+impl MemoId {
+    fn new() -> Self {
+        Self(NEXT_MEMO_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct ScopeId(u64);
+
+static NEXT_SCOPE_ID: AtomicU64 = AtomicU64::new(0);
+impl ScopeId {
+    fn new() -> Self {
+        Self(NEXT_SCOPE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct CleanupId(u64);
+
+static NEXT_CLEANUP_ID: AtomicU64 = AtomicU64::new(0);
+impl CleanupId {
+    fn new() -> Self {
+        Self(NEXT_CLEANUP_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// A cleanup registered with `register_cleanup`, which can be withdrawn once
+// whatever it tears down has gone away by itself
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct CleanupHandle {
+    scope: ScopeId,
+    id: CleanupId,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+enum Subscriber {
+    Node(NodeId),
+    Effect(EffectId),
+    Memo(MemoId),
+}
+
+struct Effect {
+    run: Rc<dyn Fn()>,
+    // Owns whatever the effect creates while running; reset before each run
+    scope: ScopeId,
+    // How many effects it is nested in
+    owners: usize,
+    // Longest chain of memos between it and a signal, as of its last run
+    height: usize,
+}
+
+// Where a pending effect sits in the flush order. Effects nested in another
+// effect run after it, since its run may dispose them, and otherwise effects
+// further down a chain of memos run after shallower ones. Creation order only
+// breaks ties.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct EffectRank {
+    owners: usize,
+    height: usize,
+    id: EffectId,
+}
+
+// Everything created while a scope was the current owner, torn down together
+// when the scope is disposed
+#[derive(Default)]
+struct ScopeData {
+    parent: Option<ScopeId>,
+    children: Vec<ScopeId>,
+    effects: Vec<EffectId>,
+    memos: Vec<MemoId>,
+    // Node whose subscriptions end with the scope
+    node: Option<NodeId>,
+    // How many effects the scope is nested in, counting its own
+    effect_owners: usize,
+    cleanups: Vec<(CleanupId, Box<dyn FnOnce()>)>,
+}
+
+// How far a memo's value may be behind its sources
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum MemoState {
+    Clean,
+    // A memo it reads may have changed
+    Check,
+    // A signal it reads has changed
+    Dirty,
+}
+
+// A memo in the dependency graph. Memos are pulled: a change only marks them
+// stale, and they recompute when read (or when a node or effect that reads
+// them has to find out whether they changed).
+struct MemoNode {
+    state: MemoState,
+    // Recomputes the value, returning whether it differs from the last one
+    compute: Rc<dyn Fn() -> bool>,
+    signal: Weak<dyn Source>,
+    scope: ScopeId,
+    // One more than the highest memo it read last time it computed
+    height: usize,
+}
+
+// Type-erased signal, so the graph can be walked without knowing value types
+trait Source {
+    fn unsubscribe(&self, sub: Subscriber);
+    fn subscribers(&self) -> Vec<Subscriber>;
+    // The memo computing this signal, if it is one
+    fn memo(&self) -> Option<MemoId>;
+}
+
+impl<T> Source for RefCell<SignalInner<T>> {
+    fn unsubscribe(&self, sub: Subscriber) {
+        self.borrow_mut().subscribers.remove(&sub);
+    }
+
+    fn subscribers(&self) -> Vec<Subscriber> {
+        self.borrow().subscribers.iter().copied().collect()
+    }
+
+    fn memo(&self) -> Option<MemoId> {
+        self.borrow().memo
+    }
+}
+
+// Notifications held back until the outermost `batch` finishes
+#[derive(Default)]
+struct Batch {
+    depth: usize,
+    // Effects that may need to run, and the subset whose sources definitely
+    // changed; the rest only run if a memo they read turns out to differ
+    effects: BTreeSet<EffectRank>,
+    dirty_effects: HashSet<EffectId>,
+    // Stale memos read by nodes, checked before nodes are marked dirty
+    observed: Vec<MemoId>,
+    nodes: Vec<NodeId>,
+}
+
+thread_local! {
+    static SUBSCRIBER_STACK: RefCell<Vec<Subscriber>> = const { RefCell::new(Vec::new()) };
+    static EFFECTS: RefCell<HashMap<EffectId, Effect>> = RefCell::new(HashMap::new());
+    static MEMOS: RefCell<HashMap<MemoId, MemoNode>> = RefCell::new(HashMap::new());
+    static DIRTY_NODES_TX: RefCell<Option<mpsc::Sender<NodeId>>> = const { RefCell::new(None) };
+    static BATCH: RefCell<Batch> = RefCell::new(Batch::default());
+    static SCOPES: RefCell<HashMap<ScopeId, ScopeData>> = RefCell::new(HashMap::new());
+    static OWNER_STACK: RefCell<Vec<ScopeId>> = const { RefCell::new(Vec::new()) };
+    static SOURCES: RefCell<HashMap<Subscriber, Vec<Weak<dyn Source>>>> =
+        RefCell::new(HashMap::new());
+}
+
+struct SignalInner<T> {
+    // Only empty for a memo that hasn't been read yet
+    value: Option<T>,
+    subscribers: HashSet<Subscriber>,
+    memo: Option<MemoId>,
+    // A memo's derive function, kept so a memo disposed before it was ever
+    // read can still produce a value
+    derive: Option<Rc<dyn Fn() -> T>>,
+    // Made from a plain value with no way to write it
+    constant: bool,
+}
+
+impl<T> SignalInner<T> {
+    fn value(&self) -> &T {
+        self.value
+            .as_ref()
+            .expect("signal read before it had a value")
+    }
+}
+
+#[derive(Clone)]
+pub struct ReadSignal<T: 'static> {
+    inner: Rc<RefCell<SignalInner<T>>>,
+}
+
+#[derive(Clone)]
+pub struct WriteSignal<T: 'static> {
+    inner: Rc<RefCell<SignalInner<T>>>,
+}
+
+pub fn create_signal<T: Any + Clone>(value: T) -> (ReadSignal<T>, WriteSignal<T>) {
+    let inner = Rc::new(RefCell::new(SignalInner {
+        value: Some(value),
+        subscribers: HashSet::new(),
+        memo: None,
+        derive: None,
+        constant: false,
+    }));
+
+    (
+        ReadSignal {
+            inner: inner.clone(),
+        },
+        WriteSignal { inner },
+    )
+}
+
+impl<T: Clone> ReadSignal<T> {
+    pub fn get(&self) -> T {
+        self.with(T::clone)
+    }
+
+    // Reads the value without subscribing the current effect or node
+    pub fn get_untracked(&self) -> T {
+        self.refresh();
+        self.inner.borrow().value().clone()
+    }
+}
+
+impl<T: 'static> ReadSignal<T> {
+    // A signal that always holds `value`
+    pub fn constant(value: T) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(SignalInner {
+                value: Some(value),
+                subscribers: HashSet::new(),
+                memo: None,
+                derive: None,
+                constant: true,
+            })),
+        }
+    }
+
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.inner, &other.inner)
+    }
+
+    // Tracked like `get`, but borrows the value instead of cloning it
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        self.refresh();
+        self.track();
+        f(self.inner.borrow().value())
+    }
+
+    // Brings a memo up to date before its value is read. A memo whose scope
+    // was disposed before it computed anything is computed once, untracked,
+    // and keeps that value from then on.
+    fn refresh(&self) {
+        let memo = self.inner.borrow().memo;
+        if let Some(memo) = memo {
+            refresh_memo(memo);
+        }
+
+        let derive = {
+            let inner = self.inner.borrow();
+            inner
+                .value
+                .is_none()
+                .then(|| inner.derive.clone())
+                .flatten()
+        };
+        if let Some(derive) = derive {
+            let value = untrack(|| derive());
+            self.inner.borrow_mut().value = Some(value);
+        }
+    }
+
+    fn track(&self) {
+        SUBSCRIBER_STACK.with(|stack| {
+            if let Some(subscriber) = stack.borrow().last() {
+                let mut inner = self.inner.borrow_mut();
+                if inner.subscribers.insert(*subscriber) {
+                    let source: Weak<dyn Source> = Rc::downgrade(&self.inner) as _;
+                    SOURCES.with(|sources| {
+                        sources
+                            .borrow_mut()
+                            .entry(*subscriber)
+                            .or_default()
+                            .push(source)
+                    });
+                }
+            }
+        });
+    }
+}
+
+// Runs `f` without subscribing the current effect or node to what it reads
+pub fn untrack<R>(f: impl FnOnce() -> R) -> R {
+    let saved = SUBSCRIBER_STACK.with(|stack| mem::take(&mut *stack.borrow_mut()));
+    let result = f();
+    SUBSCRIBER_STACK.with(|stack| *stack.borrow_mut() = saved);
+    result
+}
+
+impl<T: 'static> WriteSignal<T> {
+    pub fn set(&self, new_value: T) {
+        self.inner.borrow_mut().value = Some(new_value);
+        self.notify_subscribers();
+    }
+
+    pub fn update<F>(&self, updater: F)
+    where
+        F: FnOnce(&mut T),
+    {
+        if let Some(value) = self.inner.borrow_mut().value.as_mut() {
+            updater(value);
+        }
+        self.notify_subscribers();
+    }
+
+    fn notify_subscribers(&self) {
+        let subscribers = self.inner.borrow().subscribers.iter().copied().collect();
+        notify(subscribers);
+    }
+}
+
+// Tells everything that read a signal or memo that its value changed
+fn notify(subscribers: Vec<Subscriber>) {
+    batch(|| {
+        for sub in subscribers {
+            match sub {
+                Subscriber::Node(node_id) => BATCH.with(|b| {
+                    let nodes = &mut b.borrow_mut().nodes;
+                    if !nodes.contains(&node_id) {
+                        nodes.push(node_id);
+                    }
+                }),
+                Subscriber::Effect(effect_id) => {
+                    let rank = effect_rank(effect_id);
+                    BATCH.with(|b| {
+                        let mut b = b.borrow_mut();
+                        b.effects.insert(rank);
+                        b.dirty_effects.insert(effect_id);
+                    })
+                }
+                Subscriber::Memo(memo_id) => mark_memo(memo_id, MemoState::Dirty),
+            }
+        }
+    });
+}
+
+// Raises a memo's staleness. The first time it goes stale, everything
+// downstream is told it may have changed too.
+fn mark_memo(id: MemoId, state: MemoState) {
+    let marked = MEMOS.with(|memos| {
+        let mut memos = memos.borrow_mut();
+        let memo = memos.get_mut(&id)?;
+        let was = memo.state;
+        memo.state = memo.state.max(state);
+        Some((was, memo.signal.clone()))
+    });
+    let Some((MemoState::Clean, signal)) = marked else {
+        return;
+    };
+    let Some(signal) = signal.upgrade() else {
+        return;
+    };
+
+    for sub in signal.subscribers() {
+        match sub {
+            Subscriber::Node(_) => BATCH.with(|b| {
+                let observed = &mut b.borrow_mut().observed;
+                if !observed.contains(&id) {
+                    observed.push(id);
+                }
+            }),
+            Subscriber::Effect(effect_id) => {
+                let rank = effect_rank(effect_id);
+                BATCH.with(|b| b.borrow_mut().effects.insert(rank));
+            }
+            Subscriber::Memo(memo_id) => mark_memo(memo_id, MemoState::Check),
+        }
+    }
+}
+
+// Memos the subscriber read during its last run
+fn upstream_memos(sub: Subscriber) -> Vec<MemoId> {
+    SOURCES.with(|sources| {
+        sources.borrow().get(&sub).map_or(Vec::new(), |list| {
+            list.iter().filter_map(|s| s.upgrade()?.memo()).collect()
+        })
+    })
+}
+
+fn memo_state(id: MemoId) -> Option<MemoState> {
+    MEMOS.with(|memos| memos.borrow().get(&id).map(|m| m.state))
+}
+
+// Highest memo the subscriber read during its last run, counting a memo that
+// only reads signals as 1
+fn memo_height(sub: Subscriber) -> usize {
+    let upstream = upstream_memos(sub);
+    MEMOS.with(|memos| {
+        let memos = memos.borrow();
+        upstream
+            .iter()
+            .filter_map(|id| memos.get(id).map(|m| m.height))
+            .max()
+            .unwrap_or(0)
+    })
+}
+
+fn effect_rank(id: EffectId) -> EffectRank {
+    EFFECTS.with(|effects| {
+        let effects = effects.borrow();
+        let effect = effects.get(&id);
+        EffectRank {
+            owners: effect.map_or(0, |e| e.owners),
+            height: effect.map_or(0, |e| e.height),
+            id,
+        }
+    })
+}
+
+// Brings a memo up to date, recomputing it only if one of its sources really
+// changed. Upstream memos are refreshed first, so in a diamond every memo
+// recomputes at most once and never sees a half-updated input.
+fn refresh_memo(id: MemoId) {
+    if memo_state(id) == Some(MemoState::Check) {
+        for upstream in upstream_memos(Subscriber::Memo(id)) {
+            refresh_memo(upstream);
+            if memo_state(id) == Some(MemoState::Dirty) {
+                break;
+            }
+        }
+    }
+
+    let stale = MEMOS.with(|memos| {
+        let mut memos = memos.borrow_mut();
+        let memo = memos.get_mut(&id)?;
+        let dirty = memo.state == MemoState::Dirty;
+        memo.state = MemoState::Clean;
+        dirty.then(|| (memo.compute.clone(), memo.signal.clone()))
+    });
+    let Some((compute, signal)) = stale else {
+        return;
+    };
+
+    if compute()
+        && let Some(signal) = signal.upgrade()
+    {
+        notify(signal.subscribers());
+    }
+}
+
+// Runs `f` with notifications deferred until the outermost batch returns.
+// Every affected effect and memo then runs at most once, and none of them
+// observes a state where only some of the writes have happened.
+pub fn batch<R>(f: impl FnOnce() -> R) -> R {
+    let _depth = BatchDepth::enter();
+    let result = f();
+
+    let outermost = BATCH.with(|b| b.borrow().depth == 1);
+    if outermost {
+        flush();
+    }
+
+    result
+}
+
+// Keeps the batch depth balanced even if the batched closure or an effect
+// panics, so later writes aren't held back forever
+struct BatchDepth;
+
+impl BatchDepth {
+    fn enter() -> Self {
+        BATCH.with(|b| b.borrow_mut().depth += 1);
+        Self
+    }
+}
+
+impl Drop for BatchDepth {
+    fn drop(&mut self) {
+        BATCH.with(|b| b.borrow_mut().depth -= 1);
+    }
+}
+
+// Runs pending effects in `EffectRank` order, skipping those whose memos
+// turned out not to change, then checks the memos nodes are waiting on. The
+// next effect is picked after every run, so effects queued by that run take
+// their place in the order.
+fn flush() {
+    loop {
+        let next = BATCH.with(|b| b.borrow_mut().effects.pop_first());
+        if let Some(rank) = next {
+            run_if_changed(rank.id);
+            continue;
+        }
+
+        let observed = BATCH.with(|b| b.borrow_mut().observed.pop());
+        if let Some(memo) = observed {
+            refresh_memo(memo);
+            continue;
+        }
+
+        break;
+    }
+
+    let nodes = BATCH.with(|b| std::mem::take(&mut b.borrow_mut().nodes));
+    DIRTY_NODES_TX.with(|tx_cell| {
+        if let Some(tx) = tx_cell.borrow().as_ref() {
+            for node_id in nodes {
+                tx.send(node_id).unwrap();
+            }
+        }
+    });
+}
+
+fn run_if_changed(id: EffectId) {
+    let is_dirty = || BATCH.with(|b| b.borrow().dirty_effects.contains(&id));
+
+    if !is_dirty() {
+        for memo in upstream_memos(Subscriber::Effect(id)) {
+            refresh_memo(memo);
+            if is_dirty() {
+                break;
+            }
+        }
+    }
+
+    // Refreshing may have queued the effect again; this run covers that
+    let dirty = BATCH.with(|b| {
+        let mut b = b.borrow_mut();
+        b.effects.retain(|rank| rank.id != id);
+        b.dirty_effects.remove(&id)
+    });
+    if !dirty {
+        return;
+    }
+
+    let run = EFFECTS.with(|effects| effects.borrow().get(&id).map(|e| e.run.clone()));
+    if let Some(run) = run {
+        run();
+    }
+}
+
+pub type Memo<T> = ReadSignal<T>;
+
+// A derived value that only recomputes when read after one of its sources
+// changed, and only notifies readers when the result differs from the
+// previous one.
+pub fn create_memo<T, F>(derive_fn: F) -> Memo<T>
+where
+    T: Any + Clone + PartialEq + 'static,
+    F: Fn() -> T + 'static,
+{
+    create_memo_with(derive_fn, T::eq)
+}
+
+// Like `create_memo`, with `eq` deciding whether two results are the same
+pub fn create_memo_with<T, F, E>(derive_fn: F, eq: E) -> Memo<T>
+where
+    T: Any + Clone + 'static,
+    F: Fn() -> T + 'static,
+    E: Fn(&T, &T) -> bool + 'static,
+{
+    let id = MemoId::new();
+    let owner = current_owner();
+    let scope = new_scope(owner, None);
+    let derive_fn: Rc<dyn Fn() -> T> = Rc::new(derive_fn);
+
+    let inner = Rc::new(RefCell::new(SignalInner {
+        value: None,
+        subscribers: HashSet::new(),
+        memo: Some(id),
+        derive: Some(derive_fn.clone()),
+        constant: false,
+    }));
+
+    let compute: Rc<dyn Fn() -> bool> = Rc::new({
+        let inner = Rc::downgrade(&inner);
+        move || {
+            let Some(inner) = inner.upgrade() else {
+                return false;
+            };
+
+            reset_scope(scope);
+            unsubscribe(Subscriber::Memo(id));
+
+            SUBSCRIBER_STACK.with(|stack| {
+                stack.borrow_mut().push(Subscriber::Memo(id));
+            });
+            let new_value = {
+                let _owner = OwnerGuard::push(scope);
+                derive_fn()
+            };
+            SUBSCRIBER_STACK.with(|stack| {
+                stack.borrow_mut().pop();
+            });
+
+            let height = memo_height(Subscriber::Memo(id)) + 1;
+            MEMOS.with(|memos| {
+                if let Some(memo) = memos.borrow_mut().get_mut(&id) {
+                    memo.height = height;
+                }
+            });
+
+            let mut inner = inner.borrow_mut();
+            let changed = inner.value.as_ref().is_none_or(|old| !eq(old, &new_value));
+            if changed {
+                inner.value = Some(new_value);
+            }
+            changed
+        }
+    });
+
+    let signal: Weak<dyn Source> = Rc::downgrade(&inner) as _;
+    MEMOS.with(|memos| {
+        memos.borrow_mut().insert(
+            id,
+            MemoNode {
+                state: MemoState::Dirty,
+                compute,
+                signal,
+                scope,
+                height: 1,
+            },
+        );
+    });
+    if let Some(owner) = owner {
+        SCOPES.with(|scopes| {
+            if let Some(data) = scopes.borrow_mut().get_mut(&owner) {
+                data.memos.push(id);
+            }
+        });
+    }
+
+    ReadSignal { inner }
+}
+
+pub fn create_effect<F>(effect_fn: F)
+where
+    F: Fn() + 'static,
+{
+    let id = EffectId::new();
+    let owner = current_owner();
+    let scope = new_scope(owner, None);
+    let owners = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let data = scopes.get_mut(&scope)?;
+        data.effect_owners += 1;
+        Some(data.effect_owners - 1)
+    });
+    let effect_fn_rc = Rc::new(effect_fn);
+
+    let runner: Rc<dyn Fn()> = Rc::new({
+        let effect_fn_rc = effect_fn_rc.clone();
+        move || {
+            // Effects, memos and cleanups from the previous run are replaced
+            // by the ones this run creates, and dependencies are tracked
+            // afresh so branches no longer taken stop triggering the effect
+            reset_scope(scope);
+            unsubscribe(Subscriber::Effect(id));
+
+            SUBSCRIBER_STACK.with(|stack| {
+                stack.borrow_mut().push(Subscriber::Effect(id));
+            });
+            let _owner = OwnerGuard::push(scope);
+
+            (effect_fn_rc)();
+
+            SUBSCRIBER_STACK.with(|stack| {
+                stack.borrow_mut().pop();
+            });
+
+            let height = memo_height(Subscriber::Effect(id));
+            EFFECTS.with(|effects| {
+                if let Some(effect) = effects.borrow_mut().get_mut(&id) {
+                    effect.height = height;
+                }
+            });
+        }
+    });
+
+    EFFECTS.with(|effects| {
+        effects.borrow_mut().insert(
+            id,
+            Effect {
+                run: runner.clone(),
+                scope,
+                owners: owners.unwrap_or_default(),
+                height: 0,
+            },
+        );
+    });
+    if let Some(owner) = owner {
+        SCOPES.with(|scopes| {
+            if let Some(data) = scopes.borrow_mut().get_mut(&owner) {
+                data.effects.push(id);
+            }
+        });
+    }
+
+    runner();
+}
+
+// Owner of the effects, memos and cleanups created while it is current.
+// Dropping the scope disposes all of them, along with its child scopes, and
+// runs the `on_cleanup` callbacks registered in it.
+pub struct Scope {
+    id: ScopeId,
+}
+
+impl Scope {
+    // A child of the current owner, or a root if there is none
+    pub fn new() -> Self {
+        Self {
+            id: new_scope(current_owner(), None),
+        }
+    }
+
+    // Root scope whose disposal also drops the node's signal subscriptions
+    pub(crate) fn for_node(node: NodeId) -> Self {
+        Self {
+            id: new_scope(None, Some(node)),
+        }
+    }
+
+    // Runs `f` with this scope as the owner
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> R {
+        let _owner = self.enter();
+        f()
+    }
+
+    // Makes this scope the owner until the guard is dropped
+    pub(crate) fn enter(&self) -> OwnerGuard {
+        OwnerGuard::push(self.id)
+    }
+
+    pub fn dispose(self) {}
+
+    // Disposes everything the scope owns without giving it up; dropping it
+    // afterwards does nothing more
+    pub(crate) fn dispose_now(&self) {
+        dispose_scope(self.id);
+    }
+
+    #[cfg(test)]
+    pub(crate) fn cleanup_count(&self) -> usize {
+        SCOPES.with(|scopes| {
+            scopes
+                .borrow()
+                .get(&self.id)
+                .map_or(0, |d| d.cleanups.len())
+        })
+    }
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Scope {
+    fn drop(&mut self) {
+        dispose_scope(self.id);
+    }
+}
+
+pub(crate) struct OwnerGuard;
+
+impl OwnerGuard {
+    fn push(id: ScopeId) -> Self {
+        OWNER_STACK.with(|stack| stack.borrow_mut().push(id));
+        Self
+    }
+}
+
+impl Drop for OwnerGuard {
+    fn drop(&mut self) {
+        OWNER_STACK.with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+// Runs `f` in a new root scope that isn't owned by the current one. Whatever
+// `f` creates lives until the returned scope is dropped.
+pub fn create_root<R>(f: impl FnOnce() -> R) -> (R, Scope) {
+    let scope = Scope {
+        id: new_scope(None, None),
+    };
+    let result = scope.run(f);
+    (result, scope)
+}
+
+// Registers `f` to run when the current owner is disposed, or before the
+// current effect runs again. Does nothing outside of any scope.
+pub fn on_cleanup(f: impl FnOnce() + 'static) {
+    register_cleanup(f);
+}
+
+// Like `on_cleanup`, returning a handle to withdraw the cleanup with
+pub(crate) fn register_cleanup(f: impl FnOnce() + 'static) -> Option<CleanupHandle> {
+    let owner = current_owner()?;
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let data = scopes.get_mut(&owner)?;
+        let id = CleanupId::new();
+        data.cleanups.push((id, Box::new(f)));
+        Some(CleanupHandle { scope: owner, id })
+    })
+}
+
+// Drops a cleanup without running it. Does nothing if it already ran.
+pub(crate) fn cancel_cleanup(handle: CleanupHandle) {
+    SCOPES.with(|scopes| {
+        if let Some(data) = scopes.borrow_mut().get_mut(&handle.scope) {
+            data.cleanups.retain(|(id, _)| *id != handle.id);
+        }
+    });
+}
+
+fn current_owner() -> Option<ScopeId> {
+    OWNER_STACK.with(|stack| stack.borrow().last().copied())
+}
+
+fn new_scope(parent: Option<ScopeId>, node: Option<NodeId>) -> ScopeId {
+    let id = ScopeId::new();
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        let mut effect_owners = 0;
+        if let Some(parent) = parent.and_then(|p| scopes.get_mut(&p)) {
+            parent.children.push(id);
+            effect_owners = parent.effect_owners;
+        }
+        scopes.insert(
+            id,
+            ScopeData {
+                parent,
+                node,
+                effect_owners,
+                ..Default::default()
+            },
+        );
+    });
+    id
+}
+
+// Disposes everything the scope owns but keeps the scope itself
+fn reset_scope(id: ScopeId) {
+    let owned = SCOPES.with(|scopes| {
+        scopes.borrow_mut().get_mut(&id).map(|data| {
+            (
+                mem::take(&mut data.children),
+                mem::take(&mut data.effects),
+                mem::take(&mut data.memos),
+                mem::take(&mut data.cleanups),
+            )
+        })
+    });
+    let Some((children, effects, memos, cleanups)) = owned else {
+        return;
+    };
+
+    for (_, cleanup) in cleanups.into_iter().rev() {
+        cleanup();
+    }
+    for child in children {
+        dispose_scope(child);
+    }
+    for effect in effects {
+        dispose_effect(effect);
+    }
+    for memo in memos {
+        dispose_memo(memo);
+    }
+}
+
+fn dispose_scope(id: ScopeId) {
+    reset_scope(id);
+
+    let Some(data) = SCOPES.with(|scopes| scopes.borrow_mut().remove(&id)) else {
+        return;
+    };
+    if let Some(parent) = data.parent {
+        SCOPES.with(|scopes| {
+            if let Some(parent) = scopes.borrow_mut().get_mut(&parent) {
+                parent.children.retain(|c| *c != id);
+            }
+        });
+    }
+    if let Some(node) = data.node {
+        unsubscribe(Subscriber::Node(node));
+    }
+}
+
+fn dispose_effect(id: EffectId) {
+    let Some(effect) = EFFECTS.with(|effects| effects.borrow_mut().remove(&id)) else {
+        return;
+    };
+    BATCH.with(|b| {
+        let mut b = b.borrow_mut();
+        b.effects.retain(|rank| rank.id != id);
+        b.dirty_effects.remove(&id);
+    });
+    unsubscribe(Subscriber::Effect(id));
+    dispose_scope(effect.scope);
+}
+
+fn dispose_memo(id: MemoId) {
+    let Some(memo) = MEMOS.with(|memos| memos.borrow_mut().remove(&id)) else {
+        return;
+    };
+    BATCH.with(|b| b.borrow_mut().observed.retain(|m| *m != id));
+    unsubscribe(Subscriber::Memo(id));
+    dispose_scope(memo.scope);
+}
+
+// Removes the subscriber from every signal it has read
+fn unsubscribe(sub: Subscriber) {
+    let sources = SOURCES.with(|sources| sources.borrow_mut().remove(&sub));
+    for source in sources.into_iter().flatten() {
+        if let Some(source) = source.upgrade() {
+            source.unsubscribe(sub);
+        }
+    }
+}
+
+pub struct ScopedNodeContext(Option<NodeId>);
+
+impl ScopedNodeContext {
+    pub fn new(id: NodeId) -> Self {
+        SUBSCRIBER_STACK.with(|stack| {
+            stack.borrow_mut().push(Subscriber::Node(id));
+        });
+
+        Self(Some(id))
+    }
+}
+
+impl Drop for ScopedNodeContext {
+    fn drop(&mut self) {
+        if self.0.is_some() {
+            SUBSCRIBER_STACK.with(|stack| {
+                stack.borrow_mut().pop();
+            });
+        }
+    }
+}
+
+pub fn init_reactivity(tx: mpsc::Sender<NodeId>) {
+    DIRTY_NODES_TX.with(|tx_cell| *tx_cell.borrow_mut() = Some(tx));
+}
+
+type Waker = Arc<dyn Fn() + Send + Sync>;
+
+// Shared by every thread, unlike the rest of the reactive state, since it is
+// how other threads get the UI thread's attention
+static WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
+// Sets how work finishing off the UI thread (resource fetches, updates from
+// a `SignalSender`) wakes it up. `App::run` posts to its event loop here.
+pub fn init_waker(wake: impl Fn() + Send + Sync + 'static) {
+    if let Ok(mut waker) = WAKER.lock() {
+        *waker = Some(Arc::new(wake));
+    }
+}
+
+pub(crate) fn wake_ui() {
+    let waker = WAKER.lock().ok().and_then(|waker| waker.clone());
+    if let Some(wake) = waker {
+        wake();
+    }
+}
+
+impl<T: PartialEq + 'static> ReadSignal<T> {
+    // Whether both always read the same value: the same signal, or two
+    // constants holding equal values
+    pub fn same_as(&self, other: &Self) -> bool {
+        if self.ptr_eq(other) {
+            return true;
+        }
+        let (a, b) = (self.inner.borrow(), other.inner.borrow());
+        a.constant && b.constant && a.value == b.value
+    }
+}
+
+impl From<String> for ReadSignal<String> {
+    fn from(s: String) -> Self {
+        ReadSignal::constant(s)
+    }
+}
+
+impl<'a> From<&'a str> for ReadSignal<String> {
+    fn from(s: &'a str) -> Self {
+        ReadSignal::constant(s.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memo_disposed_before_first_read_still_has_a_value() {
+        let (count, set_count) = create_signal(2);
+        let (doubled, scope) = create_root(|| create_memo(move || count.get() * 2));
+        drop(scope);
+
+        assert_eq!(doubled.get(), 4);
+        // It no longer follows its source once disposed
+        set_count.set(5);
+        assert_eq!(doubled.get(), 4);
+    }
+
+    #[test]
+    fn memo_disposed_after_a_read_keeps_its_last_value() {
+        let (count, set_count) = create_signal(1);
+        let (doubled, scope) = create_root(|| create_memo(move || count.get() * 2));
+        set_count.set(3);
+        assert_eq!(doubled.get(), 6);
+        drop(scope);
+
+        set_count.set(10);
+        assert_eq!(doubled.get_untracked(), 6);
+    }
+
+    #[test]
+    fn effects_flush_shallowest_first() {
+        let (count, set_count) = create_signal(0);
+        let order = Rc::new(RefCell::new(Vec::new()));
+
+        let ((), _scope) = create_root(|| {
+            let plus_one = create_memo({
+                let count = count.clone();
+                move || count.get() + 1
+            });
+            let doubled = create_memo(move || plus_one.get() * 2);
+            create_effect({
+                let order = order.clone();
+                move || order.borrow_mut().push(("deep", doubled.get()))
+            });
+            create_effect({
+                let order = order.clone();
+                move || order.borrow_mut().push(("shallow", count.get()))
+            });
+        });
+        order.borrow_mut().clear();
+
+        set_count.set(1);
+        assert_eq!(*order.borrow(), [("shallow", 1), ("deep", 4)]);
+    }
+
+    #[test]
+    fn batch_recovers_from_a_panic() {
+        let (count, set_count) = create_signal(0);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let ((), _scope) = create_root(|| {
+            let seen = seen.clone();
+            create_effect(move || seen.borrow_mut().push(count.get()));
+        });
+
+        let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            batch(|| {
+                set_count.set(1);
+                panic!("boom");
+            })
+        }));
+        assert!(panicked.is_err());
+
+        // Not batched any more, so the write is seen straight away
+        set_count.set(2);
+        assert_eq!(seen.borrow().last(), Some(&2));
+    }
+
+    #[test]
+    fn disposing_a_scope_stops_everything_it_owns() {
+        let (count, set_count) = create_signal(0);
+        let runs = Rc::new(RefCell::new(Vec::new()));
+        let cleanups = Rc::new(RefCell::new(Vec::new()));
+
+        let effect = |name: &'static str| {
+            let (count, runs) = (count.clone(), runs.clone());
+            create_effect(move || runs.borrow_mut().push((name, count.get())));
+        };
+        let cleanup = |name: &'static str| {
+            let cleanups = cleanups.clone();
+            on_cleanup(move || cleanups.borrow_mut().push(name));
+        };
+
+        let ((child, doubled), root) = create_root(|| {
+            effect("root");
+            cleanup("root");
+            let child = Scope::new();
+            let doubled = child.run(|| {
+                effect("child");
+                cleanup("child");
+                let count = count.clone();
+                create_memo(move || count.get() * 2)
+            });
+            (child, doubled)
+        });
+        assert_eq!(doubled.get(), 0);
+        set_count.set(1);
+        assert_eq!(
+            *runs.borrow(),
+            [("root", 0), ("child", 0), ("root", 1), ("child", 1)]
+        );
+        assert_eq!(doubled.get(), 2);
+
+        drop(root);
+        let mut ran = cleanups.borrow().clone();
+        ran.sort();
+        assert_eq!(ran, ["child", "root"]);
+        assert!(count.inner.borrow().subscribers.is_empty());
+
+        // Nothing follows the signal any more, the nested scope included
+        runs.borrow_mut().clear();
+        set_count.set(2);
+        assert!(runs.borrow().is_empty());
+        assert_eq!(doubled.get(), 2);
+
+        // Its handle outlived it, and dropping that does nothing more
+        drop(child);
+        assert_eq!(cleanups.borrow().len(), 2);
+    }
+
+    #[test]
+    fn disposing_a_child_scope_leaves_its_parent_running() {
+        let (count, set_count) = create_signal(0);
+        let runs = Rc::new(RefCell::new(Vec::new()));
+        let cleaned = Rc::new(RefCell::new(false));
+
+        let (child, _root) = create_root(|| {
+            let runs = runs.clone();
+            let count = count.clone();
+            create_effect({
+                let (runs, count) = (runs.clone(), count.clone());
+                move || runs.borrow_mut().push(("root", count.get()))
+            });
+            let child = Scope::new();
+            child.run(|| {
+                create_effect(move || runs.borrow_mut().push(("child", count.get())));
+                let cleaned = cleaned.clone();
+                on_cleanup(move || *cleaned.borrow_mut() = true);
+            });
+            child
+        });
+
+        child.dispose();
+        assert!(*cleaned.borrow());
+        runs.borrow_mut().clear();
+        set_count.set(1);
+        assert_eq!(*runs.borrow(), [("root", 1)]);
+    }
+
+    #[test]
+    fn disposing_a_node_scope_drops_its_subscriptions() {
+        let (count, _) = create_signal(0);
+        let node = NodeId::new();
+        let scope = Scope::for_node(node);
+        {
+            let _guard = ScopedNodeContext::new(node);
+            count.get();
+        }
+        assert!(
+            count
+                .inner
+                .borrow()
+                .subscribers
+                .contains(&Subscriber::Node(node))
+        );
+
+        drop(scope);
+        assert!(count.inner.borrow().subscribers.is_empty());
+    }
+
+    #[test]
+    fn dependencies_follow_the_branch_taken() {
+        let (flag, set_flag) = create_signal(true);
+        let (a, set_a) = create_signal(1);
+        let (b, set_b) = create_signal(10);
+        let runs = Rc::new(RefCell::new(0));
+
+        let (picked, _scope) = create_root(|| {
+            let picked = create_memo({
+                let (flag, a, b) = (flag.clone(), a.clone(), b.clone());
+                move || if flag.get() { a.get() } else { b.get() }
+            });
+            create_effect({
+                let (runs, a) = (runs.clone(), a.clone());
+                move || {
+                    *runs.borrow_mut() += 1;
+                    if flag.get() {
+                        a.get()
+                    } else {
+                        b.get()
+                    };
+                }
+            });
+            picked
+        });
+        assert_eq!(picked.get(), 1);
+
+        set_flag.set(false);
+        assert_eq!(picked.get(), 10);
+        assert_eq!(*runs.borrow(), 2);
+
+        // `a` is off the branch now, so neither reads it any more
+        set_a.set(2);
+        assert_eq!(*runs.borrow(), 2);
+        assert!(a.inner.borrow().subscribers.is_empty());
+
+        set_b.set(20);
+        assert_eq!(picked.get(), 20);
+        assert_eq!(*runs.borrow(), 3);
+    }
+
+    #[test]
+    fn untracked_reads_do_not_subscribe() {
+        let (tracked, set_tracked) = create_signal(1);
+        let (ignored, set_ignored) = create_signal(10);
+        let seen = Rc::new(RefCell::new(Vec::new()));
+
+        let ((), _scope) = create_root(|| {
+            let seen = seen.clone();
+            let ignored = ignored.clone();
+            create_effect(move || {
+                let ignored = untrack(|| ignored.get());
+                seen.borrow_mut().push(tracked.get() + ignored);
+            });
+        });
+
+        set_ignored.set(20);
+        assert_eq!(*seen.borrow(), [11]);
+        assert!(ignored.inner.borrow().subscribers.is_empty());
+
+        // The next run still sees the latest value
+        set_tracked.set(2);
+        assert_eq!(*seen.borrow(), [11, 22]);
+    }
+
+    #[test]
+    fn only_constants_compare_by_value() {
+        let label = ReadSignal::constant("Save".to_string());
+        assert!(label.same_as(&"Save".into()));
+        assert!(!label.same_as(&"Load".into()));
+
+        // Equal now, but either could be written later
+        let (a, _) = create_signal(1);
+        let (b, _) = create_signal(1);
+        assert!(a.same_as(&a.clone()));
+        assert!(!a.same_as(&b));
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    any::Any,
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+use tokio::runtime::Handle;
+
+use super::{ReadSignal, batch, create_effect, create_signal, on_cleanup, untrack, wake_ui};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct ResourceId(u64);
+
+static NEXT_RESOURCE_ID: AtomicU64 = AtomicU64::new(0);
+impl ResourceId {
+    fn new() -> Self {
+        Self(NEXT_RESOURCE_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+type Output = Box<dyn Any + Send>;
+type Task = Pin<Box<dyn Future<Output = Output> + Send>>;
+
+// A finished fetch on its way back to the UI thread
+struct Completion {
+    id: ResourceId,
+    generation: u64,
+    output: Output,
+}
+
+struct AsyncContext {
+    handle: Option<Handle>,
+    // Fetches started before a runtime was installed
+    pending: Vec<(ResourceId, u64, Task)>,
+    tx: mpsc::Sender<Completion>,
+    rx: mpsc::Receiver<Completion>,
+    // Applies a completion to the resource's signals on the UI thread
+    deliver: HashMap<ResourceId, Rc<dyn Fn(u64, Output)>>,
+}
+
+impl Default for AsyncContext {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            handle: None,
+            pending: Vec::new(),
+            tx,
+            rx,
+            deliver: HashMap::new(),
+        }
+    }
+}
+
+thread_local! {
+    static ASYNC: RefCell<AsyncContext> = RefCell::new(AsyncContext::default());
+}
+
+#[derive(Debug, Clone)]
+pub enum ResourceState<T> {
+    Loading,
+    Ready(T),
+    Error(Rc<anyhow::Error>),
+}
+
+// The result of an async fetch that reruns whenever its source changes.
+// Only the latest fetch is applied; results of superseded ones are dropped.
+pub struct Resource<T: 'static> {
+    state: ReadSignal<ResourceState<T>>,
+    refetch: Rc<dyn Fn()>,
+}
+
+impl<T: Clone> Clone for Resource<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+            refetch: self.refetch.clone(),
+        }
+    }
+}
+
+impl<T: Clone + 'static> Resource<T> {
+    pub fn state(&self) -> ReadSignal<ResourceState<T>> {
+        self.state.clone()
+    }
+
+    pub fn get(&self) -> ResourceState<T> {
+        self.state.get()
+    }
+
+    pub fn loading(&self) -> bool {
+        self.state
+            .with(|state| matches!(state, ResourceState::Loading))
+    }
+
+    pub fn value(&self) -> Option<T> {
+        self.state.with(|state| match state {
+            ResourceState::Ready(value) => Some(value.clone()),
+            _ => None,
+        })
+    }
+
+    pub fn error(&self) -> Option<Rc<anyhow::Error>> {
+        self.state.with(|state| match state {
+            ResourceState::Error(err) => Some(err.clone()),
+            _ => None,
+        })
+    }
+
+    // Fetches again with the current source value
+    pub fn refetch(&self) {
+        (self.refetch)();
+    }
+}
+
+// Runs `fetcher` on the app's async runtime for the current value of
+// `source`, and again every time it changes. The state starts out as
+// `Loading` and is switched to `Ready` or `Error` on the UI thread once the
+// future completes.
+pub fn create_resource<S, T, F, Fut>(source: ReadSignal<S>, fetcher: F) -> Resource<T>
+where
+    S: Clone + 'static,
+    T: Clone + Send + 'static,
+    F: Fn(S) -> Fut + 'static,
+    Fut: Future<Output = anyhow::Result<T>> + Send + 'static,
+{
+    let id = ResourceId::new();
+    let (state, set_state) = create_signal(ResourceState::<T>::Loading);
+    let generation = Rc::new(Cell::new(0));
+
+    let deliver: Rc<dyn Fn(u64, Output)> = Rc::new({
+        let generation = generation.clone();
+        let set_state = set_state.clone();
+        move |finished, output| {
+            if finished != generation.get() {
+                return;
+            }
+            let Ok(result) = output.downcast::<anyhow::Result<T>>() else {
+                return;
+            };
+            set_state.set(match *result {
+                Ok(value) => ResourceState::Ready(value),
+                Err(err) => ResourceState::Error(Rc::new(err)),
+            });
+        }
+    });
+    ASYNC.with(|cx| cx.borrow_mut().deliver.insert(id, deliver));
+    on_cleanup(move || {
+        ASYNC.with(|cx| cx.borrow_mut().deliver.remove(&id));
+    });
+
+    let fetch: Rc<dyn Fn(S)> = Rc::new({
+        let state = state.clone();
+        move |value| {
+            generation.set(generation.get() + 1);
+            if !untrack(|| state.with(|state| matches!(state, ResourceState::Loading))) {
+                set_state.set(ResourceState::Loading);
+            }
+
+            let future = fetcher(value);
+            let task: Task = Box::pin(async move { Box::new(future.await) as Output });
+            spawn(id, generation.get(), task);
+        }
+    });
+
+    create_effect({
+        let fetch = fetch.clone();
+        let source = source.clone();
+        move || fetch(source.get())
+    });
+
+    Resource {
+        state,
+        refetch: Rc::new(move || fetch(source.get_untracked())),
+    }
+}
+
+fn spawn(id: ResourceId, generation: u64, task: Task) {
+    ASYNC.with(|cx| {
+        let mut cx = cx.borrow_mut();
+        match &cx.handle {
+            Some(handle) => {
+                let tx = cx.tx.clone();
+                handle.spawn(async move {
+                    let output = task.await;
+                    if tx
+                        .send(Completion {
+                            id,
+                            generation,
+                            output,
+                        })
+                        .is_ok()
+                    {
+                        wake_ui();
+                    }
+                });
+            }
+            None => cx.pending.push((id, generation, task)),
+        }
+    });
+}
+
+// Installs the runtime resources are fetched on and starts any fetches
+// queued so far. `App::run` does this with its own runtime; finished fetches
+// wake the UI thread through `init_waker`.
+pub fn init_async(handle: Handle) {
+    let pending = ASYNC.with(|cx| {
+        let mut cx = cx.borrow_mut();
+        cx.handle = Some(handle);
+        std::mem::take(&mut cx.pending)
+    });
+    for (id, generation, task) in pending {
+        spawn(id, generation, task);
+    }
+}
+
+// Applies finished fetches to their resources. Returns how many arrived.
+pub fn deliver_resources() -> usize {
+    let completed: Vec<Completion> = ASYNC.with(|cx| cx.borrow().rx.try_iter().collect());
+    let count = completed.len();
+
+    batch(|| {
+        for done in completed {
+            let deliver = ASYNC.with(|cx| cx.borrow().deliver.get(&done.id).cloned());
+            if let Some(deliver) = deliver {
+                deliver(done.generation, done.output);
+            }
+        }
+    });
+    count
+}
@@ -0,0 +1,201 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    marker::PhantomData,
+    rc::{Rc, Weak},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+};
+
+use super::{SignalInner, WriteSignal, batch, wake_ui};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct SenderId(u64);
+
+static NEXT_SENDER_ID: AtomicU64 = AtomicU64::new(0);
+impl SenderId {
+    fn new() -> Self {
+        Self(NEXT_SENDER_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+type Updater<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+enum Op<T> {
+    Set(T),
+    Update(Updater<T>),
+}
+
+// An `Op<T>` for the signal registered under `id`
+struct Message {
+    id: SenderId,
+    op: Box<dyn Any + Send>,
+}
+
+// The signal a sender writes to, held weakly so a sender kept alive by a
+// worker thread doesn't keep the signal alive too
+struct Target {
+    signal: Weak<dyn Any>,
+    apply: fn(Rc<dyn Any>, Box<dyn Any + Send>),
+}
+
+struct SenderContext {
+    tx: mpsc::Sender<Message>,
+    rx: mpsc::Receiver<Message>,
+    targets: HashMap<SenderId, Target>,
+}
+
+impl Default for SenderContext {
+    fn default() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self {
+            tx,
+            rx,
+            targets: HashMap::new(),
+        }
+    }
+}
+
+thread_local! {
+    static SENDERS: RefCell<SenderContext> = RefCell::new(SenderContext::default());
+}
+
+// A handle for setting a signal from another thread. Writes are queued and
+// applied on the UI thread the next time the event loop wakes up; writes to
+// a signal that has since been dropped are ignored.
+pub struct SignalSender<T> {
+    id: SenderId,
+    tx: mpsc::Sender<Message>,
+    _marker: PhantomData<fn(T)>,
+}
+
+impl<T> Clone for SignalSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            tx: self.tx.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Send + 'static> SignalSender<T> {
+    pub fn set(&self, value: T) {
+        self.send(Op::Set(value));
+    }
+
+    pub fn update(&self, updater: impl FnOnce(&mut T) + Send + 'static) {
+        self.send(Op::Update(Box::new(updater)));
+    }
+
+    fn send(&self, op: Op<T>) {
+        let message = Message {
+            id: self.id,
+            op: Box::new(op),
+        };
+        if self.tx.send(message).is_ok() {
+            wake_ui();
+        }
+    }
+}
+
+impl<T: Send + 'static> WriteSignal<T> {
+    // A `Send + Sync` handle to this signal for background threads
+    pub fn sender(&self) -> SignalSender<T> {
+        let id = SenderId::new();
+        let signal: Rc<dyn Any> = self.inner.clone();
+        let tx = SENDERS.with(|cx| {
+            let mut cx = cx.borrow_mut();
+            cx.targets
+                .retain(|_, target| target.signal.strong_count() > 0);
+            cx.targets.insert(
+                id,
+                Target {
+                    signal: Rc::downgrade(&signal),
+                    apply: apply::<T>,
+                },
+            );
+            cx.tx.clone()
+        });
+
+        SignalSender {
+            id,
+            tx,
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn apply<T: 'static>(signal: Rc<dyn Any>, op: Box<dyn Any + Send>) {
+    let (Ok(inner), Ok(op)) = (
+        signal.downcast::<RefCell<SignalInner<T>>>(),
+        op.downcast::<Op<T>>(),
+    ) else {
+        return;
+    };
+
+    let signal = WriteSignal { inner };
+    match *op {
+        Op::Set(value) => signal.set(value),
+        Op::Update(updater) => signal.update(updater),
+    }
+}
+
+// Applies writes queued by `SignalSender`s, in the order they were sent.
+// Returns how many arrived.
+pub fn deliver_signal_updates() -> usize {
+    let messages: Vec<Message> = SENDERS.with(|cx| cx.borrow().rx.try_iter().collect());
+    let count = messages.len();
+
+    batch(|| {
+        for message in messages {
+            let target = SENDERS.with(|cx| {
+                let cx = cx.borrow();
+                let target = cx.targets.get(&message.id)?;
+                Some((target.signal.upgrade()?, target.apply))
+            });
+            if let Some((signal, apply)) = target {
+                apply(signal, message.op);
+            }
+        }
+    });
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use crate::{Text, signals::create_signal, snapshot::Snapshot, style::Theme};
+
+    #[test]
+    fn writes_from_another_thread_land_on_the_next_pump() {
+        let (label, set_label) = create_signal("waiting".to_string());
+        let mut snap = Snapshot::new(
+            Box::new(Text::new(label.clone())),
+            Theme::default(),
+            120,
+            40,
+        );
+        snap.render().unwrap();
+
+        let sender = set_label.sender();
+        thread::spawn(move || {
+            sender.set("done".to_string());
+            sender.update(|label| label.push('!'));
+        })
+        .join()
+        .unwrap();
+
+        // Queued until the UI thread gets to them
+        assert_eq!(label.get_untracked(), "waiting");
+
+        assert!(snap.pump().unwrap());
+        assert_eq!(label.get_untracked(), "done!");
+        let mut expected = Snapshot::new(Box::new(Text::new("done!")), Theme::default(), 120, 40);
+        assert_eq!(snap.render().unwrap(), expected.render().unwrap());
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use anyhow::{Context, anyhow};
+use glam::vec2;
+use image::{Rgba, RgbaImage};
+
+use crate::{
+    Result, animation,
+    layout::{Rect, node::Node},
+    renderer::Renderer,
+    scheduler,
+    signals::{self, NodeId},
+    style::Theme,
+    timer,
+    widgets::{BuildCtx, Widget},
+};
+
+// Set this to 1 to record golden images instead of comparing against them
+pub const UPDATE_ENV: &str = "LUNA_UPDATE_SNAPSHOTS";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SnapshotOutcome {
+    Matched,
+    Recorded,
+}
+
+pub struct Comparison {
+    pub mismatched: usize,
+    pub diff: RgbaImage,
+}
+
+// Renders a widget tree offscreen and compares it with golden PNGs
+pub struct Snapshot {
+    root: Node,
+    theme: Theme,
+    renderer: Renderer<'static>,
+    width: u32,
+    tolerance: u8,
+    dirty_rx: mpsc::Receiver<NodeId>,
+}
+
+impl Snapshot {
+    // Takes over this thread's signal updates, so signal changes rebuild the
+    // snapshot's nodes as they would in an `App`
+    pub fn new(root: Box<dyn Widget>, theme: Theme, width: u32, height: u32) -> Self {
+        let viewport = Rect::new(vec2(0.0, 0.0), vec2(width as f32, height as f32));
+        let (dirty_tx, dirty_rx) = mpsc::channel();
+        signals::init_reactivity(dirty_tx);
+
+        Self {
+            root: Node::new(root, viewport, &mut BuildCtx),
+            theme,
+            renderer: Renderer::headless(width, height, 1.0),
+            width,
+            tolerance: 0,
+            dirty_rx,
+        }
+    }
+
+    // Maximum per-channel difference a pixel may have and still match
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    pub fn root(&mut self) -> &mut Node {
+        &mut self.root
+    }
+
+    pub fn render(&mut self) -> Result<RgbaImage> {
+        self.apply_signal_updates();
+        self.renderer.begin_frame();
+        self.root.rebuild(&mut BuildCtx);
+        self.root
+            .layout(self.width as f32, &self.theme, self.renderer.font_system());
+        self.root.collect(&mut self.renderer, &self.theme);
+        self.renderer.end_frame()?;
+        self.root.clear_paint_dirty();
+        if self.renderer.compaction_due() {
+            self.root.remap_slots(&self.renderer.compact());
+        }
+
+        self.renderer
+            .read_pixels()
+            .ok_or_else(|| anyhow!("renderer backend cannot read back pixels"))
+    }
+
+    // One turn of the event loop as `App` runs it: takes in results and
+    // signal writes from other threads, fires due timers and animations,
+    // applies signal changes, and renders only if something changed or a
+    // frame was requested. Returns whether it rendered.
+    pub fn pump(&mut self) -> Result<bool> {
+        signals::deliver_resources();
+        signals::deliver_signal_updates();
+
+        let now = timer::now();
+        timer::run_due();
+        animation::tick(now);
+        if animation::style_transitions_running() {
+            self.root.advance_transitions(now);
+        }
+        self.apply_signal_updates();
+
+        if scheduler::take_due(now) || self.root.needs_paint() {
+            self.render()?;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    fn apply_signal_updates(&mut self) {
+        for id in self.dirty_rx.try_iter() {
+            self.root.mark_dirty_by_id(id);
+        }
+    }
+
+    // Renders the tree and compares it with the PNG at `golden`. With
+    // UPDATE_ENV=1 the current output is recorded instead; otherwise a
+    // missing golden is an error, so a typo'd path can't pass. On mismatch
+    // `<name>.actual.png` and `<name>.diff.png` are written next to the
+    // golden and an error is returned.
+    pub fn assert_matches(&mut self, golden: impl AsRef<Path>) -> Result<SnapshotOutcome> {
+        let golden = golden.as_ref();
+        let actual = self.render()?;
+
+        if std::env::var(UPDATE_ENV).is_ok_and(|v| v == "1") {
+            if let Some(parent) = golden.parent() {
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("creating directory {:?}", parent))?;
+            }
+            actual
+                .save(golden)
+                .with_context(|| format!("writing golden {:?}", golden))?;
+            return Ok(SnapshotOutcome::Recorded);
+        }
+
+        if !golden.exists() {
+            return Err(anyhow!(
+                "snapshot {:?} has no golden; run with {}=1 to record it",
+                golden,
+                UPDATE_ENV
+            ));
+        }
+
+        let expected = image::open(golden)
+            .with_context(|| format!("reading golden {:?}", golden))?
+            .to_rgba8();
+
+        if expected.dimensions() != actual.dimensions() {
+            actual.save(sibling(golden, "actual"))?;
+            return Err(anyhow!(
+                "snapshot {:?} is {:?} but the render is {:?}",
+                golden,
+                expected.dimensions(),
+                actual.dimensions()
+            ));
+        }
+
+        let comparison = compare(&actual, &expected, self.tolerance);
+        if comparison.mismatched == 0 {
+            return Ok(SnapshotOutcome::Matched);
+        }
+
+        let actual_path = sibling(golden, "actual");
+        let diff_path = sibling(golden, "diff");
+        actual.save(&actual_path)?;
+        comparison.diff.save(&diff_path)?;
+
+        Err(anyhow!(
+            "snapshot {:?} differs in {} pixels (see {:?} and {:?})",
+            golden,
+            comparison.mismatched,
+            actual_path,
+            diff_path
+        ))
+    }
+}
+
+// Pixels that differ by more than `tolerance` in any channel are painted red
+// in the diff image; matching pixels are kept as a faded copy of the golden.
+pub fn compare(actual: &RgbaImage, expected: &RgbaImage, tolerance: u8) -> Comparison {
+    let mut diff = RgbaImage::new(expected.width(), expected.height());
+    let mut mismatched = 0;
+
+    for ((out, a), e) in diff
+        .pixels_mut()
+        .zip(actual.pixels())
+        .zip(expected.pixels())
+    {
+        let differs =
+            a.0.iter()
+                .zip(e.0.iter())
+                .any(|(x, y)| x.abs_diff(*y) > tolerance);
+
+        *out = if differs {
+            mismatched += 1;
+            Rgba([255, 0, 0, 255])
+        } else {
+            let Rgba([r, g, b, _]) = *e;
+            Rgba([r / 4, g / 4, b / 4, 255])
+        };
+    }
+
+    Comparison { mismatched, diff }
+}
+
+fn sibling(golden: &Path, suffix: &str) -> PathBuf {
+    let stem = golden
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    golden.with_file_name(format!("{stem}.{suffix}.png"))
+}
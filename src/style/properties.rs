@@ -1,5 +1,7 @@
 use glam::{Vec2, Vec4, vec2};
 
+use crate::animation::Transition;
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Display {
     Block,
@@ -29,6 +31,15 @@ pub enum Align {
     Stretch,
 }
 
+// Whether a node can be the target of pointer events. With `None` they pass
+// through to whatever is underneath, for decorative overlays. Like CSS, this
+// only affects the node itself; its children are hit-tested as usual.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PointerEvents {
+    Auto,
+    None,
+}
+
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Flex {
     pub dir: FlexDir,
@@ -77,6 +88,13 @@ pub struct Style {
     pub background_color: Option<Vec4>,
     pub width: Option<f32>,
     pub height: Option<f32>,
+    // Stacking order among everything drawn this frame; unset inherits the
+    // parent's. Higher values paint on top regardless of tree order.
+    pub z_index: Option<i32>,
+    pub pointer_events: PointerEvents,
+    // When set, changes to the background color, size or padding ease in
+    // over this transition instead of applying at once
+    pub transition: Option<Transition>,
 }
 
 impl Default for Style {
@@ -90,6 +108,9 @@ impl Default for Style {
             background_color: None,
             width: None,
             height: None,
+            z_index: None,
+            pointer_events: PointerEvents::Auto,
+            transition: None,
         }
     }
 }
@@ -0,0 +1,467 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::signals::{self, CleanupHandle};
+
+// Where luna reads the current time from. Timers, animations and the event
+// loop's wakeups all go through it, so tests can swap in a `MockClock`.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// A clock that only moves when told to. Clones share the same time.
+#[derive(Clone)]
+pub struct MockClock {
+    now: Rc<Cell<Instant>>,
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            now: Rc::new(Cell::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.set(self.now.get() + by);
+    }
+
+    pub fn set(&self, now: Instant) {
+        self.now.set(now);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct TimerId(u64);
+
+static NEXT_TIMER_ID: AtomicU64 = AtomicU64::new(0);
+impl TimerId {
+    fn new() -> Self {
+        Self(NEXT_TIMER_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+enum Callback {
+    Once(Box<dyn FnOnce()>),
+    Repeat(Box<dyn FnMut()>),
+}
+
+struct Timer {
+    due: Instant,
+    period: Option<Duration>,
+    // Taken out while the callback runs
+    callback: Option<Callback>,
+    // Cancels the timer when its owning scope goes away; withdrawn once the
+    // timer is gone, so a scope that keeps starting timers doesn't pile up
+    // cleanups for ones that have long since finished
+    cleanup: Option<CleanupHandle>,
+}
+
+impl Timer {
+    fn release(self) {
+        if let Some(cleanup) = self.cleanup {
+            signals::cancel_cleanup(cleanup);
+        }
+    }
+}
+
+thread_local! {
+    static CLOCK: RefCell<Rc<dyn Clock>> = RefCell::new(Rc::new(SystemClock));
+    static TIMERS: RefCell<HashMap<TimerId, Timer>> = RefCell::new(HashMap::new());
+}
+
+// Replaces the clock for this thread
+pub fn set_clock(clock: impl Clock + 'static) {
+    CLOCK.with(|c| *c.borrow_mut() = Rc::new(clock));
+}
+
+pub fn now() -> Instant {
+    let clock = CLOCK.with(|c| c.borrow().clone());
+    clock.now()
+}
+
+// Cancels its timer on request. Dropping the handle leaves the timer
+// running; it still stops when the scope that created it is disposed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TimerHandle {
+    id: TimerId,
+}
+
+impl TimerHandle {
+    pub fn cancel(&self) {
+        if let Some(timer) = TIMERS.with(|t| t.borrow_mut().remove(&self.id)) {
+            timer.release();
+        }
+    }
+
+    // False once a timeout has fired or the timer was cancelled
+    pub fn is_active(&self) -> bool {
+        TIMERS.with(|t| t.borrow().contains_key(&self.id))
+    }
+}
+
+// Runs `f` once, `delay` from now. Created inside a node's event handler or
+// an effect, the timer is cancelled along with that node or effect run.
+pub fn set_timeout(delay: Duration, f: impl FnOnce() + 'static) -> TimerHandle {
+    schedule(delay, None, Callback::Once(Box::new(f)))
+}
+
+// Runs `f` every `period` until cancelled, with the same ownership as
+// `set_timeout`
+pub fn set_interval(period: Duration, f: impl FnMut() + 'static) -> TimerHandle {
+    // A zero period would fire forever without yielding to the event loop
+    let period = period.max(Duration::from_millis(1));
+    schedule(period, Some(period), Callback::Repeat(Box::new(f)))
+}
+
+fn schedule(delay: Duration, period: Option<Duration>, callback: Callback) -> TimerHandle {
+    let handle = TimerHandle { id: TimerId::new() };
+    let cleanup = signals::register_cleanup(move || handle.cancel());
+    TIMERS.with(|t| {
+        t.borrow_mut().insert(
+            handle.id,
+            Timer {
+                due: now() + delay,
+                period,
+                callback: Some(callback),
+                cleanup,
+            },
+        )
+    });
+    handle
+}
+
+// Earliest time a timer is due; the event loop sleeps until then
+pub fn next_deadline() -> Option<Instant> {
+    TIMERS.with(|t| {
+        t.borrow()
+            .values()
+            .filter(|timer| timer.callback.is_some())
+            .map(|timer| timer.due)
+            .min()
+    })
+}
+
+// Runs every timer that is due according to the clock, earliest first, and
+// returns how many fired. An interval that fell behind fires once and picks
+// up from now rather than catching up. Timers scheduled by the callbacks wait
+// for the next call, even with no delay, so a timeout that keeps re-arming
+// itself can't hold up the event loop.
+pub fn run_due() -> usize {
+    let now = now();
+    let mut fired = 0;
+
+    signals::batch(|| {
+        for id in due_timers(now) {
+            let Some(callback) = take_callback(id, now) else {
+                continue;
+            };
+            fired += 1;
+            match callback {
+                Callback::Once(f) => f(),
+                Callback::Repeat(mut f) => {
+                    f();
+                    // Put it back unless it was cancelled while running
+                    TIMERS.with(|t| {
+                        if let Some(timer) = t.borrow_mut().get_mut(&id) {
+                            let period = timer.period.unwrap_or_default();
+                            timer.due += period;
+                            if timer.due <= now {
+                                timer.due = now + period;
+                            }
+                            timer.callback = Some(Callback::Repeat(f));
+                        }
+                    });
+                }
+            }
+        }
+    });
+    fired
+}
+
+// Timers due at `now`, earliest first
+fn due_timers(now: Instant) -> Vec<TimerId> {
+    TIMERS.with(|t| {
+        let timers = t.borrow();
+        let mut due: Vec<_> = timers
+            .iter()
+            .filter(|(_, timer)| timer.callback.is_some() && timer.due <= now)
+            .map(|(id, timer)| (timer.due, id.0))
+            .collect();
+        due.sort_unstable();
+        due.into_iter().map(|(_, id)| TimerId(id)).collect()
+    })
+}
+
+// Takes out the callback of a timer that is still due, removing the timer if
+// it only fires once. An earlier callback may have cancelled it.
+fn take_callback(id: TimerId, now: Instant) -> Option<Callback> {
+    let (callback, finished) = TIMERS.with(|t| {
+        let mut timers = t.borrow_mut();
+        let timer = timers.get_mut(&id).filter(|timer| timer.due <= now)?;
+        let callback = timer.callback.take()?;
+        let finished = match timer.period {
+            Some(_) => None,
+            None => timers.remove(&id),
+        };
+        Some((callback, finished))
+    })?;
+
+    if let Some(timer) = finished {
+        timer.release();
+    }
+    Some(callback)
+}
+
+#[cfg(test)]
+mod tests {
+    use cosmic_text::FontSystem;
+    use glam::{Vec2, Vec4};
+
+    use super::*;
+    use crate::{
+        Element, Renderer, Theme, Widget,
+        animation::{self, Easing, Transition, create_tween},
+        layout::node::Node,
+        signals::{ReadSignal, Scope, create_root, create_signal},
+        snapshot::Snapshot,
+        widgets::BuildCtx,
+    };
+
+    fn mock_clock() -> MockClock {
+        let clock = MockClock::new();
+        set_clock(clock.clone());
+        clock
+    }
+
+    fn counter() -> (Rc<Cell<u32>>, impl FnMut() + 'static) {
+        let count = Rc::new(Cell::new(0));
+        let bump = {
+            let count = count.clone();
+            move || count.set(count.get() + 1)
+        };
+        (count, bump)
+    }
+
+    #[test]
+    fn timeout_fires_once_when_due() {
+        let clock = mock_clock();
+        let (count, bump) = counter();
+        let handle = set_timeout(Duration::from_millis(100), bump);
+
+        clock.advance(Duration::from_millis(99));
+        assert_eq!(run_due(), 0);
+        assert!(handle.is_active());
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(run_due(), 1);
+        assert_eq!(count.get(), 1);
+        assert!(!handle.is_active());
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(run_due(), 0);
+        assert_eq!(count.get(), 1);
+    }
+
+    #[test]
+    fn cancelled_timeout_never_fires() {
+        let clock = mock_clock();
+        let (count, bump) = counter();
+        let handle = set_timeout(Duration::from_millis(10), bump);
+        handle.cancel();
+
+        clock.advance(Duration::from_millis(20));
+        assert_eq!(run_due(), 0);
+        assert_eq!(count.get(), 0);
+        assert!(!handle.is_active());
+    }
+
+    #[test]
+    fn interval_repeats_until_cancelled() {
+        let clock = mock_clock();
+        let (count, bump) = counter();
+        let handle = set_interval(Duration::from_millis(50), bump);
+
+        for expected in 1..=3 {
+            clock.advance(Duration::from_millis(50));
+            assert_eq!(run_due(), 1);
+            assert_eq!(count.get(), expected);
+        }
+        assert_eq!(
+            next_deadline(),
+            Some(clock.now() + Duration::from_millis(50))
+        );
+
+        handle.cancel();
+        clock.advance(Duration::from_millis(50));
+        assert_eq!(run_due(), 0);
+        assert_eq!(count.get(), 3);
+        assert_eq!(next_deadline(), None);
+    }
+
+    #[test]
+    fn interval_that_fell_behind_fires_once() {
+        let clock = mock_clock();
+        let (count, bump) = counter();
+        let handle = set_interval(Duration::from_millis(10), bump);
+
+        clock.advance(Duration::from_millis(55));
+        assert_eq!(run_due(), 1);
+        assert_eq!(count.get(), 1);
+        assert_eq!(
+            next_deadline(),
+            Some(clock.now() + Duration::from_millis(10))
+        );
+        handle.cancel();
+    }
+
+    #[test]
+    fn disposing_the_scope_cancels_its_timers() {
+        let clock = mock_clock();
+        let (count, bump) = counter();
+        let scope = Scope::new();
+        let handle = scope.run(|| set_interval(Duration::from_millis(10), bump));
+        drop(scope);
+
+        assert!(!handle.is_active());
+        clock.advance(Duration::from_millis(10));
+        assert_eq!(run_due(), 0);
+        assert_eq!(count.get(), 0);
+    }
+
+    #[test]
+    fn finished_timers_release_their_cleanups() {
+        let clock = mock_clock();
+        let scope = Scope::new();
+
+        // Restarting an interval, as the text input's caret does on every
+        // keystroke, leaves only the live one registered
+        scope.run(|| {
+            let mut handle = set_interval(Duration::from_millis(500), || {});
+            for _ in 0..100 {
+                handle.cancel();
+                handle = set_interval(Duration::from_millis(500), || {});
+            }
+        });
+        assert_eq!(scope.cleanup_count(), 1);
+
+        scope.run(|| set_timeout(Duration::from_millis(1), || {}));
+        assert_eq!(scope.cleanup_count(), 2);
+        clock.advance(Duration::from_millis(1));
+        run_due();
+        assert_eq!(scope.cleanup_count(), 1);
+    }
+
+    #[test]
+    fn timeout_rearmed_with_no_delay_waits_for_the_next_run() {
+        let clock = mock_clock();
+        let count = Rc::new(Cell::new(0));
+
+        fn rearm(count: Rc<Cell<u32>>) {
+            set_timeout(Duration::ZERO, move || {
+                count.set(count.get() + 1);
+                rearm(count);
+            });
+        }
+        rearm(count.clone());
+
+        for expected in 1..=3 {
+            assert_eq!(run_due(), 1);
+            assert_eq!(count.get(), expected);
+        }
+        assert_eq!(next_deadline(), Some(clock.now()));
+    }
+
+    #[test]
+    fn tween_follows_the_mock_clock_to_its_target() {
+        let clock = mock_clock();
+        let (target, set_target) = create_signal(0.0_f32);
+        let (value, _scope) =
+            create_root(|| create_tween(target, Duration::from_millis(100), Easing::Linear));
+
+        set_target.set(10.0);
+        clock.advance(Duration::from_millis(40));
+        assert!(animation::tick(clock.now()));
+        assert!((value.get() - 4.0).abs() < 1e-4);
+
+        clock.advance(Duration::from_millis(60));
+        assert!(!animation::tick(clock.now()));
+        assert_eq!(value.get(), 10.0);
+    }
+
+    // A swatch whose background eases to the colour in the signal
+    #[derive(Clone)]
+    struct Tinted(ReadSignal<Vec4>);
+
+    impl Widget for Tinted {
+        fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+            let swatch = Element::new()
+                .width(40.0)
+                .height(20.0)
+                .background_color(self.0.get())
+                .transition(Transition::new(Duration::from_millis(100)));
+            vec![Box::new(swatch)]
+        }
+
+        fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+            Vec2::ZERO
+        }
+
+        fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+            for child in &mut node.children {
+                child.collect(ren, theme);
+            }
+        }
+    }
+
+    #[test]
+    fn style_transition_follows_the_mock_clock_to_its_target() {
+        let clock = mock_clock();
+        let (color, set_color) = create_signal(Vec4::ZERO);
+        let mut snap = Snapshot::new(Box::new(Tinted(color)), Theme::default(), 60, 40);
+        snap.render().unwrap();
+        let background = |snap: &mut Snapshot| {
+            let node = snap.root().node_at(&[0]).unwrap();
+            node.animated_style().map(|style| style.background_color)
+        };
+
+        set_color.set(Vec4::ONE);
+        snap.pump().unwrap();
+        clock.advance(Duration::from_millis(50));
+        assert!(snap.pump().unwrap());
+        let midway = background(&mut snap).flatten().unwrap();
+        assert!(midway.x > 0.0 && midway.x < 1.0);
+
+        clock.advance(Duration::from_millis(60));
+        snap.pump().unwrap();
+        assert_eq!(background(&mut snap), None);
+        let node = snap.root().node_at(&[0]).unwrap();
+        assert_eq!(node.style().background_color, Some(Vec4::ONE));
+        assert!(!animation::style_transitions_running());
+    }
+}
@@ -1,15 +1,36 @@
 use super::BuildCtx;
 use crate::layout::node::Node;
 use crate::style::{Style, Theme};
-use crate::windowing::events::{EventCtx, EventKind};
-use crate::{Renderer, layout::Rect};
+use crate::windowing::{
+    FocusScope,
+    events::{EventCtx, EventKind},
+};
+use crate::{Renderer, layout::Rect, renderer::SlotRemap};
 use glam::Vec2;
+use std::any::Any;
+
+// An incremental change to a node's children, by index into the current list
+pub enum ChildEdit {
+    Insert(usize, Box<dyn Widget>),
+    Remove(usize),
+    Move { from: usize, to: usize },
+    // Reconciled into the existing node when the widget type matches
+    Update(usize, Box<dyn Widget>),
+    Clear,
+}
 
 pub trait Widget: WidgetClone {
     fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
         Vec::new()
     }
 
+    // Lets a widget whose children mirror a reactive collection patch them
+    // instead of rebuilding. Called in place of `build` on rebuilds; return
+    // None to fall back to a full `build` and reconcile.
+    fn child_edits(&self, _ctx: &mut BuildCtx) -> Option<Vec<ChildEdit>> {
+        None
+    }
+
     fn measure(
         &self,
         max_width: f32,
@@ -17,27 +38,94 @@ pub trait Widget: WidgetClone {
         font_system: &mut cosmic_text::FontSystem,
     ) -> Vec2;
 
+    // Only called when something in the node's subtree changed, such as a
+    // signal read here or a repaint asked for by an event; otherwise last
+    // frame's draws are reused. A widget that looks different over time has
+    // to ask the scheduler for its next frame from here.
     fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme);
 
     fn event(&mut self, _ctx: &mut EventCtx, _ev: &EventKind) {}
 
-    fn hit_test(&self, _pt: Vec2, _layout: Rect) -> bool {
+    // Whether `pt` lands on this widget, for shapes that don't fill their
+    // layout rect. Children are tested separately.
+    fn hit_test(&self, pt: Vec2, layout: Rect) -> bool {
+        layout.contains(pt)
+    }
+
+    // Whether children are cut off at this widget's layout rect when painted.
+    // Children of widgets that don't clip can be hit outside of it.
+    fn clips_children(&self) -> bool {
         false
     }
 
     fn style(&self) -> Style {
         Style::default()
     }
+
+    // Whether keyboard focus can land on this widget
+    fn focusable(&self) -> bool {
+        false
+    }
+
+    // Position in the tab order of the enclosing focus scope: positive
+    // values go first in ascending order, then 0 in tree order. Negative
+    // values are skipped by Tab but can still be focused directly.
+    fn tab_index(&self) -> i32 {
+        0
+    }
+
+    fn focus_scope(&self) -> Option<FocusScope> {
+        None
+    }
+
+    // Called on the ancestors of a node that keyboard focus moved to, so
+    // scrolling containers can bring `target` into view. Returns how far
+    // the content moved.
+    fn scroll_into_view(&mut self, _target: Rect, _layout: Rect) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    // Identity used when reconciling rebuilt children; keyed widgets are
+    // matched by key and type, unkeyed ones by type in order.
+    fn key(&self) -> Option<u64> {
+        None
+    }
+
+    // Called when reconciliation keeps this widget's node for a freshly built
+    // widget of the same type and key. Take over its configuration but keep
+    // internal state such as renderer slots.
+    fn update(&mut self, _next: &dyn Widget) {}
+
+    // Whether `next` would configure this widget exactly as it is, letting
+    // reconciliation skip `update` and leave the node's subtree alone.
+    // Widgets that can't tell report a change.
+    fn same_config(&self, _next: &dyn Widget) -> bool {
+        false
+    }
+
+    // Called once the widget's node has been removed from the tree. Free any
+    // renderer slots allocated in `paint` so they stop being drawn.
+    fn release(&mut self, _ren: &mut Renderer) {}
+
+    // Called after the renderer compacted its pools. Swap any slot ids kept
+    // from `paint` for their new ones.
+    fn remap_slots(&mut self, _remap: &SlotRemap) {}
 }
 
 pub trait WidgetClone {
     fn box_clone(&self) -> Box<dyn Widget>;
+
+    fn as_any(&self) -> &dyn Any;
 }
 
 impl<T: Widget + Clone + 'static> WidgetClone for T {
     fn box_clone(&self) -> Box<dyn Widget> {
         Box::new(self.clone())
     }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
 }
 
 impl Clone for Box<dyn Widget> {
@@ -1,25 +1,34 @@
 use crate::layout::node::Node;
 use crate::style::Theme;
-use std::rc::Rc;
+use std::{rc::Rc, time::Duration};
 
 use cosmic_text::{Attrs, Buffer, Metrics, Shaping};
 use glam::{Vec2, Vec4, vec2};
 use winit::event::MouseButton;
+use winit::keyboard::{Key, NamedKey};
 
 use super::base::Widget;
-use crate::signals::{ReadSignal, create_signal};
+use crate::animation::{Animated, Transition};
+use crate::signals::ReadSignal;
 use crate::{
     Renderer,
     layout::Rect,
-    renderer::{RenderPrimative, primatives::RectInstance},
+    renderer::{RenderPrimative, SlotRemap, primatives::RectInstance},
     windowing::events::{EventCtx, EventKind, Phase},
 };
 
+const HOVER_TRANSITION: Transition = Transition::new(Duration::from_millis(120));
+
 #[derive(Clone)]
 pub struct Button {
     pub label: ReadSignal<String>,
     pub on_click: Rc<dyn Fn() + 'static>,
     pub hovered: bool,
+    // 0 at rest, 1 fully hovered
+    hover: Animated<f32>,
+    focused: bool,
+    tab_index: i32,
+    focus_ring_id: Option<usize>,
     bg_id: Option<usize>,
     label_id: Option<usize>,
 }
@@ -30,23 +39,73 @@ impl Button {
             label: label.into(),
             on_click: Rc::new(|| {}),
             hovered: false,
+            hover: Animated::new(0.0, HOVER_TRANSITION),
+            focused: false,
+            tab_index: 0,
+            focus_ring_id: None,
             bg_id: None,
             label_id: None,
         }
     }
 
     pub fn label(txt: &str) -> Self {
-        let (read_label, _) = create_signal(txt.to_string());
-        Self::new(read_label)
+        Self::new(txt)
     }
 
     pub fn on_click(mut self, handler: impl Fn() + 'static) -> Self {
         self.on_click = Rc::new(handler);
         self
     }
+
+    pub fn with_tab_index(mut self, tab_index: i32) -> Self {
+        self.tab_index = tab_index;
+        self
+    }
 }
 
 impl Widget for Button {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.label = next.label.clone();
+            self.on_click = next.on_click.clone();
+            self.tab_index = next.tab_index;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.label.same_as(&next.label)
+                && Rc::ptr_eq(&self.on_click, &next.on_click)
+                && self.tab_index == next.tab_index
+        })
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn tab_index(&self) -> i32 {
+        self.tab_index
+    }
+
+    fn release(&mut self, ren: &mut Renderer) {
+        for id in [self.focus_ring_id.take(), self.bg_id.take()]
+            .into_iter()
+            .flatten()
+        {
+            ren.free_rect(id);
+        }
+        if let Some(id) = self.label_id.take() {
+            ren.free_text(id);
+        }
+    }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        remap.remap_rect(&mut self.focus_ring_id);
+        remap.remap_rect(&mut self.bg_id);
+        remap.remap_text(&mut self.label_id);
+    }
+
     fn measure(
         &self,
         _max_width: f32,
@@ -75,11 +134,27 @@ impl Widget for Button {
     fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
         let layout = node.layout_rect;
 
-        let bg_color = if self.hovered {
-            Vec4::from(theme.color.primary_hover)
+        let bg_color = Vec4::from(theme.color.primary)
+            .lerp(Vec4::from(theme.color.primary_hover), self.hover.get());
+
+        let ring_id = *self.focus_ring_id.get_or_insert_with(|| ren.alloc_rect());
+        if self.focused {
+            let offset = 2.0;
+            let mut ring_color = Vec4::from(theme.color.primary);
+            ring_color.w = 0.5;
+            ren.update_rect(
+                ring_id,
+                RectInstance {
+                    pos: (layout.origin - offset).to_array(),
+                    size: (layout.size + offset * 2.0).to_array(),
+                    color: ring_color.to_array(),
+                    radius: theme.radius.md + offset,
+                    ..Default::default()
+                },
+            );
         } else {
-            Vec4::from(theme.color.primary)
-        };
+            ren.update_rect(ring_id, RectInstance::default());
+        }
 
         let id = *self.bg_id.get_or_insert_with(|| ren.alloc_rect());
 
@@ -97,7 +172,7 @@ impl Widget for Button {
 
         let txt_pos = layout.origin + vec2(theme.spacing.md, theme.spacing.sm);
         let text_prim = RenderPrimative::text(
-            &self.label.get(),
+            self.label.get(),
             txt_pos,
             Vec4::from(theme.color.text),
             theme.typography.body,
@@ -124,11 +199,17 @@ impl Widget for Button {
                 ctx.stop_propagation();
             }
             EventKind::PointerMove { .. } if ctx.phase == Phase::Target => {
-                self.hovered = true;
+                if !self.hovered {
+                    self.hovered = true;
+                    self.hover.set(1.0);
+                    ctx.request_paint();
+                }
                 ctx.stop_propagation();
             }
-            EventKind::PointerLeave => {
+            EventKind::PointerLeave if self.hovered => {
                 self.hovered = false;
+                self.hover.set(0.0);
+                ctx.request_paint();
             }
 
             EventKind::PointerUp {
@@ -138,6 +219,20 @@ impl Widget for Button {
                 (self.on_click)();
                 ctx.stop_propagation();
             }
+            EventKind::KeyDown {
+                key: Key::Named(NamedKey::Enter | NamedKey::Space),
+            } if ctx.phase == Phase::Target => {
+                (self.on_click)();
+                ctx.stop_propagation();
+            }
+            EventKind::FocusIn if ctx.phase == Phase::Target => {
+                self.focused = true;
+                ctx.request_paint();
+            }
+            EventKind::FocusOut if ctx.phase == Phase::Target => {
+                self.focused = false;
+                ctx.request_paint();
+            }
             _ => {}
         }
     }
@@ -2,22 +2,83 @@ use std::{cell::RefCell, rc::Rc};
 
 use glam::Vec2;
 
-use crate::{layout::node::Node, renderer::Renderer, style::Theme, widgets::Widget};
+use crate::{
+    layout::{Rect, node::Node},
+    renderer::{Renderer, SlotRemap},
+    style::Theme,
+    widgets::Widget,
+};
+
+type PaintFn = Box<dyn FnMut(&mut Renderer)>;
+type HitTestFn = Rc<dyn Fn(Vec2) -> bool>;
+type RemapFn = Rc<RefCell<dyn FnMut(&SlotRemap)>>;
 
 #[derive(Clone)]
 pub struct Canvas {
-    on_paint: Rc<RefCell<Box<dyn FnMut(&mut Renderer)>>>,
+    on_paint: Rc<RefCell<PaintFn>>,
+    hit_test: Option<HitTestFn>,
+    on_remap: Option<RemapFn>,
 }
 
 impl Canvas {
+    // `on_paint` runs again when a signal it read changes or when it asked
+    // the scheduler for a frame; state it reads from anywhere else won't
+    // repaint the canvas.
     pub fn new(on_paint: impl FnMut(&mut Renderer) + 'static) -> Self {
         Self {
             on_paint: Rc::new(RefCell::new(Box::new(on_paint))),
+            hit_test: None,
+            on_remap: None,
         }
     }
+
+    // Decides which window positions land on what was drawn, instead of the
+    // whole layout rect
+    pub fn with_hit_test(mut self, hit_test: impl Fn(Vec2) -> bool + 'static) -> Self {
+        self.hit_test = Some(Rc::new(hit_test));
+        self
+    }
+
+    // Slots `on_paint` allocates and keeps across frames move when the
+    // renderer compacts its pools; `on_remap` gets the new ids to swap them
+    // for, e.g. with `SlotRemap::remap_line`. Without it, keep only
+    // immediate-mode draws (`draw_rect`, `draw_text`) across frames.
+    pub fn with_remap(mut self, on_remap: impl FnMut(&SlotRemap) + 'static) -> Self {
+        self.on_remap = Some(Rc::new(RefCell::new(on_remap)));
+        self
+    }
 }
 
 impl Widget for Canvas {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.on_paint = next.on_paint.clone();
+            self.hit_test = next.hit_test.clone();
+            self.on_remap = next.on_remap.clone();
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            let same_hit_test = match (&self.hit_test, &next.hit_test) {
+                (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            };
+            let same_remap = match (&self.on_remap, &next.on_remap) {
+                (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            };
+            Rc::ptr_eq(&self.on_paint, &next.on_paint) && same_hit_test && same_remap
+        })
+    }
+
+    fn hit_test(&self, pt: Vec2, layout: Rect) -> bool {
+        match &self.hit_test {
+            Some(hit_test) => hit_test(pt),
+            None => layout.contains(pt),
+        }
+    }
+
     fn build(&self, _ctx: &mut crate::widgets::BuildCtx) -> Vec<Box<dyn Widget>> {
         Vec::new()
     }
@@ -34,4 +95,10 @@ impl Widget for Canvas {
     fn paint(&mut self, _node: &mut Node, ren: &mut Renderer, _theme: &Theme) {
         (self.on_paint.borrow_mut())(ren);
     }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        if let Some(on_remap) = &self.on_remap {
+            (on_remap.borrow_mut())(remap);
+        }
+    }
 }
@@ -1,16 +1,25 @@
+use std::time::Duration;
+
 use glam::{Vec2, Vec4, vec2};
 use winit::event::MouseButton;
 use winit::keyboard::{Key, NamedKey};
 
 use crate::{
     Widget,
+    animation::{Animated, Easing, Transition},
     layout::node::Node,
-    renderer::{RectId, Renderer, primatives::RectInstance},
+    renderer::{RectId, Renderer, SlotRemap, primatives::RectInstance},
     signals::{ReadSignal, WriteSignal, create_signal},
     style::Theme,
     windowing::events::{EventCtx, EventKind},
 };
 
+const HOVER_TRANSITION: Transition = Transition::new(Duration::from_millis(120));
+const FILL_TRANSITION: Transition = Transition {
+    duration: Duration::from_millis(150),
+    easing: Easing::EaseOut,
+};
+
 #[derive(Clone)]
 pub struct Checkbox {
     pub label: ReadSignal<String>,
@@ -19,6 +28,11 @@ pub struct Checkbox {
     hovered: bool,
     focused: bool,
     pressed: bool,
+    hover: Animated<f32>,
+    // How far the fill has grown in, 0 to 1; created on first paint so a
+    // box that starts out checked doesn't animate in
+    fill: Option<Animated<f32>>,
+    tab_index: i32,
 
     border_id: Option<RectId>,
     fill_id: Option<RectId>,
@@ -36,12 +50,20 @@ impl Checkbox {
             hovered: false,
             focused: false,
             pressed: false,
+            hover: Animated::new(0.0, HOVER_TRANSITION),
+            fill: None,
+            tab_index: 0,
             border_id: None,
             fill_id: None,
             focus_ring_id: None,
         }
     }
 
+    pub fn with_tab_index(mut self, tab_index: i32) -> Self {
+        self.tab_index = tab_index;
+        self
+    }
+
     pub fn new_with_label(label: &str) -> Self {
         let (read, write) = create_signal(false);
         Self::new(label.to_string(), (read, write))
@@ -49,6 +71,47 @@ impl Checkbox {
 }
 
 impl Widget for Checkbox {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.label = next.label.clone();
+            self.state = next.state.clone();
+            self.tab_index = next.tab_index;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.label.same_as(&next.label)
+                && self.state.0.ptr_eq(&next.state.0)
+                && self.tab_index == next.tab_index
+        })
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn tab_index(&self) -> i32 {
+        self.tab_index
+    }
+
+    fn release(&mut self, ren: &mut Renderer) {
+        let slots = [
+            self.border_id.take(),
+            self.fill_id.take(),
+            self.focus_ring_id.take(),
+        ];
+        for id in slots.into_iter().flatten() {
+            ren.free_rect(id);
+        }
+    }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        remap.remap_rect(&mut self.border_id);
+        remap.remap_rect(&mut self.fill_id);
+        remap.remap_rect(&mut self.focus_ring_id);
+    }
+
     fn measure(
         &self,
         _max_width: f32,
@@ -88,19 +151,25 @@ impl Widget for Checkbox {
                 self.pressed = true;
                 self.state.1.update(|v| *v = !*v);
                 ctx.focus.request_focus(ctx.path);
+                ctx.request_paint();
             }
             EventKind::PointerUp {
                 button: MouseButton::Left,
                 ..
             } => {
                 self.pressed = false;
+                ctx.request_paint();
             }
-            EventKind::PointerMove { .. } => {
+            EventKind::PointerMove { .. } if !self.hovered => {
                 self.hovered = true;
+                self.hover.set(1.0);
+                ctx.request_paint();
             }
-            EventKind::PointerLeave => {
+            EventKind::PointerLeave if self.hovered || self.pressed => {
                 self.hovered = false;
                 self.pressed = false;
+                self.hover.set(0.0);
+                ctx.request_paint();
             }
             EventKind::KeyDown {
                 key: Key::Named(NamedKey::Space),
@@ -110,9 +179,11 @@ impl Widget for Checkbox {
             }
             EventKind::FocusIn => {
                 self.focused = true;
+                ctx.request_paint();
             }
             EventKind::FocusOut => {
                 self.focused = false;
+                ctx.request_paint();
             }
             _ => {}
         }
@@ -149,11 +220,8 @@ impl Widget for Checkbox {
             ren.update_rect(focus_ring_id, RectInstance::default());
         }
 
-        let border_color = if self.hovered {
-            Vec4::from(theme.color.primary) * 0.9
-        } else {
-            Vec4::from(theme.color.text) * Vec4::new(1.0, 1.0, 1.0, 0.5)
-        };
+        let border_color = (Vec4::from(theme.color.text) * Vec4::new(1.0, 1.0, 1.0, 0.5))
+            .lerp(Vec4::from(theme.color.primary) * 0.9, self.hover.get());
 
         let border_id = *self.border_id.get_or_insert_with(|| ren.alloc_rect());
         ren.update_rect(
@@ -167,11 +235,20 @@ impl Widget for Checkbox {
             },
         );
 
+        let target = if is_checked { 1.0 } else { 0.0 };
+        let fill = self
+            .fill
+            .get_or_insert_with(|| Animated::new(target, FILL_TRANSITION));
+        fill.set(target);
+        let grown = fill.get();
+
         let fill_id = *self.fill_id.get_or_insert_with(|| ren.alloc_rect());
-        if is_checked {
+        if grown > 0.0 {
+            // Grows out from the middle of the box
             let border_width = 2.0;
-            let fill_size = (box_size - (border_width * 2.0)).max(0.0);
-            let fill_pos = layout.origin + border_width;
+            let full_size = (box_size - (border_width * 2.0)).max(0.0);
+            let fill_size = full_size * grown;
+            let fill_pos = layout.origin + border_width + (full_size - fill_size) / 2.0;
 
             let fill_color = if self.pressed {
                 Vec4::from(theme.color.primary_hover)
@@ -1,16 +1,20 @@
-use crate::{layout::node::Node, style::Theme};
+use crate::{animation::Transition, layout::node::Node, style::Theme};
 use glam::{Vec2, Vec4, vec2};
+use std::hash::{DefaultHasher, Hash, Hasher};
 
 use crate::{
-    renderer::{RectId, Renderer, primatives::RectInstance},
-    style::{Align, Display, FlexDir, Justify, Style},
+    renderer::{RectId, Renderer, SlotRemap, primatives::RectInstance},
+    style::{Align, Display, FlexDir, Justify, PointerEvents, Style},
     widgets::{BuildCtx, Widget},
+    windowing::FocusScope,
 };
 
 #[derive(Clone, Default)]
 pub struct Element {
     pub style: Style,
     pub children: Vec<Box<dyn Widget>>,
+    key: Option<u64>,
+    focus_scope: Option<FocusScope>,
     bg_id: Option<RectId>,
 }
 
@@ -19,6 +23,19 @@ impl Element {
         Self::default()
     }
 
+    pub fn key(mut self, key: impl Hash) -> Self {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.key = Some(hasher.finish());
+        self
+    }
+
+    // Groups the children for Tab traversal, or traps focus inside them
+    pub fn focus_scope(mut self, scope: FocusScope) -> Self {
+        self.focus_scope = Some(scope);
+        self
+    }
+
     pub fn fill_cross(mut self, v: bool) -> Self {
         self.style.flex.fill_cross = v;
         self
@@ -49,6 +66,21 @@ impl Element {
         self
     }
 
+    pub fn z_index(mut self, z_index: i32) -> Self {
+        self.style.z_index = Some(z_index);
+        self
+    }
+
+    pub fn pointer_events(mut self, pointer_events: PointerEvents) -> Self {
+        self.style.pointer_events = pointer_events;
+        self
+    }
+
+    pub fn transition(mut self, transition: Transition) -> Self {
+        self.style.transition = Some(transition);
+        self
+    }
+
     pub fn padding(mut self, padding: f32) -> Self {
         self.style.padding = Vec2::splat(padding);
         self
@@ -100,6 +132,46 @@ impl Widget for Element {
         self.style
     }
 
+    fn key(&self) -> Option<u64> {
+        self.key
+    }
+
+    fn focus_scope(&self) -> Option<FocusScope> {
+        self.focus_scope
+    }
+
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.style = next.style;
+            self.children = next.children.clone();
+            self.focus_scope = next.focus_scope;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.style == next.style
+                && self.key == next.key
+                && self.focus_scope == next.focus_scope
+                && self.children.len() == next.children.len()
+                && self
+                    .children
+                    .iter()
+                    .zip(&next.children)
+                    .all(|(a, b)| a.same_config(b.as_ref()))
+        })
+    }
+
+    fn release(&mut self, ren: &mut Renderer) {
+        if let Some(id) = self.bg_id.take() {
+            ren.free_rect(id);
+        }
+    }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        remap.remap_rect(&mut self.bg_id);
+    }
+
     fn measure(
         &self,
         _max_width: f32,
@@ -110,7 +182,8 @@ impl Widget for Element {
     }
 
     fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
-        if let Some(color) = self.style.background_color {
+        let style = node.animated_style().unwrap_or(self.style);
+        if let Some(color) = style.background_color {
             let id = *self.bg_id.get_or_insert_with(|| ren.alloc_rect());
             ren.update_rect(
                 id,
@@ -121,6 +194,8 @@ impl Widget for Element {
                     ..Default::default()
                 },
             );
+        } else if let Some(id) = self.bg_id.take() {
+            ren.free_rect(id);
         }
 
         for child in &mut node.children {
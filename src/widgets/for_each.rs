@@ -0,0 +1,347 @@
+use std::{cell::RefCell, hash::Hash, rc::Rc};
+
+use glam::Vec2;
+
+use crate::{
+    layout::node::Node,
+    renderer::Renderer,
+    signals::{MapDiff, MapWatcher, Scope, SignalMap, SignalVec, VecDiff, VecWatcher},
+    style::{Display, FlexDir, Style, Theme},
+    widgets::{BuildCtx, ChildEdit, Widget},
+};
+
+type RenderFn<T> = Rc<dyn Fn(&T) -> Box<dyn Widget>>;
+type RenderEntryFn<K, V> = Rc<dyn Fn(&K, &V) -> Box<dyn Widget>>;
+
+struct ForState<T: 'static> {
+    watcher: VecWatcher<T>,
+    // One scope per row, owning whatever `render` created for it
+    scopes: Vec<Scope>,
+    // Set when the widget was pointed at a different list
+    rebuild: bool,
+}
+
+// Renders one child per item of a `SignalVec`, laid out as a flex column by
+// default. Changes to the list patch the children: pushing a row builds one
+// new node and leaves the others untouched.
+pub struct For<T: 'static> {
+    list: SignalVec<T>,
+    render: RenderFn<T>,
+    style: Style,
+    state: Rc<RefCell<ForState<T>>>,
+}
+
+impl<T> Clone for For<T> {
+    fn clone(&self) -> Self {
+        Self {
+            list: self.list.clone(),
+            render: self.render.clone(),
+            style: self.style,
+            state: self.state.clone(),
+        }
+    }
+}
+
+fn column() -> Style {
+    let mut style = Style {
+        display: Display::Flex,
+        ..Default::default()
+    };
+    style.flex.dir = FlexDir::Column;
+    style
+}
+
+fn paint_visible_rows(node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+    for child in &mut node.children {
+        if child.layout_rect.intersects(&node.layout_rect) {
+            child.collect(ren, theme);
+        }
+    }
+}
+
+impl<T: Clone + 'static> For<T> {
+    pub fn new<W, F>(list: SignalVec<T>, render: F) -> Self
+    where
+        W: Widget + 'static,
+        F: Fn(&T) -> W + 'static,
+    {
+        Self {
+            state: Rc::new(RefCell::new(ForState {
+                watcher: list.watch(),
+                scopes: Vec::new(),
+                rebuild: false,
+            })),
+            list,
+            render: Rc::new(move |item| Box::new(render(item))),
+            style: column(),
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn render_row(&self, item: &T) -> (Scope, Box<dyn Widget>) {
+        let scope = Scope::new();
+        let widget = scope.run(|| (self.render)(item));
+        (scope, widget)
+    }
+}
+
+impl<T: Clone + 'static> Widget for For<T> {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        let items = self.list.to_vec();
+
+        let mut state = self.state.borrow_mut();
+        state.watcher.drain();
+        state.rebuild = false;
+
+        let (scopes, widgets) = items.iter().map(|item| self.render_row(item)).unzip();
+        state.scopes = scopes;
+        widgets
+    }
+
+    fn child_edits(&self, _ctx: &mut BuildCtx) -> Option<Vec<ChildEdit>> {
+        self.list.track();
+
+        let mut state = self.state.borrow_mut();
+        if state.rebuild {
+            return None;
+        }
+
+        let edits = state
+            .watcher
+            .drain()
+            .into_iter()
+            .map(|diff| match diff {
+                VecDiff::Insert { index, value } => {
+                    let (scope, widget) = self.render_row(&value);
+                    state.scopes.insert(index, scope);
+                    ChildEdit::Insert(index, widget)
+                }
+                VecDiff::Remove { index } => {
+                    state.scopes.remove(index);
+                    ChildEdit::Remove(index)
+                }
+                VecDiff::Move { from, to } => {
+                    let scope = state.scopes.remove(from);
+                    state.scopes.insert(to, scope);
+                    ChildEdit::Move { from, to }
+                }
+                VecDiff::Update { index, value } => {
+                    let (scope, widget) = self.render_row(&value);
+                    state.scopes[index] = scope;
+                    ChildEdit::Update(index, widget)
+                }
+                VecDiff::Clear => {
+                    state.scopes.clear();
+                    ChildEdit::Clear
+                }
+            })
+            .collect();
+        Some(edits)
+    }
+
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            if !self.list.ptr_eq(&next.list) {
+                *self = next.clone();
+                self.state.borrow_mut().rebuild = true;
+            }
+            self.render = next.render.clone();
+            self.style = next.style;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.list.ptr_eq(&next.list)
+                && Rc::ptr_eq(&self.render, &next.render)
+                && self.style == next.style
+        })
+    }
+
+    fn style(&self) -> Style {
+        self.style
+    }
+
+    fn measure(
+        &self,
+        _max_width: f32,
+        _theme: &Theme,
+        _font_system: &mut cosmic_text::FontSystem,
+    ) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        paint_visible_rows(node, ren, theme);
+    }
+}
+
+struct ForMapState<K: 'static, V: 'static> {
+    watcher: MapWatcher<K, V>,
+    // Keys of the current rows, in order, with the scope of each row
+    keys: Vec<K>,
+    scopes: Vec<Scope>,
+    rebuild: bool,
+}
+
+// `For` over a `SignalMap`, with one child per entry in key order. Inserting
+// an entry builds one new node; the other rows keep theirs.
+pub struct ForMap<K: 'static, V: 'static> {
+    map: SignalMap<K, V>,
+    render: RenderEntryFn<K, V>,
+    style: Style,
+    state: Rc<RefCell<ForMapState<K, V>>>,
+}
+
+impl<K, V> Clone for ForMap<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            render: self.render.clone(),
+            style: self.style,
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<K, V> ForMap<K, V>
+where
+    K: Clone + Ord + Hash + 'static,
+    V: Clone + 'static,
+{
+    pub fn new<W, F>(map: SignalMap<K, V>, render: F) -> Self
+    where
+        W: Widget + 'static,
+        F: Fn(&K, &V) -> W + 'static,
+    {
+        Self {
+            state: Rc::new(RefCell::new(ForMapState {
+                watcher: map.watch(),
+                keys: Vec::new(),
+                scopes: Vec::new(),
+                rebuild: false,
+            })),
+            map,
+            render: Rc::new(move |key, value| Box::new(render(key, value))),
+            style: column(),
+        }
+    }
+
+    pub fn with_style(mut self, style: Style) -> Self {
+        self.style = style;
+        self
+    }
+
+    fn render_row(&self, key: &K, value: &V) -> (Scope, Box<dyn Widget>) {
+        let scope = Scope::new();
+        let widget = scope.run(|| (self.render)(key, value));
+        (scope, widget)
+    }
+}
+
+impl<K, V> Widget for ForMap<K, V>
+where
+    K: Clone + Ord + Hash + 'static,
+    V: Clone + 'static,
+{
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        let mut entries: Vec<(K, V)> = self
+            .map
+            .with(|items| items.iter().map(|(k, v)| (k.clone(), v.clone())).collect());
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut state = self.state.borrow_mut();
+        state.watcher.drain();
+        state.rebuild = false;
+
+        let (scopes, widgets) = entries
+            .iter()
+            .map(|(key, value)| self.render_row(key, value))
+            .unzip();
+        state.keys = entries.into_iter().map(|(key, _)| key).collect();
+        state.scopes = scopes;
+        widgets
+    }
+
+    fn child_edits(&self, _ctx: &mut BuildCtx) -> Option<Vec<ChildEdit>> {
+        self.map.track();
+
+        let mut state = self.state.borrow_mut();
+        if state.rebuild {
+            return None;
+        }
+
+        let mut edits = Vec::new();
+        for diff in state.watcher.drain() {
+            match diff {
+                MapDiff::Insert { key, value } | MapDiff::Update { key, value } => {
+                    let (scope, widget) = self.render_row(&key, &value);
+                    match state.keys.binary_search(&key) {
+                        Ok(index) => {
+                            state.scopes[index] = scope;
+                            edits.push(ChildEdit::Update(index, widget));
+                        }
+                        Err(index) => {
+                            state.keys.insert(index, key);
+                            state.scopes.insert(index, scope);
+                            edits.push(ChildEdit::Insert(index, widget));
+                        }
+                    }
+                }
+                MapDiff::Remove { key } => {
+                    if let Ok(index) = state.keys.binary_search(&key) {
+                        state.keys.remove(index);
+                        state.scopes.remove(index);
+                        edits.push(ChildEdit::Remove(index));
+                    }
+                }
+                MapDiff::Clear => {
+                    state.keys.clear();
+                    state.scopes.clear();
+                    edits.push(ChildEdit::Clear);
+                }
+            }
+        }
+        Some(edits)
+    }
+
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            if !self.map.ptr_eq(&next.map) {
+                *self = next.clone();
+                self.state.borrow_mut().rebuild = true;
+            }
+            self.render = next.render.clone();
+            self.style = next.style;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.map.ptr_eq(&next.map)
+                && Rc::ptr_eq(&self.render, &next.render)
+                && self.style == next.style
+        })
+    }
+
+    fn style(&self) -> Style {
+        self.style
+    }
+
+    fn measure(
+        &self,
+        _max_width: f32,
+        _theme: &Theme,
+        _font_system: &mut cosmic_text::FontSystem,
+    ) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        paint_visible_rows(node, ren, theme);
+    }
+}
@@ -1,12 +1,18 @@
-use std::cell::RefCell;
+use std::{
+    cell::RefCell,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
     Widget,
     layout::{Rect, node::Node},
     renderer::Renderer,
+    signals::ReadSignal,
     style::{Style, Theme},
+    windowing::events::{EventCtx, EventKind},
 };
-use glam::{Vec2, vec2};
+use glam::{Vec2, Vec4, vec2};
 
 #[derive(Copy, Clone, Debug, PartialEq, Default)]
 pub enum ImageFit {
@@ -16,16 +22,23 @@ pub enum ImageFit {
     Cover,
 }
 
+type FileDropFn = Rc<dyn Fn(&Path)>;
+// Source path and the dimensions read from it, if it could be read
+type CachedDimensions = Option<(String, Option<(u32, u32)>)>;
+
 #[derive(Clone)]
 pub struct Image {
-    path: String,
+    path: ReadSignal<String>,
     fit: ImageFit,
-    dimensions: RefCell<Option<(u32, u32)>>,
+    dimensions: RefCell<CachedDimensions>,
     style: Style,
+    on_file_drop: Option<FileDropFn>,
+    // Set while image files from the OS are held over it
+    file_hover: bool,
 }
 
 impl Image {
-    pub fn new(path: impl Into<String>) -> Self {
+    pub fn new(path: impl Into<ReadSignal<String>>) -> Self {
         Self {
             path: path.into(),
             fit: ImageFit::default(),
@@ -34,9 +47,19 @@ impl Image {
                 flex_grow: 0.0,
                 ..Default::default()
             },
+            on_file_drop: None,
+            file_hover: false,
         }
     }
 
+    // Turns the image into a drop target for image files from the OS. `f`
+    // gets the first one dropped and usually sets the signal the image's
+    // path comes from.
+    pub fn on_file_drop(mut self, f: impl Fn(&Path) + 'static) -> Self {
+        self.on_file_drop = Some(Rc::new(f));
+        self
+    }
+
     pub fn fit(mut self, fit: ImageFit) -> Self {
         self.fit = fit;
         self
@@ -53,15 +76,80 @@ impl Image {
     }
 
     fn get_dimensions(&self) -> Option<(u32, u32)> {
-        if self.dimensions.borrow().is_none() {
-            let new_dims = image::image_dimensions(&self.path).ok();
-            *self.dimensions.borrow_mut() = new_dims;
+        let path = self.path.get();
+        let mut cached = self.dimensions.borrow_mut();
+        match &*cached {
+            Some((cached_path, dims)) if *cached_path == path => *dims,
+            _ => {
+                let dims = image::image_dimensions(&path).ok();
+                *cached = Some((path, dims));
+                dims
+            }
         }
-        *self.dimensions.borrow()
     }
 }
 
+// The first of `paths` that looks like an image we can load
+fn first_image(paths: &[PathBuf]) -> Option<&PathBuf> {
+    paths
+        .iter()
+        .find(|path| image::ImageFormat::from_path(path).is_ok())
+}
+
 impl Widget for Image {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            let file_hover = self.file_hover;
+            *self = next.clone();
+            self.file_hover = file_hover;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            let same_drop = match (&self.on_file_drop, &next.on_file_drop) {
+                (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+                (a, b) => a.is_none() && b.is_none(),
+            };
+            self.path.same_as(&next.path)
+                && self.fit == next.fit
+                && self.style == next.style
+                && same_drop
+        })
+    }
+
+    fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
+        let Some(on_file_drop) = &self.on_file_drop else {
+            return;
+        };
+
+        match ev {
+            EventKind::FileHover { paths, .. } => {
+                let file_hover = first_image(paths).is_some();
+                if file_hover != self.file_hover {
+                    self.file_hover = file_hover;
+                    ctx.request_paint();
+                }
+                if file_hover {
+                    ctx.stop_propagation();
+                }
+            }
+            EventKind::FileHoverCancel => {
+                self.file_hover = false;
+                ctx.request_paint();
+            }
+            EventKind::FileDrop { paths, .. } => {
+                self.file_hover = false;
+                ctx.request_paint();
+                if let Some(path) = first_image(paths) {
+                    on_file_drop(path);
+                    ctx.stop_propagation();
+                }
+            }
+            _ => {}
+        }
+    }
+
     fn style(&self) -> Style {
         self.style
     }
@@ -83,7 +171,7 @@ impl Widget for Image {
         Vec2::ZERO
     }
 
-    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, _theme: &Theme) {
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
         let container_rect = node.layout_rect;
 
         if container_rect.size.x <= 0.0 || container_rect.size.y <= 0.0 {
@@ -125,6 +213,17 @@ impl Widget for Image {
             container_rect
         };
 
-        ren.draw_image(&self.path, draw_rect);
+        ren.draw_image(&self.path.get(), draw_rect);
+
+        // Shows the image will take the files being held over it
+        if self.file_hover {
+            let highlight = Vec4::from(theme.color.primary).truncate().extend(0.35);
+            ren.draw_rounded_rect(
+                container_rect.origin,
+                container_rect.size,
+                theme.radius.md,
+                highlight,
+            );
+        }
     }
 }
@@ -5,19 +5,21 @@ pub mod canvas;
 pub mod checkbox;
 pub mod column;
 pub mod element;
+pub mod for_each;
 pub mod image;
 pub mod prim_id;
 pub mod scrollable;
 pub mod text;
 pub mod text_input;
 
-pub use base::Widget;
+pub use base::{ChildEdit, Widget};
 pub use build_context::BuildCtx;
 pub use button::Button;
 pub use canvas::Canvas;
 pub use checkbox::Checkbox;
 pub use column::Column;
 pub use element::Element;
+pub use for_each::{For, ForMap};
 pub use image::Image;
 pub use prim_id::PrimId;
 pub use scrollable::Scrollable;
@@ -26,10 +26,26 @@ impl Scrollable {
 }
 
 impl Widget for Scrollable {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.child = next.child.clone();
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|next| self.child.same_config(next.child.as_ref()))
+    }
+
     fn build(&self, _ctx: &mut crate::widgets::BuildCtx) -> Vec<Box<dyn Widget>> {
         vec![self.child.clone()]
     }
 
+    fn clips_children(&self) -> bool {
+        true
+    }
+
     fn measure(
         &self,
         max_width: f32,
@@ -40,7 +56,7 @@ impl Widget for Scrollable {
     }
 
     fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
-        if let EventKind::Wheel { delta } = ev {
+        if let EventKind::Wheel { delta, .. } = ev {
             let scroll_amount = delta.y * 20.0;
             self.offset.y -= scroll_amount;
 
@@ -52,6 +68,24 @@ impl Widget for Scrollable {
         }
     }
 
+    fn scroll_into_view(&mut self, target: Rect, layout: Rect) -> Vec2 {
+        let before = self.offset;
+
+        let overflow_bottom = target.origin.y + target.size.y - (layout.origin.y + layout.size.y);
+        if overflow_bottom > 0.0 {
+            self.offset.y += overflow_bottom;
+        }
+        // The top edge wins when the target is taller than the view
+        let overflow_top = layout.origin.y - target.origin.y;
+        if overflow_top > 0.0 {
+            self.offset.y -= overflow_top;
+        }
+
+        let max_offset_y = (self.child_size.y - layout.size.y).max(0.0);
+        self.offset.y = self.offset.y.clamp(0.0, max_offset_y);
+        self.offset - before
+    }
+
     fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
         let layout = node.layout_rect;
         ren.push_scissor_rect(layout);
@@ -19,6 +19,7 @@ impl Text {
         }
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(content: &str) -> Self {
         Self::new(content.to_string())
     }
@@ -35,6 +36,20 @@ impl Text {
 }
 
 impl Widget for Text {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            *self = next.clone();
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.content.same_as(&next.content)
+                && self.color == next.color
+                && self.size == next.size
+        })
+    }
+
     fn measure(
         &self,
         _max_width: f32,
@@ -1,7 +1,7 @@
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use arboard::Clipboard;
-use cosmic_text::{Attrs, Buffer, Color, Metrics, Shaping};
+use cosmic_text::{Attrs, Buffer, Metrics, Shaping};
 use glam::{Vec2, Vec4, vec2};
 use winit::keyboard::ModifiersState;
 use winit::keyboard::{Key, NamedKey};
@@ -10,11 +10,16 @@ use crate::style::Theme;
 use crate::{
     Widget,
     layout::{Rect, node::Node},
-    renderer::{RectId, Renderer, primatives::RectInstance},
+    renderer::{RectId, RenderPrimative, Renderer, SlotRemap, primatives::RectInstance},
+    signals::{ReadSignal, WriteSignal, create_signal},
     style::Style,
+    timer::{self, TimerHandle},
     windowing::events::{EventCtx, EventKind, Phase},
 };
 
+// How long the caret stays shown, then hidden
+const CARET_BLINK: Duration = Duration::from_millis(500);
+
 fn is_command_modifier(mods: ModifiersState) -> bool {
     #[cfg(target_os = "macos")]
     {
@@ -26,6 +31,35 @@ fn is_command_modifier(mods: ModifiersState) -> bool {
     }
 }
 
+// A press or drag waiting for the next paint, where the text is shaped and
+// the position can be turned into a character index
+#[derive(Copy, Clone)]
+struct PendingClick {
+    pos: Vec2,
+    click_count: u32,
+    // Moves the caret but keeps the anchor, as with shift-click or dragging
+    extend: bool,
+}
+
+// Runs of word characters, whitespace and punctuation each count as a word
+// when double-clicking
+#[derive(PartialEq)]
+enum CharClass {
+    Word,
+    Space,
+    Other,
+}
+
+fn char_class(ch: char) -> CharClass {
+    if ch.is_alphanumeric() || ch == '_' {
+        CharClass::Word
+    } else if ch.is_whitespace() {
+        CharClass::Space
+    } else {
+        CharClass::Other
+    }
+}
+
 #[derive(Clone)]
 pub struct TextInput {
     pub value: String,
@@ -35,18 +69,23 @@ pub struct TextInput {
     focused: bool,
     is_dragging: bool,
     scroll_offset: f32,
-    last_pos: Vec2,
-    click_to_process: Option<Vec2>,
+    pending_click: Option<PendingClick>,
 
     bg_id: Option<RectId>,
-    glyph_rect_ids: Vec<RectId>,
+    text_id: Option<usize>,
     cursor_id: Option<RectId>,
     selection_id: Option<RectId>,
-    last_blink: Instant,
+    // Toggled by the blink timer, which runs while focused; a signal so
+    // each toggle repaints the input
+    caret_on: ReadSignal<bool>,
+    set_caret_on: WriteSignal<bool>,
+    blink: Option<TimerHandle>,
+    tab_index: i32,
 }
 
 impl TextInput {
     pub fn new(placeholder: impl Into<String>) -> Self {
+        let (caret_on, set_caret_on) = create_signal(false);
         Self {
             value: String::new(),
             placeholder: placeholder.into(),
@@ -55,21 +94,78 @@ impl TextInput {
             focused: false,
             is_dragging: false,
             scroll_offset: 0.0,
-            last_pos: Vec2::ZERO,
-            click_to_process: None,
+            pending_click: None,
             bg_id: None,
-            glyph_rect_ids: Vec::new(),
+            text_id: None,
             cursor_id: None,
             selection_id: None,
-            last_blink: Instant::now(),
+            caret_on,
+            set_caret_on,
+            blink: None,
+            tab_index: 0,
+        }
+    }
+
+    pub fn with_tab_index(mut self, tab_index: i32) -> Self {
+        self.tab_index = tab_index;
+        self
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    // Selected character range as (start, end); empty when start == end
+    pub fn selection(&self) -> (usize, usize) {
+        self.selection_range()
+    }
+
+    // Shift extends the selection; double and triple clicks select a word
+    // and everything
+    fn on_pointer_down(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
+        if let EventKind::PointerDown {
+            pos,
+            click_count,
+            modifiers,
+            ..
+        } = *ev
+        {
+            self.pending_click = Some(PendingClick {
+                pos,
+                click_count,
+                extend: modifiers.shift_key(),
+            });
+            // Keep selecting while the drag wanders outside the input
+            self.is_dragging = true;
+            ctx.capture_pointer();
+            ctx.request_layout();
         }
     }
 
     fn on_input_change(&mut self, ctx: &mut EventCtx) {
-        self.last_blink = Instant::now();
+        self.restart_blink();
         ctx.request_layout();
     }
 
+    // Shows the caret and starts its blink cycle over, so it stays visible
+    // while the user is typing or clicking around
+    fn restart_blink(&mut self) {
+        self.stop_blink();
+        self.set_caret_on.set(true);
+
+        let (caret_on, set_caret_on) = (self.caret_on.clone(), self.set_caret_on.clone());
+        self.blink = Some(timer::set_interval(CARET_BLINK, move || {
+            set_caret_on.set(!caret_on.get_untracked());
+        }));
+    }
+
+    fn stop_blink(&mut self) {
+        if let Some(blink) = self.blink.take() {
+            blink.cancel();
+        }
+        self.set_caret_on.set(false);
+    }
+
     fn has_selection(&self) -> bool {
         self.cursor != self.selection_anchor
     }
@@ -119,9 +215,73 @@ impl TextInput {
             self.selection_anchor = self.cursor;
         }
     }
+
+    // Selects the word around the character at `char_idx`, or the one just
+    // before it at the end of the text
+    fn select_word(&mut self, char_idx: usize) {
+        let chars: Vec<char> = self.value.chars().collect();
+        if chars.is_empty() {
+            return;
+        }
+        let idx = char_idx.min(chars.len() - 1);
+        let class = char_class(chars[idx]);
+
+        let mut start = idx;
+        while start > 0 && char_class(chars[start - 1]) == class {
+            start -= 1;
+        }
+        let mut end = idx + 1;
+        while end < chars.len() && char_class(chars[end]) == class {
+            end += 1;
+        }
+        self.selection_anchor = start;
+        self.cursor = end;
+    }
 }
 
 impl Widget for TextInput {
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.placeholder = next.placeholder.clone();
+            self.tab_index = next.tab_index;
+        }
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any().downcast_ref::<Self>().is_some_and(|next| {
+            self.placeholder == next.placeholder && self.tab_index == next.tab_index
+        })
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn tab_index(&self) -> i32 {
+        self.tab_index
+    }
+
+    fn release(&mut self, ren: &mut Renderer) {
+        let slots = [
+            self.bg_id.take(),
+            self.cursor_id.take(),
+            self.selection_id.take(),
+        ];
+        for id in slots.into_iter().flatten() {
+            ren.free_rect(id);
+        }
+        if let Some(id) = self.text_id.take() {
+            ren.free_text(id);
+        }
+    }
+
+    fn remap_slots(&mut self, remap: &SlotRemap) {
+        remap.remap_rect(&mut self.bg_id);
+        remap.remap_rect(&mut self.cursor_id);
+        remap.remap_rect(&mut self.selection_id);
+        remap.remap_text(&mut self.text_id);
+    }
+
     fn style(&self) -> Style {
         Style {
             padding: vec2(8.0, 8.0),
@@ -164,9 +324,9 @@ impl Widget for TextInput {
             layout.size - vec2(padding * 2.0, padding * 2.0),
         );
         let text_to_draw = if self.value.is_empty() && !self.focused {
-            &self.placeholder
+            self.placeholder.clone()
         } else {
-            &self.value
+            self.value.clone()
         };
         let text_color = if self.value.is_empty() && !self.focused {
             Vec4::new(0.5, 0.5, 0.5, 1.0)
@@ -176,35 +336,32 @@ impl Widget for TextInput {
 
         let selection_instance_data: Option<RectInstance>;
         let cursor_instance_data: Option<RectInstance>;
-        let mut visible_glyphs = Vec::new();
-
-        #[derive(Copy, Clone)]
-        struct GlyphInfo {
-            pos: Vec2,
-            size: [f32; 2],
-            color: [f32; 4],
-        }
-
         {
-            let (font_system, swash_cache) = ren.font_and_swash_cache();
+            let font_system = ren.font_system();
             let metrics = Metrics::new(theme.typography.body, theme.typography.body * 1.2);
             let mut text_buffer = Buffer::new(font_system, metrics);
             let mut buffer_mut = text_buffer.borrow_with(font_system);
-            buffer_mut.set_text(text_to_draw, &Attrs::new(), Shaping::Advanced);
+            buffer_mut.set_text(&text_to_draw, &Attrs::new(), Shaping::Advanced);
             buffer_mut.shape_until_scroll(true);
 
-            if let Some(click_pos) = self.click_to_process.take() {
-                let relative_click_x = click_pos.x - content_area.origin.x + self.scroll_offset;
-                if let Some(cursor) = buffer_mut.hit(relative_click_x, 0.0) {
-                    let char_idx = text_to_draw
+            if let Some(click) = self.pending_click.take() {
+                let relative_click_x = click.pos.x - content_area.origin.x + self.scroll_offset;
+                let char_idx = match buffer_mut.hit(relative_click_x, 0.0) {
+                    Some(cursor) => text_to_draw
                         .char_indices()
                         .take_while(|(i, _)| *i < cursor.index)
-                        .count();
-                    self.move_cursor(char_idx, self.is_dragging);
-                } else {
-                    self.move_cursor(text_to_draw.chars().count(), self.is_dragging);
+                        .count(),
+                    None => text_to_draw.chars().count(),
+                };
+                match click.click_count {
+                    1 => self.move_cursor(char_idx, click.extend),
+                    2 => self.select_word(char_idx),
+                    _ => {
+                        self.selection_anchor = 0;
+                        self.cursor = self.value.chars().count();
+                    }
                 }
-                self.last_blink = Instant::now();
+                self.restart_blink();
             }
 
             let cursor_px_offset = buffer_mut.layout_runs().next().map_or(0.0, |run| {
@@ -247,52 +404,25 @@ impl Widget for TextInput {
             let max_scroll = (total_text_width - content_area.size.x).max(0.0);
             self.scroll_offset = self.scroll_offset.clamp(0.0, max_scroll);
 
-            let fg = Color::rgba(
-                (text_color.x * 255.0) as u8,
-                (text_color.y * 255.0) as u8,
-                (text_color.z * 255.0) as u8,
-                (text_color.w * 255.0) as u8,
-            );
-
-            buffer_mut.draw(swash_cache, fg, |x, y, w, h, color| {
-                let glyph_pos =
-                    vec2(x as f32, y as f32) + content_area.origin - vec2(self.scroll_offset, 0.0);
-                if glyph_pos.x + w as f32 >= content_area.origin.x
-                    && glyph_pos.x <= content_area.origin.x + content_area.size.x
+            cursor_instance_data = if self.focused && self.caret_on.get() {
+                let cursor_abs_pos =
+                    content_area.origin + vec2(cursor_px_offset - self.scroll_offset, 0.0);
+                if cursor_abs_pos.x >= content_area.origin.x
+                    && cursor_abs_pos.x <= content_area.origin.x + content_area.size.x
                 {
-                    visible_glyphs.push(GlyphInfo {
-                        pos: glyph_pos,
-                        size: [w as f32, h as f32],
-                        color: [
-                            color.r() as f32 / 255.0,
-                            color.g() as f32 / 255.0,
-                            color.b() as f32 / 255.0,
-                            color.a() as f32 / 255.0,
-                        ],
-                    });
-                }
-            });
-
-            cursor_instance_data =
-                if self.focused && self.last_blink.elapsed() < Duration::from_millis(500) {
-                    let cursor_abs_pos =
-                        content_area.origin + vec2(cursor_px_offset - self.scroll_offset, 0.0);
-                    if cursor_abs_pos.x >= content_area.origin.x
-                        && cursor_abs_pos.x <= content_area.origin.x + content_area.size.x
-                    {
-                        Some(RectInstance {
-                            pos: cursor_abs_pos.to_array(),
-                            size: [2.0, theme.typography.body],
-                            color: Vec4::from(theme.color.text).to_array(),
-                            radius: 1.0,
-                            ..Default::default()
-                        })
-                    } else {
-                        None
-                    }
+                    Some(RectInstance {
+                        pos: cursor_abs_pos.to_array(),
+                        size: [2.0, theme.typography.body],
+                        color: Vec4::from(theme.color.text).to_array(),
+                        radius: 1.0,
+                        ..Default::default()
+                    })
                 } else {
                     None
-                };
+                }
+            } else {
+                None
+            };
         }
 
         let selection_id = *self.selection_id.get_or_insert_with(|| ren.alloc_rect());
@@ -301,67 +431,59 @@ impl Widget for TextInput {
         let cursor_id = *self.cursor_id.get_or_insert_with(|| ren.alloc_rect());
         ren.update_rect(cursor_id, cursor_instance_data.unwrap_or_default());
 
-        let num_visible = visible_glyphs.len();
-        if num_visible > self.glyph_rect_ids.len() {
-            for _ in 0..(num_visible - self.glyph_rect_ids.len()) {
-                self.glyph_rect_ids.push(ren.alloc_rect());
-            }
-        }
-        for i in 0..num_visible {
-            let glyph = &visible_glyphs[i];
-            ren.update_rect(
-                self.glyph_rect_ids[i],
-                RectInstance {
-                    pos: glyph.pos.to_array(),
-                    size: glyph.size,
-                    color: glyph.color,
-                    ..Default::default()
-                },
-            );
-        }
-        for i in num_visible..self.glyph_rect_ids.len() {
-            ren.update_rect(self.glyph_rect_ids[i], RectInstance::default());
-        }
-
-        if self.focused && self.last_blink.elapsed() > Duration::from_millis(1000) {
-            self.last_blink = Instant::now();
+        // The text scrolls horizontally inside the content area
+        let text_prim = RenderPrimative::text(
+            text_to_draw,
+            content_area.origin - vec2(self.scroll_offset, 0.0),
+            text_color,
+            theme.typography.body,
+        );
+        ren.push_scissor_rect(content_area);
+        match self.text_id {
+            Some(id) => ren.update_text(id, text_prim),
+            None => self.text_id = Some(ren.push_text(text_prim)),
         }
+        ren.pop_scissor_rect();
     }
 
     fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
         if !self.focused || ctx.phase != Phase::Target {
-            if let EventKind::FocusIn = ev {
-                if ctx.phase == Phase::Target {
-                    self.focused = true;
-                    self.last_blink = Instant::now();
-                    ctx.request_layout();
-                }
+            if let EventKind::FocusIn = ev
+                && ctx.phase == Phase::Target
+            {
+                self.focused = true;
+                self.restart_blink();
+                ctx.request_layout();
             }
-            if let EventKind::PointerDown { .. } = ev {
-                if ctx.phase == Phase::Target {
-                    ctx.focus.request_focus(ctx.path);
-                    self.click_to_process = Some(self.last_pos);
-                    self.is_dragging = true;
-                    ctx.request_layout();
-                }
+            if let EventKind::PointerDown { .. } = ev
+                && ctx.phase == Phase::Target
+            {
+                ctx.focus.request_focus(ctx.path);
+                self.on_pointer_down(ctx, ev);
             }
             return;
         }
 
         match ev {
+            EventKind::PointerDown { .. } => self.on_pointer_down(ctx, ev),
             EventKind::FocusOut => {
                 self.focused = false;
                 self.is_dragging = false;
+                if ctx.has_pointer_capture() {
+                    ctx.release_pointer();
+                }
+                self.stop_blink();
                 ctx.request_layout();
             }
-            EventKind::PointerMove { pos, .. } => {
-                self.last_pos = *pos;
-                if self.is_dragging {
-                    self.click_to_process = Some(*pos);
-                    ctx.request_layout();
-                }
+            EventKind::PointerMove { pos, .. } if self.is_dragging => {
+                self.pending_click = Some(PendingClick {
+                    pos: *pos,
+                    click_count: 1,
+                    extend: true,
+                });
+                ctx.request_layout();
             }
-            EventKind::PointerUp { .. } => {
+            EventKind::PointerUp { .. } | EventKind::PointerCancel => {
                 self.is_dragging = false;
             }
             EventKind::CharInput { ch } => {
@@ -395,18 +517,18 @@ impl Widget for TextInput {
                             self.delete_selection();
                         }
                         Key::Character(s) if s == "v" => {
-                            if let Ok(mut clip) = Clipboard::new() {
-                                if let Ok(text) = clip.get_text() {
-                                    let sanitized_text = text.replace('\r', "").replace('\n', " ");
-
-                                    self.delete_selection();
-                                    let byte_idx = self.get_byte_index(self.cursor);
-                                    self.value.insert_str(byte_idx, &sanitized_text);
-                                    self.move_cursor(
-                                        self.cursor + sanitized_text.chars().count(),
-                                        false,
-                                    );
-                                }
+                            if let Ok(mut clip) = Clipboard::new()
+                                && let Ok(text) = clip.get_text()
+                            {
+                                let sanitized_text = text.replace('\r', "").replace('\n', " ");
+
+                                self.delete_selection();
+                                let byte_idx = self.get_byte_index(self.cursor);
+                                self.value.insert_str(byte_idx, &sanitized_text);
+                                self.move_cursor(
+                                    self.cursor + sanitized_text.chars().count(),
+                                    false,
+                                );
                             }
                         }
                         _ => {}
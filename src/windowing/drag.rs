@@ -0,0 +1,89 @@
+use std::any::Any;
+
+use glam::{Vec2, Vec4, vec2};
+
+use crate::{renderer::Renderer, style::Theme};
+
+// Drawn under the pointer while a drag is in progress, on top of everything
+// else
+#[derive(Clone, Debug)]
+pub struct DragPreview {
+    pub size: Vec2,
+    pub color: Vec4,
+    pub label: Option<String>,
+    // Where the pointer holds the preview, from its top-left corner
+    pub offset: Vec2,
+}
+
+impl DragPreview {
+    pub fn new(size: Vec2, color: impl Into<Vec4>) -> Self {
+        Self {
+            size,
+            color: color.into(),
+            label: None,
+            offset: size / 2.0,
+        }
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+}
+
+// A drag from `start_drag` until it is dropped or cancelled
+pub(crate) struct DragSession {
+    pub(crate) payload: Box<dyn Any>,
+    pub(crate) preview: DragPreview,
+    pub(crate) source: Vec<usize>,
+    pub(crate) pos: Vec2,
+    // The path under the pointer that was last sent `DragEnter`
+    pub(crate) over: Option<Vec<usize>>,
+    // Whether a node on `over` accepted the drag
+    pub(crate) accepted: bool,
+    // False until the node tree has taken over the pointer from the source
+    pub(crate) active: bool,
+}
+
+impl DragSession {
+    pub(crate) fn new(payload: Box<dyn Any>, preview: DragPreview, source: Vec<usize>) -> Self {
+        Self {
+            payload,
+            preview,
+            source,
+            pos: Vec2::ZERO,
+            over: None,
+            accepted: false,
+            active: false,
+        }
+    }
+
+    // The preview fades while it is over something that won't take it
+    pub(crate) fn paint_preview(&self, ren: &mut Renderer, theme: &Theme) {
+        let preview = &self.preview;
+        let origin = self.pos - preview.offset;
+        let alpha = if self.accepted { 0.9 } else { 0.5 };
+
+        ren.push_z_index(i32::MAX);
+        ren.draw_rounded_rect(
+            origin,
+            preview.size,
+            theme.radius.md,
+            preview.color.truncate().extend(preview.color.w * alpha),
+        );
+        if let Some(label) = &preview.label {
+            ren.draw_text(
+                label,
+                origin + vec2(theme.spacing.md, theme.spacing.sm),
+                Vec4::from(theme.color.text),
+                theme.typography.body,
+            );
+        }
+        ren.pop_z_index();
+    }
+}
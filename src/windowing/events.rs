@@ -1,21 +1,92 @@
+use std::{
+    any::Any,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
 use glam::Vec2;
 use winit::keyboard::ModifiersState;
 use winit::{event::MouseButton, keyboard::Key};
 
+use crate::{
+    timer::{self, TimerHandle},
+    windowing::{DragPreview, DragSession, Traversal},
+};
+
+// Pointer positions are in logical pixels relative to the window; use
+// `EventCtx::local_pos` for the receiving node's coordinates.
 #[derive(Clone, Debug)]
 pub enum EventKind {
-    PointerDown { button: MouseButton, pos: Vec2 },
-    PointerUp { button: MouseButton, pos: Vec2 },
-    PointerMove { pos: Vec2 },
+    // `click_count` is 2 for the second press of a double click, 3 for a
+    // triple click and so on
+    PointerDown {
+        button: MouseButton,
+        pos: Vec2,
+        click_count: u32,
+        modifiers: ModifiersState,
+    },
+    PointerUp {
+        button: MouseButton,
+        pos: Vec2,
+        click_count: u32,
+        modifiers: ModifiersState,
+    },
+    PointerMove {
+        pos: Vec2,
+        modifiers: ModifiersState,
+    },
     PointerLeave,
-    Wheel { delta: Vec2 },
+    // The pointer was taken away mid-interaction, e.g. the window lost
+    // focus; sent to the node holding pointer capture, which is released
+    PointerCancel,
+    Wheel {
+        delta: Vec2,
+        pos: Vec2,
+    },
 
-    KeyDown { key: Key },
-    KeyUp { key: Key },
-    CharInput { ch: char },
+    KeyDown {
+        key: Key,
+    },
+    KeyUp {
+        key: Key,
+    },
+    CharInput {
+        ch: char,
+    },
 
     FocusIn,
     FocusOut,
+
+    // Sent along the path under the pointer while something is dragged over
+    // it. Handlers read the payload with `EventCtx::drag_payload` and call
+    // `accept_drag` to take it; `Drop` only arrives once one has.
+    DragEnter {
+        pos: Vec2,
+    },
+    DragOver {
+        pos: Vec2,
+    },
+    DragLeave,
+    Drop {
+        pos: Vec2,
+    },
+    // Sent to the node that started the drag once it's over
+    DragEnd {
+        dropped: bool,
+    },
+
+    // Files dragged in from outside the app, sent along the path under the
+    // pointer. `FileHoverCancel` goes to the last path that got a hover when
+    // the files leave it or the drag is abandoned.
+    FileHover {
+        paths: Vec<PathBuf>,
+        pos: Vec2,
+    },
+    FileHoverCancel,
+    FileDrop {
+        paths: Vec<PathBuf>,
+        pos: Vec2,
+    },
 }
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
@@ -30,9 +101,12 @@ pub struct EventCtx<'a> {
     pub focus: &'a mut FocusManager,
     pub path: &'a [usize],
     pub node_layout: crate::layout::Rect,
+    // How much of `path` leads to the node handling the event
+    node_depth: usize,
     stopped: bool,
     default_prevented: bool,
     pub layout_requested: bool,
+    pub paint_requested: bool,
     pub modifiers: ModifiersState,
 }
 
@@ -41,6 +115,13 @@ impl<'a> EventCtx<'a> {
         self.layout_requested = true;
     }
 
+    // Repaints the node handling this event without laying it out again,
+    // for changes that only affect how it looks. Nodes aren't repainted
+    // just for receiving an event.
+    pub fn request_paint(&mut self) {
+        self.paint_requested = true;
+    }
+
     pub fn stop_propagation(&mut self) {
         self.stopped = true;
     }
@@ -53,6 +134,85 @@ impl<'a> EventCtx<'a> {
         self.stopped
     }
 
+    pub fn is_default_prevented(&self) -> bool {
+        self.default_prevented
+    }
+
+    // Path of the node handling the event, which in the capture and bubble
+    // phases is an ancestor of the target
+    pub fn node_path(&self) -> &[usize] {
+        &self.path[..self.node_depth]
+    }
+
+    pub(crate) fn set_node(&mut self, depth: usize, layout: crate::layout::Rect) {
+        self.node_depth = depth;
+        self.node_layout = layout;
+    }
+
+    // Sends every pointer move and button event to the node handling this
+    // one, wherever the pointer goes, until released. Capture ends by
+    // itself after the next button release or a `PointerCancel`.
+    pub fn capture_pointer(&mut self) {
+        self.focus.pointer_capture = Some(self.path[..self.node_depth].to_vec());
+    }
+
+    pub fn release_pointer(&mut self) {
+        self.focus.pointer_capture = None;
+    }
+
+    // Starts dragging `payload` from the node handling this event. The
+    // preview follows the pointer and drag events go to whatever is under
+    // it until the button is released.
+    pub fn start_drag(&mut self, payload: impl Any, preview: DragPreview) {
+        if self.focus.drag.is_some() {
+            return;
+        }
+        self.focus.drag = Some(DragSession::new(
+            Box::new(payload),
+            preview,
+            self.node_path().to_vec(),
+        ));
+    }
+
+    // The payload of the drag in progress, if there is one of type `T`
+    pub fn drag_payload<T: Any>(&self) -> Option<&T> {
+        self.focus.drag.as_ref()?.payload.downcast_ref()
+    }
+
+    // Lets the drag be dropped on the path it is over; handlers call this
+    // from `DragEnter` or `DragOver`
+    pub fn accept_drag(&mut self) {
+        if let Some(drag) = &mut self.focus.drag {
+            drag.accepted = true;
+        }
+    }
+
+    pub fn reject_drag(&mut self) {
+        if let Some(drag) = &mut self.focus.drag {
+            drag.accepted = false;
+        }
+    }
+
+    pub fn has_pointer_capture(&self) -> bool {
+        self.focus.pointer_capture.as_deref() == Some(self.node_path())
+    }
+
+    // A window position relative to the top-left of the node handling the
+    // event
+    pub fn local_pos(&self, pos: Vec2) -> Vec2 {
+        pos - self.node_layout.origin
+    }
+
+    // Runs `f` once after `delay`, unless this node is dropped first
+    pub fn set_timeout(&self, delay: Duration, f: impl FnOnce() + 'static) -> TimerHandle {
+        timer::set_timeout(delay, f)
+    }
+
+    // Runs `f` every `period` until cancelled or this node is dropped
+    pub fn set_interval(&self, period: Duration, f: impl FnMut() + 'static) -> TimerHandle {
+        timer::set_interval(period, f)
+    }
+
     pub fn new(
         phase: Phase,
         focus: &'a mut FocusManager,
@@ -65,19 +225,69 @@ impl<'a> EventCtx<'a> {
             focus,
             path,
             node_layout,
+            node_depth: path.len(),
             stopped: false,
             default_prevented: false,
             layout_requested: false,
+            paint_requested: false,
             modifiers,
         }
     }
 }
 
+// How close in time and space presses must be to count as one multi-click
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClickConfig {
+    pub max_interval: Duration,
+    pub max_distance: f32,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self {
+            max_interval: Duration::from_millis(500),
+            max_distance: 4.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct ClickTracker {
+    pub(crate) config: ClickConfig,
+    last_press: Option<(Instant, Vec2, MouseButton)>,
+    count: u32,
+}
+
+impl ClickTracker {
+    // Counts a press, returning its click count
+    pub(crate) fn press(&mut self, button: MouseButton, pos: Vec2) -> u32 {
+        let now = timer::now();
+        let continues = self.last_press.is_some_and(|(at, last_pos, last_button)| {
+            last_button == button
+                && now.saturating_duration_since(at) <= self.config.max_interval
+                && last_pos.distance(pos) <= self.config.max_distance
+        });
+
+        self.count = if continues { self.count + 1 } else { 1 };
+        self.last_press = Some((now, pos, button));
+        self.count
+    }
+
+    // The count of the press a release belongs to
+    pub(crate) fn current(&self) -> u32 {
+        self.count.max(1)
+    }
+}
+
 #[derive(Default)]
 pub struct FocusManager {
     focused_path: Vec<usize>,
     change_request: Option<Vec<usize>>,
+    traversal: Option<Traversal>,
     pub modifiers: ModifiersState,
+    pub(crate) clicks: ClickTracker,
+    pub(crate) pointer_capture: Option<Vec<usize>>,
+    pub(crate) drag: Option<DragSession>,
 }
 
 impl FocusManager {
@@ -93,6 +303,20 @@ impl FocusManager {
         self.change_request = Some(path.to_vec());
     }
 
+    pub fn set_click_config(&mut self, config: ClickConfig) {
+        self.clicks.config = config;
+    }
+
+    // Moves focus to the next node in tab order, as Tab does
+    pub fn focus_next(&mut self) {
+        self.traversal = Some(Traversal::Next);
+    }
+
+    // Moves focus to the previous node in tab order, as Shift+Tab does
+    pub fn focus_prev(&mut self) {
+        self.traversal = Some(Traversal::Prev);
+    }
+
     pub fn blur(&mut self) {
         if !self.focused_path.is_empty() {
             self.change_request = Some(Vec::new());
@@ -103,11 +327,24 @@ impl FocusManager {
         &self.focused_path
     }
 
+    // Path of the node holding pointer capture, if any
+    pub fn pointer_capture(&self) -> Option<&[usize]> {
+        self.pointer_capture.as_deref()
+    }
+
+    pub fn is_dragging(&self) -> bool {
+        self.drag.as_ref().is_some_and(|drag| drag.active)
+    }
+
     #[allow(dead_code)]
     pub fn has_focus(&self, path: &[usize]) -> bool {
         path == self.focused_path
     }
 
+    pub(crate) fn take_traversal(&mut self) -> Option<Traversal> {
+        self.traversal.take()
+    }
+
     pub(crate) fn take_change_request(&mut self) -> Option<Vec<usize>> {
         self.change_request.take()
     }
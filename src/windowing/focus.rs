@@ -0,0 +1,155 @@
+use glam::Vec2;
+
+use crate::layout::node::Node;
+
+// Groups a subtree for keyboard traversal
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum FocusScope {
+    // Tab indices inside are only ordered against each other, and the group
+    // as a whole is visited where it sits in the tree
+    Group,
+    // A group that Tab and Shift+Tab cycle within instead of leaving. While
+    // one is in the tree, focus outside of it moves into it; for dialogs.
+    Trap,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Traversal {
+    Next,
+    Prev,
+}
+
+// A stop in a scope's tab order: either a focusable node or a whole nested
+// group, already ordered
+struct Stop {
+    tab_index: i32,
+    paths: Vec<Vec<usize>>,
+}
+
+// The focusable nodes under `path`, in the order Tab visits them. Positive
+// tab indices come first in ascending order, then zero in tree order;
+// negative ones can only be focused directly.
+fn tab_order(node: &Node, path: &mut Vec<usize>) -> Vec<Vec<usize>> {
+    let mut stops = Vec::new();
+    collect_stops(node, path, &mut stops);
+    stops.sort_by_key(|stop| match stop.tab_index {
+        0 => (1, 0),
+        index => (0, index),
+    });
+    stops.into_iter().flat_map(|stop| stop.paths).collect()
+}
+
+fn collect_stops(node: &Node, path: &mut Vec<usize>, stops: &mut Vec<Stop>) {
+    let widget = &node.widget;
+    if widget.focusable() && widget.tab_index() >= 0 {
+        stops.push(Stop {
+            tab_index: widget.tab_index(),
+            paths: vec![path.clone()],
+        });
+    }
+
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        if child.widget.focus_scope().is_some() {
+            stops.push(Stop {
+                tab_index: 0,
+                paths: tab_order(child, path),
+            });
+        } else {
+            collect_stops(child, path, stops);
+        }
+        path.pop();
+    }
+}
+
+// The innermost trap containing `focused`, or failing that the first trap in
+// the tree
+fn active_trap(root: &Node, focused: &[usize]) -> Option<Vec<usize>> {
+    let mut node = root;
+    let mut trap = None;
+    for (depth, &i) in focused.iter().enumerate() {
+        let Some(child) = node.children.get(i) else {
+            break;
+        };
+        if child.widget.focus_scope() == Some(FocusScope::Trap) {
+            trap = Some(focused[..=depth].to_vec());
+        }
+        node = child;
+    }
+    trap.or_else(|| find_trap(root, &mut Vec::new()))
+}
+
+fn find_trap(node: &Node, path: &mut Vec<usize>) -> Option<Vec<usize>> {
+    for (i, child) in node.children.iter().enumerate() {
+        path.push(i);
+        if child.widget.focus_scope() == Some(FocusScope::Trap) {
+            return Some(path.clone());
+        }
+        if let Some(found) = find_trap(child, path) {
+            return Some(found);
+        }
+        path.pop();
+    }
+    None
+}
+
+// Where focus goes from `focused` when tabbing in `direction`, wrapping at
+// either end. None if there is nothing focusable.
+pub(crate) fn traverse(root: &Node, focused: &[usize], direction: Traversal) -> Option<Vec<usize>> {
+    let mut scope = active_trap(root, focused).unwrap_or_default();
+    let scope_node = root.node_at(&scope)?;
+    let order = tab_order(scope_node, &mut scope);
+    if order.is_empty() {
+        return None;
+    }
+
+    let last = order.len() - 1;
+    let index = match (order.iter().position(|p| p == focused), direction) {
+        (Some(i), Traversal::Next) => (i + 1) % order.len(),
+        (Some(i), Traversal::Prev) => i.checked_sub(1).unwrap_or(last),
+        // Coming from outside the order, or from a node tabbing skips,
+        // start from whichever end we're moving away from
+        (None, Traversal::Next) => 0,
+        (None, Traversal::Prev) => last,
+    };
+    Some(order[index].clone())
+}
+
+// Asks every ancestor of the node at `path`, innermost first, to scroll it
+// into view. Ancestors that moved their content are repainted.
+pub(crate) fn scroll_into_view(root: &mut Node, path: &[usize]) {
+    let Some(mut target) = root.node_at(path).map(|node| node.layout_rect) else {
+        return;
+    };
+
+    for depth in (0..path.len()).rev() {
+        let Some(ancestor) = node_at_mut(root, &path[..depth]) else {
+            continue;
+        };
+        let shift = ancestor
+            .widget
+            .scroll_into_view(target, ancestor.layout_rect);
+        if shift != Vec2::ZERO {
+            target.origin -= shift;
+            ancestor.mark_dirty();
+            mark_path_dirty(root, &path[..depth]);
+        }
+    }
+}
+
+fn node_at_mut<'a>(node: &'a mut Node, path: &[usize]) -> Option<&'a mut Node> {
+    match path.split_first() {
+        Some((&idx, rest)) => node_at_mut(node.children.get_mut(idx)?, rest),
+        None => Some(node),
+    }
+}
+
+// Lets layout reach the node at `path` on the next frame
+fn mark_path_dirty(node: &mut Node, path: &[usize]) {
+    if let Some((&idx, rest)) = path.split_first() {
+        node.mark_child_dirty();
+        if let Some(child) = node.children.get_mut(idx) {
+            mark_path_dirty(child, rest);
+        }
+    }
+}
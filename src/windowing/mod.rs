@@ -1,4 +1,11 @@
+mod drag;
 pub mod events;
+mod focus;
+
+pub use drag::DragPreview;
+pub(crate) use drag::DragSession;
+pub use focus::FocusScope;
+pub(crate) use focus::{Traversal, scroll_into_view, traverse};
 
 pub struct Window {
     pub root: crate::layout::node::Node,
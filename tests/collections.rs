@@ -0,0 +1,145 @@
+use std::{cell::Cell, collections::HashMap, rc::Rc};
+
+use cosmic_text::FontSystem;
+use luna::{
+    For, ForMap, Renderer, Theme, Vec2,
+    layout::node::Node,
+    signals::{NodeId, SignalMap, SignalVec},
+    snapshot::Snapshot,
+    vec2,
+    widgets::{BuildCtx, Widget},
+};
+
+// A row that is cheap to lay out, so long lists stay fast
+#[derive(Clone)]
+struct Row(u32);
+
+impl Widget for Row {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        Vec::new()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        vec2(100.0, 2.0)
+    }
+
+    fn paint(&mut self, _node: &mut Node, _ren: &mut Renderer, _theme: &Theme) {}
+
+    fn update(&mut self, next: &dyn Widget) {
+        if let Some(next) = next.as_any().downcast_ref::<Self>() {
+            self.0 = next.0;
+        }
+    }
+}
+
+// Counts how many rows `render` was asked for
+fn counted<K>(renders: &Rc<Cell<usize>>) -> impl Fn(&K, &u32) -> Row + 'static {
+    let renders = renders.clone();
+    move |_, value| {
+        renders.set(renders.get() + 1);
+        Row(*value)
+    }
+}
+
+fn mount(widget: impl Widget + 'static) -> Snapshot {
+    let mut snap = Snapshot::new(Box::new(widget), Theme::default(), 200, 200);
+    snap.render().unwrap();
+    snap
+}
+
+fn row_ids(snap: &mut Snapshot) -> Vec<NodeId> {
+    snap.root().children.iter().map(|row| row.id).collect()
+}
+
+fn row_values(snap: &mut Snapshot) -> Vec<u32> {
+    let rows = snap.root().children.len();
+    (0..rows)
+        .map(|i| snap.root().widget_at::<Row>(&[i]).unwrap().0)
+        .collect()
+}
+
+fn list(len: u32) -> (SignalVec<u32>, Rc<Cell<usize>>, Snapshot) {
+    let items = SignalVec::new((0..len).collect());
+    let renders = Rc::new(Cell::new(0));
+    let render = counted::<()>(&renders);
+    let snap = mount(For::new(items.clone(), move |value| render(&(), value)));
+    (items, renders, snap)
+}
+
+#[test]
+fn pushing_to_a_long_list_builds_one_node() {
+    let (items, renders, mut snap) = list(1000);
+    assert_eq!(renders.get(), 1000);
+    let before = row_ids(&mut snap);
+
+    items.push(1000);
+    snap.render().unwrap();
+
+    let after = row_ids(&mut snap);
+    assert_eq!(renders.get(), 1001);
+    assert_eq!(after.len(), 1001);
+    assert_eq!(after[..1000], before[..]);
+    assert!(!before.contains(&after[1000]));
+}
+
+#[test]
+fn removing_and_moving_rows_keeps_the_other_nodes() {
+    let (items, renders, mut snap) = list(10);
+    let mut expected = row_ids(&mut snap);
+
+    items.remove(3);
+    snap.render().unwrap();
+    expected.remove(3);
+    assert_eq!(row_ids(&mut snap), expected);
+
+    items.move_item(0, 5);
+    snap.render().unwrap();
+    let moved = expected.remove(0);
+    expected.insert(5, moved);
+    assert_eq!(row_ids(&mut snap), expected);
+    assert_eq!(row_values(&mut snap), [1, 2, 4, 5, 6, 0, 7, 8, 9]);
+
+    assert_eq!(renders.get(), 10);
+}
+
+#[test]
+fn updating_a_row_reuses_its_node() {
+    let (items, renders, mut snap) = list(5);
+    let before = row_ids(&mut snap);
+
+    items.set(2, 20);
+    snap.render().unwrap();
+
+    assert_eq!(row_ids(&mut snap), before);
+    assert_eq!(row_values(&mut snap), [0, 1, 20, 3, 4]);
+    assert_eq!(renders.get(), 6);
+}
+
+#[test]
+fn map_diffs_touch_only_their_entry() {
+    let entries = SignalMap::new(HashMap::from([("a", 1), ("c", 3), ("d", 4)]));
+    let renders = Rc::new(Cell::new(0));
+    let mut snap = mount(ForMap::new(entries.clone(), counted(&renders)));
+    assert_eq!(row_values(&mut snap), [1, 3, 4]);
+    let before = row_ids(&mut snap);
+
+    // Inserted rows land in key order
+    entries.insert("b", 2);
+    snap.render().unwrap();
+    let ids = row_ids(&mut snap);
+    assert_eq!(row_values(&mut snap), [1, 2, 3, 4]);
+    assert_eq!([ids[0], ids[2], ids[3]], before[..]);
+    assert!(!before.contains(&ids[1]));
+
+    entries.insert("c", 30);
+    snap.render().unwrap();
+    assert_eq!(row_ids(&mut snap), ids);
+    assert_eq!(row_values(&mut snap), [1, 2, 30, 4]);
+
+    entries.remove(&"a");
+    snap.render().unwrap();
+    assert_eq!(row_ids(&mut snap), ids[1..]);
+    assert_eq!(row_values(&mut snap), [2, 30, 4]);
+
+    assert_eq!(renders.get(), 5);
+}
@@ -0,0 +1,134 @@
+use std::{cell::RefCell, rc::Rc};
+
+use luna::{
+    Canvas, Display, Element, FlexDir, For, Style, Theme, Vec4,
+    renderer::{
+        CircId, LineId,
+        primatives::{CircleInstance, LineInstance},
+    },
+    signals::{ReadSignal, SignalVec, create_signal},
+    snapshot::Snapshot,
+};
+
+// Enough frames for the renderer to compact its pools at least once
+const FRAMES: usize = 300;
+
+fn swatch(shade: &u32) -> Element {
+    let shade = *shade as f32 / 40.0;
+    Element::new()
+        .key(shade.to_bits())
+        .width(200.0)
+        .height(4.0)
+        .background_color(Vec4::new(shade, 1.0 - shade, 0.5, 1.0))
+}
+
+#[test]
+fn compaction_keeps_surviving_widgets_drawing_their_own_slots() {
+    let shades = SignalVec::new((0..40).collect::<Vec<u32>>());
+    let mut column = Style {
+        display: Display::Flex,
+        ..Default::default()
+    };
+    column.flex.dir = FlexDir::Column;
+    let list = For::new(shades.clone(), swatch).with_style(column);
+
+    let mut snap = Snapshot::new(
+        Box::new(Element::new().child(list)),
+        Theme::default(),
+        200,
+        160,
+    );
+    snap.render().unwrap();
+
+    // Freeing the slots at the front leaves the survivors to be moved down
+    for _ in 0..20 {
+        shades.remove(0);
+    }
+    let expected = snap.render().unwrap();
+
+    for _ in 0..FRAMES {
+        snap.render().unwrap();
+    }
+    assert_eq!(snap.render().unwrap(), expected);
+
+    // And they keep updating the right slots afterwards
+    shades.push(40);
+    let mut reference = Snapshot::new(
+        Box::new(Element::new().child(
+            For::new(SignalVec::new((20..=40).collect::<Vec<u32>>()), swatch).with_style(column),
+        )),
+        Theme::default(),
+        200,
+        160,
+    );
+    assert_eq!(snap.render().unwrap(), reference.render().unwrap());
+}
+
+type Slots = Rc<RefCell<(Option<LineId>, Option<CircId>)>>;
+
+// A canvas keeping a line and a circle slot across frames. With `scratch` it
+// frees two slots below them first, so compaction has to move them.
+fn retained(shift: ReadSignal<f32>, scratch: bool) -> (Canvas, Slots) {
+    let slots: Slots = Rc::new(RefCell::new((None, None)));
+    let canvas = Canvas::new({
+        let slots = slots.clone();
+        move |ren| {
+            let mut slots = slots.borrow_mut();
+            if slots.0.is_none() {
+                let freed = scratch.then(|| (ren.alloc_line(), ren.alloc_circle()));
+                *slots = (Some(ren.alloc_line()), Some(ren.alloc_circle()));
+                if let Some((line, circle)) = freed {
+                    ren.free_line(line);
+                    ren.free_circle(circle);
+                }
+            }
+            let x = shift.get();
+            ren.update_line(
+                slots.0.unwrap(),
+                LineInstance {
+                    a: [x + 10.0, 10.0],
+                    b: [x + 60.0, 50.0],
+                    color: [0.9, 0.3, 0.2, 1.0],
+                    half_width: 2.0,
+                    ..Default::default()
+                },
+            );
+            ren.update_circle(
+                slots.1.unwrap(),
+                CircleInstance {
+                    center: [x + 100.0, 40.0],
+                    radius: 20.0,
+                    color: [0.2, 0.4, 0.9, 1.0],
+                    ..Default::default()
+                },
+            );
+        }
+    })
+    .with_remap({
+        let slots = slots.clone();
+        move |remap| {
+            let mut slots = slots.borrow_mut();
+            remap.remap_line(&mut slots.0);
+            remap.remap_circle(&mut slots.1);
+        }
+    });
+    (canvas, slots)
+}
+
+#[test]
+fn compaction_remaps_slots_a_canvas_keeps() {
+    let (shift, set_shift) = create_signal(0.0);
+    let (canvas, slots) = retained(shift, true);
+    let mut snap = Snapshot::new(Box::new(canvas), Theme::default(), 200, 80);
+    for _ in 0..FRAMES {
+        snap.render().unwrap();
+    }
+    assert_eq!(*slots.borrow(), (Some(0), Some(0)));
+
+    // Repainting writes the moved slots, not the ones they left behind
+    set_shift.set(20.0);
+    let (shift, _) = create_signal(20.0);
+    let (canvas, _) = retained(shift, false);
+    let mut reference = Snapshot::new(Box::new(canvas), Theme::default(), 200, 80);
+    assert_eq!(snap.render().unwrap(), reference.render().unwrap());
+}
@@ -0,0 +1,489 @@
+use std::{cell::RefCell, path::PathBuf, rc::Rc, slice, time::Duration};
+
+use cosmic_text::FontSystem;
+use luna::{
+    Checkbox, Display, Element, FlexDir, Image, Key, Renderer, TextInput, Theme, Vec2, Vec4,
+    Widget,
+    layout::node::Node,
+    signals::create_signal,
+    snapshot::Snapshot,
+    timer::{MockClock, set_clock},
+    vec2,
+    widgets::BuildCtx,
+    windowing::{
+        DragPreview, FocusScope,
+        events::{EventCtx, EventKind, FocusManager, Phase},
+    },
+};
+use winit::keyboard::ModifiersState;
+
+type Log = Rc<RefCell<Vec<(&'static str, String)>>>;
+
+// Takes the pointer when pressed and logs the pointer events it receives
+#[derive(Clone)]
+struct Pad {
+    name: &'static str,
+    log: Log,
+}
+
+impl Widget for Pad {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        Vec::new()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        vec2(50.0, 50.0)
+    }
+
+    fn paint(&mut self, _node: &mut Node, _ren: &mut Renderer, _theme: &Theme) {}
+
+    fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
+        if ctx.phase != Phase::Target {
+            return;
+        }
+        let entry = match *ev {
+            EventKind::PointerDown { .. } => {
+                ctx.capture_pointer();
+                "down".to_string()
+            }
+            EventKind::PointerMove { pos, .. } => format!("move {pos}"),
+            EventKind::PointerUp { pos, .. } => format!("up {pos}"),
+            EventKind::PointerCancel => "cancel".to_string(),
+            _ => return,
+        };
+        self.log.borrow_mut().push((self.name, entry));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Role {
+    // Starts dragging a number when pressed
+    Source,
+    Refuses,
+    Takes,
+}
+
+// Takes part in drags as its role says and logs what happens to it
+#[derive(Clone)]
+struct Dock {
+    name: &'static str,
+    role: Role,
+    log: Log,
+}
+
+impl Widget for Dock {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        Vec::new()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        vec2(50.0, 50.0)
+    }
+
+    fn paint(&mut self, _node: &mut Node, _ren: &mut Renderer, _theme: &Theme) {}
+
+    fn event(&mut self, ctx: &mut EventCtx, ev: &EventKind) {
+        if ctx.phase != Phase::Target {
+            return;
+        }
+        let entry = match *ev {
+            EventKind::PointerDown { .. } if self.role == Role::Source => {
+                ctx.start_drag(7u32, DragPreview::new(vec2(20.0, 20.0), Vec4::ONE));
+                return;
+            }
+            EventKind::DragEnter { .. } => {
+                if self.role == Role::Takes {
+                    ctx.accept_drag();
+                } else {
+                    ctx.reject_drag();
+                }
+                "enter".to_string()
+            }
+            EventKind::DragLeave => "leave".to_string(),
+            EventKind::Drop { .. } => format!("drop {:?}", ctx.drag_payload::<u32>()),
+            EventKind::DragEnd { dropped } => format!("end {dropped}"),
+            _ => return,
+        };
+        self.log.borrow_mut().push((self.name, entry));
+    }
+}
+
+// Lays the widgets out side by side, returning the centre of each
+fn row(widgets: Vec<Box<dyn Widget>>) -> (Snapshot, Vec<Vec2>) {
+    let count = widgets.len();
+    let root = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Row)
+        .padding(10.0)
+        .gap(10.0)
+        .children(widgets);
+    let mut snap = Snapshot::new(Box::new(root), Theme::default(), 300, 200);
+    snap.render().unwrap();
+    let centers = (0..count)
+        .map(|i| {
+            let rect = snap.root().node_at(&[i]).unwrap().layout_rect;
+            rect.origin + rect.size / 2.0
+        })
+        .collect();
+    (snap, centers)
+}
+
+// Two pads side by side, with the centre of each
+fn pads(log: &Log) -> (Snapshot, Vec2, Vec2) {
+    let pad = |name| {
+        Box::new(Pad {
+            name,
+            log: log.clone(),
+        }) as Box<dyn Widget>
+    };
+    let (snap, centers) = row(vec![pad("a"), pad("b")]);
+    (snap, centers[0], centers[1])
+}
+
+// The root is never a hover target, so widgets under test sit one level down
+fn mount(child: impl Widget + 'static) -> Snapshot {
+    let root = Element::new().padding(10.0).child(child);
+    let mut snap = Snapshot::new(Box::new(root), Theme::default(), 300, 200);
+    snap.render().unwrap();
+    snap
+}
+
+#[test]
+fn clicking_a_checkbox_toggles_its_signal() {
+    let (checked, set_checked) = create_signal(false);
+    let mut snap = mount(Checkbox::new(
+        "Accept".to_string(),
+        (checked.clone(), set_checked),
+    ));
+    let mut focus = FocusManager::default();
+
+    let pos = snap.root().node_at(&[0]).unwrap().origin() + vec2(4.0, 4.0);
+    snap.root().click(pos, &mut focus);
+    assert!(checked.get());
+
+    snap.render().unwrap();
+    snap.root().click(pos, &mut focus);
+    assert!(!checked.get());
+}
+
+#[test]
+fn ctrl_a_selects_all_text() {
+    let mut snap = mount(TextInput::new("Name"));
+    let mut focus = FocusManager::default();
+
+    let pos = snap.root().node_at(&[0]).unwrap().origin() + vec2(4.0, 4.0);
+    snap.root().click(pos, &mut focus);
+    snap.root().type_text("hello", &mut focus);
+    snap.root().press(
+        Key::Character("a".into()),
+        ModifiersState::CONTROL,
+        &mut focus,
+    );
+
+    let input = snap.root().widget_at::<TextInput>(&[0]).unwrap();
+    assert_eq!(input.value, "hello");
+    assert_eq!(input.selection(), (0, 5));
+}
+
+// A focused input holding two words, and a point over the first one. Time
+// only moves on when `clock` is advanced.
+fn two_words(clock: &MockClock) -> (Snapshot, FocusManager, Vec2) {
+    set_clock(clock.clone());
+    let mut snap = mount(TextInput::new("Name"));
+    let mut focus = FocusManager::default();
+
+    let pos = snap.root().node_at(&[0]).unwrap().origin() + vec2(4.0, 4.0);
+    snap.root().click(pos, &mut focus);
+    snap.root().type_text("hello world", &mut focus);
+    // Outside the multi-click window of the focusing click
+    clock.advance(Duration::from_secs(1));
+    snap.render().unwrap();
+    (snap, focus, pos)
+}
+
+// Clicks are applied when the input next paints
+fn click_and_render(snap: &mut Snapshot, pos: Vec2, focus: &mut FocusManager) -> (usize, usize) {
+    snap.root().click(pos, focus);
+    snap.render().unwrap();
+    snap.root()
+        .widget_at::<TextInput>(&[0])
+        .unwrap()
+        .selection()
+}
+
+#[test]
+fn double_click_selects_a_word_and_triple_click_everything() {
+    let clock = MockClock::new();
+    let (mut snap, mut focus, pos) = two_words(&clock);
+
+    assert_eq!(click_and_render(&mut snap, pos, &mut focus), (0, 0));
+    clock.advance(Duration::from_millis(100));
+    assert_eq!(click_and_render(&mut snap, pos, &mut focus), (0, 5));
+    clock.advance(Duration::from_millis(100));
+    assert_eq!(click_and_render(&mut snap, pos, &mut focus), (0, 11));
+}
+
+#[test]
+fn slow_clicks_do_not_select() {
+    let clock = MockClock::new();
+    let (mut snap, mut focus, pos) = two_words(&clock);
+
+    click_and_render(&mut snap, pos, &mut focus);
+    clock.advance(Duration::from_millis(600));
+    assert_eq!(click_and_render(&mut snap, pos, &mut focus), (0, 0));
+}
+
+// Paths focused by pressing Tab (or Shift+Tab when `back`) `presses` times
+fn tab_through(
+    snap: &mut Snapshot,
+    focus: &mut FocusManager,
+    presses: usize,
+    back: bool,
+) -> Vec<Vec<usize>> {
+    (0..presses)
+        .map(|_| {
+            if back {
+                snap.root().shift_tab(focus);
+            } else {
+                snap.root().tab(focus);
+            }
+            focus.path().to_vec()
+        })
+        .collect()
+}
+
+fn inputs(widgets: Vec<Box<dyn Widget>>) -> Snapshot {
+    let root = Element::new().padding(10.0).gap(8.0).children(widgets);
+    let mut snap = Snapshot::new(Box::new(root), Theme::default(), 300, 300);
+    snap.render().unwrap();
+    snap
+}
+
+fn input(tab_index: i32) -> Box<dyn Widget> {
+    Box::new(TextInput::new("Name").with_tab_index(tab_index))
+}
+
+#[test]
+fn tab_wraps_around_at_either_end() {
+    let mut snap = inputs(vec![input(0), input(0), input(0)]);
+    let mut focus = FocusManager::default();
+
+    let forward = tab_through(&mut snap, &mut focus, 3, false);
+    assert_eq!(forward, [vec![0], vec![1], vec![2]]);
+    let back = tab_through(&mut snap, &mut focus, 4, true);
+    assert_eq!(back, [vec![1], vec![0], vec![2], vec![1]]);
+    let forward = tab_through(&mut snap, &mut focus, 2, false);
+    assert_eq!(forward, [vec![2], vec![0]]);
+}
+
+#[test]
+fn focus_group_is_ordered_on_its_own_and_visited_in_place() {
+    let group = Element::new()
+        .focus_scope(FocusScope::Group)
+        .children(vec![input(2), input(1)]);
+    let mut snap = inputs(vec![input(0), Box::new(group), input(1)]);
+
+    // Positive indices outside the group go first; inside it they only
+    // order the group's own inputs
+    let order = tab_through(&mut snap, &mut FocusManager::default(), 5, false);
+    assert_eq!(order, [vec![2], vec![0], vec![1, 1], vec![1, 0], vec![2]]);
+}
+
+#[test]
+fn focus_trap_keeps_tab_inside() {
+    let dialog = Element::new()
+        .focus_scope(FocusScope::Trap)
+        .children(vec![input(0), input(0)]);
+    let mut snap = inputs(vec![input(0), Box::new(dialog), input(0)]);
+
+    let mut focus = FocusManager::default();
+
+    let forward = tab_through(&mut snap, &mut focus, 3, false);
+    assert_eq!(forward, [vec![1, 0], vec![1, 1], vec![1, 0]]);
+    let back = tab_through(&mut snap, &mut focus, 3, true);
+    assert_eq!(back, [vec![1, 1], vec![1, 0], vec![1, 1]]);
+
+    // Focus clicked outside the trap is pulled back in by the next Tab
+    let pos = snap.root().node_at(&[0]).unwrap().origin() + vec2(4.0, 4.0);
+    snap.root().click(pos, &mut focus);
+    assert_eq!(focus.path(), [0]);
+    snap.root().tab(&mut focus);
+    assert_eq!(focus.path(), [1, 0]);
+}
+
+#[test]
+fn tab_moves_focus_between_inputs() {
+    let root = Element::new()
+        .padding(10.0)
+        .gap(8.0)
+        .child(TextInput::new("First"))
+        .child(TextInput::new("Second"));
+    let mut snap = Snapshot::new(Box::new(root), Theme::default(), 300, 200);
+    snap.render().unwrap();
+    let mut focus = FocusManager::default();
+
+    let focused = |snap: &mut Snapshot| {
+        [0, 1].map(|i| {
+            snap.root()
+                .widget_at::<TextInput>(&[i])
+                .unwrap()
+                .is_focused()
+        })
+    };
+
+    snap.root().tab(&mut focus);
+    assert_eq!(focused(&mut snap), [true, false]);
+
+    snap.root().tab(&mut focus);
+    assert_eq!(focused(&mut snap), [false, true]);
+
+    snap.root().shift_tab(&mut focus);
+    assert_eq!(focused(&mut snap), [true, false]);
+}
+
+#[test]
+fn captured_drag_stays_with_the_pressed_widget() {
+    let log = Log::default();
+    let (mut snap, a, b) = pads(&log);
+    let mut focus = FocusManager::default();
+    let outside = vec2(-20.0, 500.0);
+
+    snap.root().press_pointer(a, &mut focus);
+    assert_eq!(log.borrow().last(), Some(&("a", "down".to_string())));
+    log.borrow_mut().clear();
+
+    snap.root().move_to(b, &mut focus);
+    snap.root().move_to(outside, &mut focus);
+    snap.root().release_pointer(&mut focus);
+
+    let expected = [
+        ("a", format!("move {b}")),
+        ("a", format!("move {outside}")),
+        ("a", format!("up {outside}")),
+    ];
+    assert_eq!(*log.borrow(), expected);
+    assert_eq!(focus.pointer_capture(), None);
+
+    // Released, so moves go to whatever is under the pointer again
+    log.borrow_mut().clear();
+    snap.root().move_to(b, &mut focus);
+    assert_eq!(*log.borrow(), [("b", format!("move {b}"))]);
+}
+
+#[test]
+fn losing_capture_cancels_the_pointer() {
+    let log = Log::default();
+    let (mut snap, a, b) = pads(&log);
+    let mut focus = FocusManager::default();
+
+    snap.root().press_pointer(a, &mut focus);
+    log.borrow_mut().clear();
+    snap.root().blur_window(&mut focus);
+
+    assert_eq!(*log.borrow(), [("a", "cancel".to_string())]);
+    assert_eq!(focus.pointer_capture(), None);
+
+    log.borrow_mut().clear();
+    snap.root().move_to(b, &mut focus);
+    assert_eq!(*log.borrow(), [("b", format!("move {b}"))]);
+}
+
+// A source, then a target that refuses drags and one that takes them
+fn docks(log: &Log) -> (Snapshot, Vec<Vec2>) {
+    let dock = |name, role| {
+        Box::new(Dock {
+            name,
+            role,
+            log: log.clone(),
+        }) as Box<dyn Widget>
+    };
+    row(vec![
+        dock("source", Role::Source),
+        dock("refuses", Role::Refuses),
+        dock("takes", Role::Takes),
+    ])
+}
+
+fn entries(log: &Log) -> Vec<String> {
+    log.borrow()
+        .iter()
+        .map(|(name, entry)| format!("{name} {entry}"))
+        .collect()
+}
+
+#[test]
+fn drag_drops_on_the_target_that_accepts_it() {
+    let log = Log::default();
+    let (mut snap, at) = docks(&log);
+    let mut focus = FocusManager::default();
+
+    snap.root().press_pointer(at[0], &mut focus);
+    assert!(focus.is_dragging());
+    snap.root().move_to(at[1], &mut focus);
+    snap.root().move_to(at[2], &mut focus);
+    snap.root().release_pointer(&mut focus);
+
+    // The drag starts out over its own source
+    let expected = [
+        "source enter",
+        "source leave",
+        "refuses enter",
+        "refuses leave",
+        "takes enter",
+        "takes drop Some(7)",
+        "source end true",
+    ];
+    assert_eq!(entries(&log), expected);
+    assert!(!focus.is_dragging());
+}
+
+#[test]
+fn rejected_drag_is_not_dropped() {
+    let log = Log::default();
+    let (mut snap, at) = docks(&log);
+    let mut focus = FocusManager::default();
+
+    snap.root().drag(at[0], at[1], &mut focus);
+
+    assert_eq!(
+        entries(&log),
+        [
+            "source enter",
+            "source leave",
+            "refuses enter",
+            "refuses leave",
+            "source end false",
+        ]
+    );
+}
+
+#[test]
+fn dropping_an_image_file_sets_the_image_source() {
+    let ferris = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ferris.png").to_string();
+    let (src, set_src) = create_signal(ferris.clone());
+    let image = Image::new(src.clone())
+        .width(100.0)
+        .height(80.0)
+        .on_file_drop(move |path| set_src.set(path.display().to_string()));
+    let mut snap = mount(image);
+    let mut focus = FocusManager::default();
+    let plain = snap.render().unwrap();
+
+    let pos = snap.root().node_at(&[0]).unwrap().origin() + vec2(10.0, 10.0);
+    let notes = PathBuf::from("notes.txt");
+    let photo = PathBuf::from("photos/cat.png");
+
+    // Only image files light it up
+    snap.root()
+        .hover_files(slice::from_ref(&notes), pos, &mut focus);
+    assert_eq!(snap.render().unwrap(), plain);
+    snap.root().cancel_file_hover(&mut focus);
+
+    snap.root()
+        .hover_files(&[notes.clone(), photo.clone()], pos, &mut focus);
+    assert_ne!(snap.render().unwrap(), plain);
+    assert_eq!(src.get(), ferris);
+
+    snap.root().drop_files(&[notes, photo], pos, &mut focus);
+    assert_eq!(src.get(), "photos/cat.png");
+}
@@ -0,0 +1,78 @@
+use cosmic_text::FontSystem;
+use luna::{
+    Element, Renderer, Style, Theme, Vec2, Widget, layout::node::Node, snapshot::Snapshot,
+    style::PointerEvents, vec2, widgets::BuildCtx, windowing::events::FocusManager,
+};
+
+fn hover(root: Element, pt: Vec2) -> Vec<usize> {
+    let mut snap = Snapshot::new(Box::new(root), Theme::default(), 200, 200);
+    snap.render().unwrap();
+    let mut focus = FocusManager::default();
+    snap.root().move_to(pt, &mut focus);
+    snap.root().hover_path().to_vec()
+}
+
+#[test]
+fn overflowing_child_is_hit_outside_its_parent() {
+    let root = Element::new().child(
+        Element::new()
+            .width(50.0)
+            .height(50.0)
+            .child(Element::new().width(150.0).height(20.0)),
+    );
+
+    assert_eq!(hover(root, vec2(120.0, 10.0)), [0, 0]);
+}
+
+// An element that clips its children, like `Scrollable` does
+#[derive(Clone)]
+struct Clip(Element);
+
+impl Widget for Clip {
+    fn build(&self, ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        self.0.build(ctx)
+    }
+
+    fn style(&self) -> Style {
+        self.0.style()
+    }
+
+    fn measure(&self, max_width: f32, theme: &Theme, font_system: &mut FontSystem) -> Vec2 {
+        self.0.measure(max_width, theme, font_system)
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        self.0.paint(node, ren, theme);
+    }
+
+    fn clips_children(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn clipped_children_are_only_hit_inside_their_parent() {
+    let root = Element::new().child(Clip(
+        Element::new()
+            .width(50.0)
+            .height(50.0)
+            .child(Element::new().width(150.0).height(20.0)),
+    ));
+
+    assert_eq!(hover(root.clone(), vec2(40.0, 10.0)), [0, 0]);
+    assert_eq!(hover(root, vec2(120.0, 10.0)), Vec::<usize>::new());
+}
+
+#[test]
+fn pointer_events_none_lets_children_be_hit() {
+    let root = Element::new().child(
+        Element::new()
+            .pointer_events(PointerEvents::None)
+            .padding(20.0)
+            .child(Element::new().width(40.0).height(40.0)),
+    );
+
+    // The padding passes through, the child still takes hits
+    assert_eq!(hover(root.clone(), vec2(5.0, 5.0)), Vec::<usize>::new());
+    assert_eq!(hover(root, vec2(30.0, 30.0)), [0, 0]);
+}
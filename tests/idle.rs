@@ -0,0 +1,108 @@
+use std::time::Duration;
+
+use luna::{
+    Button, Checkbox, Display, Element, FlexDir, Text, TextInput, Theme, Vec2,
+    scheduler::{frame_stats, reset_frame_stats},
+    snapshot::Snapshot,
+    timer::{MockClock, set_clock},
+    vec2,
+    windowing::events::FocusManager,
+};
+
+const FRAME: Duration = Duration::from_millis(16);
+
+struct Harness {
+    snap: Snapshot,
+    clock: MockClock,
+    focus: FocusManager,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let clock = MockClock::new();
+        set_clock(clock.clone());
+
+        let ui = Element::new()
+            .display(Display::Flex)
+            .flex_direction(FlexDir::Column)
+            .gap(8.0)
+            .padding(8.0)
+            .child(Text::new("Nothing to see here".to_string()))
+            .child(Button::label("Hover me"))
+            .child(Checkbox::new_with_label("Tick me"))
+            .child(TextInput::new("Type here"));
+        let mut snap = Snapshot::new(Box::new(ui), Theme::default(), 240, 200);
+        snap.render().unwrap();
+
+        let mut harness = Self {
+            snap,
+            clock,
+            focus: FocusManager::default(),
+        };
+        harness.settle();
+        reset_frame_stats();
+        harness
+    }
+
+    // Runs the loop until nothing is left to draw
+    fn settle(&mut self) {
+        for _ in 0..200 {
+            self.clock.advance(FRAME);
+            if !self.snap.pump().unwrap() {
+                return;
+            }
+        }
+        panic!("the app never went idle");
+    }
+
+    fn center_of(&mut self, child: usize) -> Vec2 {
+        let node = self.snap.root().node_at(&[child]).unwrap();
+        node.origin() + node.cached() / 2.0
+    }
+
+    fn move_to(&mut self, pos: Vec2) {
+        self.snap.root().move_to(pos, &mut self.focus);
+        self.clock.advance(FRAME);
+        self.snap.pump().unwrap();
+    }
+}
+
+#[test]
+fn idle_app_renders_no_frames() {
+    let mut harness = Harness::new();
+
+    for _ in 0..50 {
+        harness.clock.advance(Duration::from_millis(100));
+        assert!(!harness.snap.pump().unwrap());
+    }
+    assert_eq!(frame_stats().frames, 0);
+}
+
+#[test]
+fn pointer_moves_over_static_content_render_no_frames() {
+    let mut harness = Harness::new();
+    let text = harness.center_of(0);
+
+    for dx in 0..20 {
+        harness.move_to(text + vec2(dx as f32 - 10.0, 0.0));
+    }
+    assert_eq!(frame_stats().frames, 0);
+}
+
+#[test]
+fn only_hover_changes_render_frames() {
+    let mut harness = Harness::new();
+    let button = harness.center_of(1);
+
+    // Entering the button starts its hover animation
+    harness.move_to(button);
+    assert!(frame_stats().frames > 0);
+    harness.settle();
+
+    // Moving around inside it changes nothing
+    reset_frame_stats();
+    for dx in 0..10 {
+        harness.move_to(button + vec2(dx as f32 - 5.0, 0.0));
+    }
+    assert_eq!(frame_stats().frames, 0);
+}
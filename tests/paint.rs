@@ -0,0 +1,122 @@
+use std::{cell::Cell, rc::Rc};
+
+use cosmic_text::FontSystem;
+use luna::{
+    Align, Display, FlexDir, Renderer, Style, Text, Theme, Vec2, Vec4,
+    layout::node::Node,
+    scheduler,
+    signals::{ReadSignal, create_signal},
+    snapshot::Snapshot,
+    vec2,
+    widgets::{BuildCtx, Widget},
+};
+
+// A swatch that counts how often it is painted, and can ask for a frame
+// each time like an animation would
+#[derive(Clone)]
+struct Swatch {
+    paints: Rc<Cell<usize>>,
+    animating: bool,
+}
+
+impl Widget for Swatch {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        Vec::new()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        vec2(40.0, 20.0)
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, _theme: &Theme) {
+        self.paints.set(self.paints.get() + 1);
+        if self.animating {
+            scheduler::request_frame();
+        }
+        let rect = node.layout_rect;
+        ren.draw_rect(rect.origin, rect.size, Vec4::new(0.2, 0.6, 0.9, 1.0));
+    }
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|next| Rc::ptr_eq(&self.paints, &next.paints))
+    }
+}
+
+// The swatch above a count it doesn't depend on
+#[derive(Clone)]
+struct Counter {
+    count: ReadSignal<u32>,
+    swatch: Swatch,
+}
+
+impl Widget for Counter {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        vec![
+            Box::new(self.swatch.clone()),
+            Box::new(Text::new(self.count.get().to_string())),
+        ]
+    }
+
+    fn style(&self) -> Style {
+        let mut style = Style {
+            display: Display::Flex,
+            ..Default::default()
+        };
+        style.flex.dir = FlexDir::Column;
+        style.flex.align = Align::Start;
+        style
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        for child in &mut node.children {
+            child.collect(ren, theme);
+        }
+    }
+}
+
+fn counter(count: ReadSignal<u32>, animating: bool) -> (Snapshot, Rc<Cell<usize>>) {
+    let swatch = Swatch {
+        paints: Rc::new(Cell::new(0)),
+        animating,
+    };
+    let paints = swatch.paints.clone();
+    let snap = Snapshot::new(
+        Box::new(Counter { count, swatch }),
+        Theme::default(),
+        120,
+        80,
+    );
+    (snap, paints)
+}
+
+#[test]
+fn unchanged_sibling_is_not_repainted() {
+    let (count, set_count) = create_signal(0);
+    let (mut snap, paints) = counter(count, false);
+    snap.render().unwrap();
+    assert_eq!(paints.get(), 1);
+
+    set_count.set(1);
+    let frame = snap.render().unwrap();
+    assert_eq!(paints.get(), 1);
+
+    // What it drew last frame is still on screen
+    let (count, _) = create_signal(1);
+    let (mut fresh, _) = counter(count, false);
+    assert_eq!(frame, fresh.render().unwrap());
+}
+
+#[test]
+fn widget_asking_for_frames_keeps_painting() {
+    let (count, _) = create_signal(0);
+    let (mut snap, paints) = counter(count, true);
+    snap.render().unwrap();
+    snap.render().unwrap();
+    assert_eq!(paints.get(), 2);
+}
@@ -0,0 +1,177 @@
+use std::{cell::Cell, rc::Rc};
+
+use cosmic_text::FontSystem;
+use luna::{
+    Align, Display, Element, FlexDir, Renderer, Style, Text, TextInput, Theme, Vec2,
+    layout::node::Node,
+    signals::{ReadSignal, create_signal},
+    snapshot::Snapshot,
+    vec2,
+    widgets::{BuildCtx, Widget},
+    windowing::events::FocusManager,
+};
+
+fn column() -> Style {
+    let mut style = Style {
+        display: Display::Flex,
+        ..Default::default()
+    };
+    style.flex.dir = FlexDir::Column;
+    style.flex.align = Align::Start;
+    style
+}
+
+// One keyed row holding a text input per name, in the signal's order
+#[derive(Clone)]
+struct Rows(ReadSignal<Vec<&'static str>>);
+
+impl Widget for Rows {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        self.0
+            .get()
+            .into_iter()
+            .map(|name| {
+                Box::new(
+                    Element::new()
+                        .key(name)
+                        .padding(4.0)
+                        .child(TextInput::new(name)),
+                ) as Box<dyn Widget>
+            })
+            .collect()
+    }
+
+    fn style(&self) -> Style {
+        column()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        for child in &mut node.children {
+            child.collect(ren, theme);
+        }
+    }
+}
+
+#[test]
+fn keyed_reorder_keeps_nodes_and_their_state() {
+    let (order, set_order) = create_signal(vec!["a", "b", "c"]);
+    let mut snap = Snapshot::new(Box::new(Rows(order)), Theme::default(), 200, 200);
+    snap.render().unwrap();
+    let mut focus = FocusManager::default();
+
+    let pos = snap.root().node_at(&[0, 0]).unwrap().origin() + vec2(4.0, 4.0);
+    snap.root().click(pos, &mut focus);
+    snap.root().type_text("hi", &mut focus);
+
+    let ids = |snap: &mut Snapshot, row: usize| {
+        let root = snap.root();
+        (
+            root.node_at(&[row]).unwrap().id,
+            root.node_at(&[row, 0]).unwrap().id,
+        )
+    };
+    let before: Vec<_> = (0..3).map(|row| ids(&mut snap, row)).collect();
+
+    set_order.set(vec!["c", "a", "b"]);
+    snap.render().unwrap();
+
+    assert_eq!(ids(&mut snap, 0), before[2]);
+    assert_eq!(ids(&mut snap, 1), before[0]);
+    assert_eq!(ids(&mut snap, 2), before[1]);
+
+    let input = snap.root().widget_at::<TextInput>(&[1, 0]).unwrap();
+    assert_eq!(input.value, "hi");
+    assert!(input.is_focused());
+    let input = snap.root().widget_at::<TextInput>(&[0, 0]).unwrap();
+    assert_eq!(input.value, "");
+}
+
+// Counts how often it is built and measured
+#[derive(Clone)]
+struct Probe {
+    builds: Rc<Cell<usize>>,
+    measures: Rc<Cell<usize>>,
+}
+
+impl Widget for Probe {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        self.builds.set(self.builds.get() + 1);
+        Vec::new()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        self.measures.set(self.measures.get() + 1);
+        vec2(10.0, 10.0)
+    }
+
+    fn paint(&mut self, _node: &mut Node, _ren: &mut Renderer, _theme: &Theme) {}
+
+    fn same_config(&self, next: &dyn Widget) -> bool {
+        next.as_any()
+            .downcast_ref::<Self>()
+            .is_some_and(|next| Rc::ptr_eq(&self.builds, &next.builds))
+    }
+}
+
+// Shows the count after a probe that doesn't depend on it
+#[derive(Clone)]
+struct Counter {
+    count: ReadSignal<u32>,
+    probe: Probe,
+}
+
+impl Widget for Counter {
+    fn build(&self, _ctx: &mut BuildCtx) -> Vec<Box<dyn Widget>> {
+        vec![
+            Box::new(Element::new().padding(2.0).child(self.probe.clone())),
+            Box::new(Text::new(self.count.get().to_string())),
+        ]
+    }
+
+    // Nothing stretches to the text's width, so the probe's box doesn't
+    // depend on it
+    fn style(&self) -> Style {
+        column()
+    }
+
+    fn measure(&self, _max_width: f32, _theme: &Theme, _fs: &mut FontSystem) -> Vec2 {
+        Vec2::ZERO
+    }
+
+    fn paint(&mut self, node: &mut Node, ren: &mut Renderer, theme: &Theme) {
+        for child in &mut node.children {
+            child.collect(ren, theme);
+        }
+    }
+}
+
+#[test]
+fn rebuild_leaves_unchanged_children_alone() {
+    let (count, set_count) = create_signal(0);
+    let probe = Probe {
+        builds: Rc::new(Cell::new(0)),
+        measures: Rc::new(Cell::new(0)),
+    };
+    let counter = Counter {
+        count,
+        probe: probe.clone(),
+    };
+    let mut snap = Snapshot::new(Box::new(counter), Theme::default(), 200, 100);
+    snap.render().unwrap();
+    let counts = |probe: &Probe| (probe.builds.get(), probe.measures.get());
+    let before = counts(&probe);
+    let text_id = snap.root().node_at(&[1]).unwrap().id;
+
+    set_count.set(1);
+    snap.render().unwrap();
+
+    let text = snap.root().node_at(&[1]).unwrap();
+    assert_eq!(text.id, text_id);
+    let text = snap.root().widget_at::<Text>(&[1]).unwrap();
+    assert_eq!(text.content.get_untracked(), "1");
+    assert_eq!(counts(&probe), before);
+}
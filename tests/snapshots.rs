@@ -0,0 +1,167 @@
+use std::path::PathBuf;
+
+use luna::{
+    Align, Button, Checkbox, Display, Element, FlexDir, Image, Justify, Text, TextInput, Theme,
+    Vec4,
+    layout::Rect,
+    renderer::primatives::{CircleInstance, LineInstance},
+    snapshot::{Snapshot, SnapshotOutcome, UPDATE_ENV},
+    vec2,
+    widgets::{Canvas, Scrollable, Widget, image::ImageFit},
+};
+
+const RED: Vec4 = Vec4::new(0.9, 0.2, 0.2, 1.0);
+const GREEN: Vec4 = Vec4::new(0.2, 0.8, 0.3, 1.0);
+const BLUE: Vec4 = Vec4::new(0.2, 0.4, 0.9, 1.0);
+
+// The same goldens `examples/snapshots.rs` checks; re-record them with
+// `LUNA_UPDATE_SNAPSHOTS=1 cargo test --test snapshots`
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(format!("assets/snapshots/{name}.png"))
+}
+
+fn assert_golden(name: &str, widget: impl Widget + 'static, (width, height): (u32, u32)) {
+    let mut snapshot =
+        Snapshot::new(Box::new(widget), Theme::default(), width, height).with_tolerance(2);
+    match snapshot.assert_matches(golden(name)) {
+        Ok(SnapshotOutcome::Matched) => {}
+        Ok(SnapshotOutcome::Recorded) => eprintln!("recorded {name}"),
+        Err(e) => panic!("{e}"),
+    }
+}
+
+fn buttons(n: usize) -> Vec<Box<dyn Widget>> {
+    (1..=n)
+        .map(|i| Box::new(Button::label(&format!("Button {i}"))) as Box<dyn Widget>)
+        .collect()
+}
+
+#[test]
+fn button() {
+    assert_golden("button", Button::label("Click me"), (160, 60));
+}
+
+#[test]
+fn checkbox() {
+    assert_golden("checkbox", Checkbox::new_with_label("I agree"), (160, 40));
+}
+
+#[test]
+fn text_input() {
+    assert_golden("text_input", TextInput::new("e.g. Jane Doe"), (200, 60));
+}
+
+#[test]
+fn image() {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets/ferris.png");
+    let image = Image::new(path.to_str().unwrap())
+        .fit(ImageFit::Contain)
+        .width(128.0)
+        .height(96.0);
+    assert_golden("image", image, (128, 96));
+}
+
+#[test]
+fn scrollable() {
+    let list = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Column)
+        .gap(8.0)
+        .padding(12.0)
+        .children(buttons(20));
+    assert_golden("scrollable", Scrollable::new(list), (160, 200));
+}
+
+#[test]
+fn nested_clip() {
+    let canvas = Canvas::new(|ren| {
+        ren.push_scissor_rect(Rect::new(vec2(20.0, 20.0), vec2(120.0, 120.0)));
+        ren.draw_rect(vec2(0.0, 0.0), vec2(200.0, 200.0), RED);
+
+        ren.push_scissor_rect(Rect::new(vec2(80.0, 80.0), vec2(100.0, 100.0)));
+        ren.draw_rect(vec2(0.0, 0.0), vec2(200.0, 200.0), BLUE);
+        ren.draw_text("clipped", vec2(70.0, 90.0), Vec4::ONE, 16.0);
+        ren.pop_scissor_rect();
+
+        ren.draw_rect(vec2(0.0, 60.0), vec2(200.0, 10.0), GREEN);
+        ren.pop_scissor_rect();
+
+        ren.draw_rect(vec2(0.0, 170.0), vec2(200.0, 10.0), GREEN);
+    });
+    assert_golden("nested_clip", canvas, (200, 200));
+}
+
+#[test]
+fn z_order() {
+    let mut slots = None;
+    let canvas = Canvas::new(move |ren| {
+        let (line, circle) = *slots.get_or_insert_with(|| (ren.alloc_line(), ren.alloc_circle()));
+
+        // Raised above everything painted after it
+        ren.push_z_index(1);
+        ren.draw_rounded_rect(vec2(110.0, 20.0), vec2(60.0, 60.0), 8.0, BLUE);
+        ren.pop_z_index();
+
+        ren.update_line(
+            line,
+            LineInstance {
+                a: [10.0, 50.0],
+                b: [190.0, 50.0],
+                color: GREEN.to_array(),
+                half_width: 4.0,
+                ..Default::default()
+            },
+        );
+        ren.draw_rect(vec2(40.0, 30.0), vec2(50.0, 40.0), RED);
+        ren.update_circle(
+            circle,
+            CircleInstance {
+                center: [90.0, 70.0],
+                radius: 20.0,
+                color: [1.0, 1.0, 1.0, 1.0],
+                ..Default::default()
+            },
+        );
+        ren.draw_text("top", vec2(70.0, 60.0), Vec4::new(0.0, 0.0, 0.0, 1.0), 16.0);
+    });
+    assert_golden("z_order", canvas, (200, 100));
+}
+
+#[test]
+fn element_flex() {
+    let row = Element::new()
+        .display(Display::Flex)
+        .flex_direction(FlexDir::Row)
+        .justify_content(Justify::SpaceBetween)
+        .align_items(Align::Center)
+        .padding(12.0)
+        .background_color(Theme::default().color.surface)
+        .child(Text::new("Label"))
+        .children(buttons(2));
+    assert_golden("element_flex", row, (320, 60));
+}
+
+#[test]
+fn element_grid() {
+    let grid = Element::new()
+        .display(Display::Grid)
+        .background_color(Theme::default().color.surface)
+        .padding(12.0)
+        .gap(8.0)
+        .grid_cols(3)
+        .grid_row_height(32.0)
+        .children(buttons(6));
+    assert_golden("element_grid", grid, (360, 110));
+}
+
+#[test]
+fn missing_golden_is_an_error() {
+    if std::env::var(UPDATE_ENV).is_ok_and(|v| v == "1") {
+        return;
+    }
+
+    let missing = golden("does_not_exist");
+    let mut snapshot = Snapshot::new(Box::new(Button::label("Nope")), Theme::default(), 80, 40);
+    assert!(snapshot.assert_matches(&missing).is_err());
+    assert!(!missing.exists());
+}